@@ -1,5 +1,9 @@
+use std::collections::HashSet;
 use std::rc::Rc;
 
+#[cfg(feature = "python")]
+use pyo3::{pyclass, pymethods};
+
 use crate::formatting::FormatSpec;
 use crate::{
     builtins::BUILTINS,
@@ -7,11 +11,11 @@ use crate::{
     types::LogicOp,
 };
 
-use super::low;
 use super::scope::{LocalScope, Scope, SubScope};
+use super::{lint, low};
 use crate::error::{Action, Error, Taggable, Tagged};
-use crate::types::Key;
 use crate::types::{BinOp, EagerOp, Res, UnOp};
+use crate::types::{Key, Map};
 use crate::Object;
 
 // Utility
@@ -36,6 +40,23 @@ impl<T: Lower> Lower for Option<T> {
     }
 }
 
+/// Return an error pointing to the second occurrence of any name that is
+/// bound more than once.
+fn check_unique_names(names: Vec<&Tagged<Key>>) -> Res<()> {
+    let mut seen = HashSet::new();
+    for name in names {
+        // The conventional placeholder name may be reused freely.
+        if name.as_ref().as_str() == "_" {
+            continue;
+        }
+        if !seen.insert(*name.as_ref()) {
+            return Err(Error::new(Syntax::DuplicateBinding(*name.as_ref()))
+                .tag(name.span(), Action::Parse));
+        }
+    }
+    Ok(())
+}
+
 // ListBindingElement
 // ----------------------------------------------------------------
 
@@ -67,6 +88,14 @@ impl ListBindingElement {
             Self::Slurp => {}
         }
     }
+
+    fn bound_names<'a>(&'a self, names: &mut Vec<&'a Tagged<Key>>) {
+        match self {
+            Self::Binding { binding, .. } => binding.bound_names(names),
+            Self::SlurpTo(name) => names.push(name),
+            Self::Slurp => {}
+        }
+    }
 }
 
 // MapBindingElement
@@ -100,12 +129,24 @@ impl MapBindingElement {
             }
         }
     }
+
+    fn bound_names<'a>(&'a self, names: &mut Vec<&'a Tagged<Key>>) {
+        match self {
+            Self::Binding { binding, .. } => binding.bound_names(names),
+            Self::SlurpTo(name) => names.push(name),
+        }
+    }
 }
 
 // ListBinding
 // ----------------------------------------------------------------
 
 /// A list binding destructures a list into a list of patterns.
+///
+/// At most one slurp (`...` or `...name`) may appear, but it can be in any
+/// position. Elements before the slurp bind from the front of the list and
+/// elements after it bind from the back, so `[a, ...rest, b]` is unambiguous.
+/// A second slurp is rejected during lowering with [`Syntax::MultiSlurp`].
 #[derive(Debug, Clone, PartialEq)]
 pub struct ListBinding(Vec<Tagged<ListBindingElement>>);
 
@@ -114,11 +155,21 @@ impl ListBinding {
         Self(elements)
     }
 
+    pub fn elements(&self) -> &[Tagged<ListBindingElement>] {
+        &self.0
+    }
+
     fn announce_bindings(&self, scope: &mut dyn SubScope) {
         for element in &self.0 {
             element.announce_bindings(scope);
         }
     }
+
+    fn bound_names<'a>(&'a self, names: &mut Vec<&'a Tagged<Key>>) {
+        for element in &self.0 {
+            element.bound_names(names);
+        }
+    }
 }
 
 impl Lower for ListBinding {
@@ -131,6 +182,12 @@ impl Lower for ListBinding {
             let (element, span) = element.decompose();
             match element {
                 ListBindingElement::Binding { binding, default } => {
+                    // Before the slurp, bindings without defaults may not
+                    // follow bindings with defaults.
+                    if default.is_none() && retval.slurp.is_none() && retval.def_front > 0 {
+                        return Err(Error::new(Syntax::DefaultSequence).tag(span, Action::Parse));
+                    }
+
                     match (default.is_some(), retval.slurp) {
                         (true, Some(_)) => {
                             retval.def_back += 1;
@@ -189,11 +246,21 @@ impl MapBinding {
         Self(elements)
     }
 
+    pub fn elements(&self) -> &[Tagged<MapBindingElement>] {
+        &self.0
+    }
+
     fn announce_bindings(&self, scope: &mut dyn SubScope) {
         for element in &self.0 {
             element.announce_bindings(scope);
         }
     }
+
+    fn bound_names<'a>(&'a self, names: &mut Vec<&'a Tagged<Key>>) {
+        for element in &self.0 {
+            element.bound_names(names);
+        }
+    }
 }
 
 impl Lower for MapBinding {
@@ -246,9 +313,14 @@ impl Lower for MapBinding {
 /// A binding comes in three flavors: identifiers (which don't do any
 /// destructuring), and list and map bindings, which destructures lists and maps
 /// respectively.
+///
+/// An identifier may also be declared `final`, in which case it behaves like
+/// an ordinary identifier binding but may not be shadowed by any later binding
+/// in its scope. This is checked by [`crate::ast::lint::check_final`].
 #[derive(Debug, Clone, PartialEq)]
 pub enum Binding {
     Identifier(Tagged<Key>),
+    Final(Tagged<Key>),
     List(Tagged<ListBinding>),
     Map(Tagged<MapBinding>),
 }
@@ -256,7 +328,7 @@ pub enum Binding {
 impl Binding {
     fn announce_bindings(&self, scope: &mut dyn SubScope) {
         match self {
-            Self::Identifier(key) => {
+            Self::Identifier(key) | Self::Final(key) => {
                 scope.announce_binding(*key.as_ref());
             }
             Self::List(binding) => {
@@ -267,6 +339,21 @@ impl Binding {
             }
         }
     }
+
+    pub(crate) fn bound_names<'a>(&'a self, names: &mut Vec<&'a Tagged<Key>>) {
+        match self {
+            Self::Identifier(key) | Self::Final(key) => names.push(key),
+            Self::List(binding) => binding.bound_names(names),
+            Self::Map(binding) => binding.bound_names(names),
+        }
+    }
+
+    /// Check that no name is bound more than once in this pattern.
+    fn check_unique_names(&self) -> Res<()> {
+        let mut names = Vec::new();
+        self.bound_names(&mut names);
+        check_unique_names(names)
+    }
 }
 
 impl Lower for Binding {
@@ -274,7 +361,7 @@ impl Lower for Binding {
 
     fn lower(self, scope: &mut dyn Scope) -> Res<Self::Target> {
         match self {
-            Self::Identifier(key) => {
+            Self::Identifier(key) | Self::Final(key) => {
                 match scope.lookup_store(*key.as_ref()) {
                     None => Err(Error::new(Reason::Unbound(*key.as_ref()))
                         .tag(key.span(), Action::LookupName)),
@@ -329,13 +416,14 @@ impl Lower for StringElement {
 /// A list element is anything that is legal inside a list literal:
 /// - singleton elements
 /// - splatted expressions
-/// - iterated elements
+/// - iterated elements (optionally with an index binding)
 /// - conditional elements
 #[derive(Debug, Clone, PartialEq)]
 pub enum ListElement {
     Singleton(Tagged<Expr>),
     Splat(Tagged<Expr>),
     Loop {
+        index: Option<Tagged<Binding>>,
         binding: Tagged<Binding>,
         iterable: Tagged<Expr>,
         element: Box<Tagged<ListElement>>,
@@ -358,18 +446,31 @@ impl Lower for ListElement {
                 element: Box::new(element.lower(scope)?),
             }),
             Self::Loop {
+                index,
                 binding,
                 iterable,
                 element,
             } => {
+                let mut names = Vec::new();
+                if let Some(index) = &index {
+                    index.bound_names(&mut names);
+                }
+                binding.bound_names(&mut names);
+                check_unique_names(names)?;
+
                 let mut subscope = LocalScope::new(scope);
+                if let Some(index) = &index {
+                    index.announce_bindings(&mut subscope);
+                }
                 binding.announce_bindings(&mut subscope);
 
                 let new_iterable = iterable.lower(&mut subscope)?;
+                let new_index = index.map(|x| x.lower(&mut subscope)).transpose()?;
                 let new_binding = binding.lower(&mut subscope)?;
                 let new_element = element.lower(&mut subscope)?;
 
                 Ok(low::ListElement::Loop {
+                    index: new_index.map(Box::new),
                     binding: new_binding,
                     slots: subscope.catalog(),
                     iterable: new_iterable,
@@ -396,6 +497,7 @@ pub enum MapElement {
     },
     Splat(Tagged<Expr>),
     Loop {
+        index: Option<Tagged<Binding>>,
         binding: Tagged<Binding>,
         iterable: Tagged<Expr>,
         element: Box<Tagged<MapElement>>,
@@ -421,18 +523,31 @@ impl Lower for MapElement {
                 element: Box::new(element.lower(scope)?),
             }),
             Self::Loop {
+                index,
                 binding,
                 iterable,
                 element,
             } => {
+                let mut names = Vec::new();
+                if let Some(index) = &index {
+                    index.bound_names(&mut names);
+                }
+                binding.bound_names(&mut names);
+                check_unique_names(names)?;
+
                 let mut subscope = LocalScope::new(scope);
+                if let Some(index) = &index {
+                    index.announce_bindings(&mut subscope);
+                }
                 binding.announce_bindings(&mut subscope);
 
                 let new_iterable = iterable.lower(&mut subscope)?;
+                let new_index = index.map(|x| x.lower(&mut subscope)).transpose()?;
                 let new_binding = binding.lower(&mut subscope)?;
                 let new_element = element.lower(&mut subscope)?;
 
                 Ok(low::MapElement::Loop {
+                    index: new_index.map(Box::new),
                     binding: new_binding,
                     slots: subscope.catalog(),
                     iterable: new_iterable,
@@ -502,6 +617,16 @@ impl Transform {
         Transform::BinOp(BinOp::Eager(EagerOp::Index).tag(loc), Box::new(subscript))
     }
 
+    /// Construct an attribute access transform.
+    ///
+    /// * `loc` - the location of the dot in the buffer.
+    pub fn access<U>(attribute: Tagged<Expr>, loc: U) -> Transform
+    where
+        Span: From<U>,
+    {
+        Transform::BinOp(BinOp::Eager(EagerOp::Access).tag(loc), Box::new(attribute))
+    }
+
     /// Construct an exponentiation transform.
     ///
     /// * `loc` - the location of the indexing operator in the buffer.
@@ -641,6 +766,26 @@ impl Transform {
         Transform::BinOp(BinOp::Eager(EagerOp::Contains).tag(loc), Box::new(rhs))
     }
 
+    /// Construct a reverse containment check transform.
+    ///
+    /// * `loc` - the location of the 'in' operator in the buffer.
+    pub fn is_in<U>(rhs: Tagged<Expr>, loc: U) -> Transform
+    where
+        Span: From<U>,
+    {
+        Transform::BinOp(BinOp::Eager(EagerOp::In).tag(loc), Box::new(rhs))
+    }
+
+    /// Construct a negated reverse containment check transform.
+    ///
+    /// * `loc` - the location of the 'not in' operator in the buffer.
+    pub fn not_in<U>(rhs: Tagged<Expr>, loc: U) -> Transform
+    where
+        Span: From<U>,
+    {
+        Transform::BinOp(BinOp::Eager(EagerOp::NotIn).tag(loc), Box::new(rhs))
+    }
+
     /// Construct a logical conjunction transform.
     ///
     /// * `loc` - the location of the indexing operator in the buffer.
@@ -724,6 +869,17 @@ pub enum Expr {
         transform: Transform,
     },
 
+    /// A chain of two or more comparisons, such as `a < b <= c`. This is
+    /// equivalent to `a < b and b <= c`, except that `b` is evaluated only
+    /// once. Single comparisons are represented as [`Expr::Transformed`].
+    Chain {
+        /// The leftmost operand.
+        operand: Box<Tagged<Expr>>,
+
+        /// The comparison operators, each with its right operand.
+        links: Vec<(Tagged<EagerOp>, Tagged<Expr>)>,
+    },
+
     /// A function definition.
     Function {
         /// Positional function parameters.
@@ -744,6 +900,40 @@ pub enum Expr {
     },
 }
 
+impl Expr {
+    /// Form the body of a module: a let block whose value is a map of all the
+    /// names bound by it (except the placeholder `_`).
+    pub fn module(bindings: Vec<(Tagged<Binding>, Tagged<Expr>)>) -> Self {
+        let mut names = Vec::new();
+        for (binding, _) in bindings.iter() {
+            binding.bound_names(&mut names);
+        }
+
+        let mut seen = HashSet::new();
+        let elements = names
+            .into_iter()
+            .filter(|name| name.as_ref().as_str() != "_" && seen.insert(*name.as_ref()))
+            .map(|name| {
+                MapElement::Singleton {
+                    key: (*name).map(Object::from).map(Expr::Literal),
+                    value: Expr::Identifier(*name).tag(name),
+                }
+                .tag(name)
+            })
+            .collect();
+
+        let span = match (bindings.first(), bindings.last()) {
+            (Some((first, _)), Some((_, last))) => Span::from(first.span()..last.span()),
+            _ => Span::from(0),
+        };
+
+        Expr::Let {
+            bindings,
+            expression: Box::new(Expr::Map(elements).tag(span)),
+        }
+    }
+}
+
 impl Tagged<Expr> {
     /// Form a sum expression from two terms.
     ///
@@ -855,6 +1045,26 @@ impl Tagged<Expr> {
         self.transform(Transform::not_equal(rhs, l))
     }
 
+    /// Form a reverse containment check expression from two operands.
+    ///
+    /// * `loc` - the location of the operator in the buffer.
+    pub fn is_in<U>(self, rhs: Tagged<Expr>, l: U) -> Expr
+    where
+        Span: From<U>,
+    {
+        self.transform(Transform::is_in(rhs, l))
+    }
+
+    /// Form a negated reverse containment check expression from two operands.
+    ///
+    /// * `loc` - the location of the operator in the buffer.
+    pub fn not_in<U>(self, rhs: Tagged<Expr>, l: U) -> Expr
+    where
+        Span: From<U>,
+    {
+        self.transform(Transform::not_in(rhs, l))
+    }
+
     /// Form a logical conjunction expression from two operands.
     ///
     /// * `loc` - the location of the operator in the buffer.
@@ -895,6 +1105,16 @@ impl Tagged<Expr> {
         self.transform(Transform::index(subscript, l))
     }
 
+    /// Form an attribute access expression from two operands.
+    ///
+    /// * `loc` - the location of the dot in the buffer.
+    pub fn access<U>(self, attribute: Tagged<Expr>, l: U) -> Expr
+    where
+        Span: From<U>,
+    {
+        self.transform(Transform::access(attribute, l))
+    }
+
     /// Arithmetically negate this expression.
     ///
     /// * `loc` - the location of the operator in the buffer.
@@ -926,6 +1146,15 @@ impl Tagged<Expr> {
         self.transform(Transform::FunCall(args.tag(loc)))
     }
 
+    /// Form a comparison chain from this operand and a sequence of comparison
+    /// operators (with locations) and right operands.
+    pub fn chain(self, links: Vec<(Tagged<EagerOp>, Tagged<Expr>)>) -> Expr {
+        Expr::Chain {
+            operand: Box::new(self),
+            links,
+        }
+    }
+
     /// Form the combined transformed expression from this operand and a transform.
     fn transform(self, op: Transform) -> Expr {
         Expr::Transformed {
@@ -968,10 +1197,13 @@ impl Lower for Expr {
                 Ok(low::Expr::String(new_elements))
             }
             Self::Identifier(name) => match scope.lookup_load(*name.as_ref(), false) {
-                None => match BUILTINS.0.get(name.as_str()) {
-                    Some(index) => Ok(low::Expr::Builtin(*index)),
-                    None => Err(Error::new(Reason::Unbound(*name.as_ref()))
-                        .tag(name.span(), Action::LookupName)),
+                None => match scope.lookup_builtin(*name.as_ref()) {
+                    Some(obj) => Ok(low::Expr::Constant(scope.new_constant(obj))),
+                    None => match BUILTINS.0.get(name.as_str()) {
+                        Some(index) => Ok(low::Expr::Builtin(*index)),
+                        None => Err(Error::new(Reason::Unbound(*name.as_ref()))
+                            .tag(name.span(), Action::LookupName)),
+                    },
                 },
                 Some(slot) => Ok(low::Expr::Slot(slot)),
             },
@@ -983,6 +1215,22 @@ impl Lower for Expr {
                 Ok(low::Expr::List(new_elements))
             }
             Self::Map(elements) => {
+                // Check for statically known duplicate keys. Dynamic keys, as
+                // well as keys in conditional and iterated elements, are exempt.
+                let mut seen = HashSet::new();
+                for element in elements.iter() {
+                    if let MapElement::Singleton { key, .. } = element.as_ref() {
+                        if let Expr::Literal(obj) = key.as_ref() {
+                            if let Some(k) = obj.get_key() {
+                                if !seen.insert(k) {
+                                    return Err(Error::new(Syntax::DuplicateKey(k))
+                                        .tag(key.span(), Action::Parse));
+                                }
+                            }
+                        }
+                    }
+                }
+
                 let mut new_elements = Vec::new();
                 for element in elements {
                     new_elements.push(element.lower(scope)?);
@@ -990,11 +1238,42 @@ impl Lower for Expr {
                 Ok(low::Expr::Map(new_elements))
             }
             Self::Let {
-                bindings,
-                expression,
+                mut bindings,
+                mut expression,
             } => {
+                // Bindings are sequential: a binding that rebinds a name bound
+                // earlier in the same block shadows it, so it and all
+                // subsequent bindings are moved to a nested block. Otherwise
+                // they would share a slot, and closures capturing the earlier
+                // binding would observe the later value.
+                let mut seen = HashSet::new();
+                let split = bindings.iter().position(|(binding, _)| {
+                    let mut names = Vec::new();
+                    binding.bound_names(&mut names);
+                    let names: Vec<Key> = names
+                        .into_iter()
+                        .map(|name| *name.as_ref())
+                        .filter(|name| name.as_str() != "_")
+                        .collect();
+                    let shadows = names.iter().any(|name| seen.contains(name));
+                    seen.extend(names);
+                    shadows
+                });
+                if let Some(index) = split.filter(|index| *index > 0) {
+                    let rest = bindings.split_off(index);
+                    let span = rest[0].0.span()..expression.span();
+                    expression = Box::new(
+                        Expr::Let {
+                            bindings: rest,
+                            expression,
+                        }
+                        .tag(span),
+                    );
+                }
+
                 let mut builder = low::LetBuilder::new(scope);
                 for (binding, _) in bindings.iter() {
+                    binding.check_unique_names()?;
                     binding.announce_bindings(builder.scope());
                 }
 
@@ -1017,6 +1296,17 @@ impl Lower for Expr {
                     transform,
                 })
             }
+            Self::Chain { operand, links } => {
+                let operand = operand.lower(scope)?;
+                let mut new_links = Vec::with_capacity(links.len());
+                for (op, expr) in links {
+                    new_links.push((op, expr.lower(scope)?));
+                }
+                Ok(low::Expr::Chain {
+                    operand: Box::new(operand),
+                    links: new_links,
+                })
+            }
             Self::Branch {
                 condition,
                 true_branch,
@@ -1031,6 +1321,13 @@ impl Lower for Expr {
                 keywords,
                 expression,
             } => {
+                let mut names = Vec::new();
+                positional.bound_names(&mut names);
+                if let Some(kw) = &keywords {
+                    kw.bound_names(&mut names);
+                }
+                check_unique_names(names)?;
+
                 let mut builder = low::FunctionBuilder::new(Some(scope));
 
                 positional.announce_bindings(builder.scope());
@@ -1056,7 +1353,7 @@ impl Lower for Expr {
 // ----------------------------------------------------------------
 
 /// A top-level AST node, only legal at the top level of a file.
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum TopLevel {
     /// Import an object by loading another file and binding it to a pattern.
     Import(Tagged<String>, Tagged<Binding>),
@@ -1067,7 +1364,7 @@ pub enum TopLevel {
 
 /// The complete AST node of a file, consisting of a number of top-level
 /// statements followed by an expression.
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct File {
     /// Top-level statements.
     pub statements: Vec<TopLevel>,
@@ -1077,12 +1374,71 @@ pub struct File {
 }
 
 impl File {
+    /// Iterate over the paths of all top-level imports, in order.
+    pub fn imports(&self) -> impl Iterator<Item = &str> {
+        self.statements.iter().map(|statement| {
+            let TopLevel::Import(path, _) = statement;
+            path.as_str()
+        })
+    }
+
+    /// Bind additional names to the given objects. The bindings are visible
+    /// in the final expression and shadow imported names.
+    pub fn with_bindings(self, bindings: impl IntoIterator<Item = (Key, Object)>) -> Self {
+        let span = self.expression.span();
+        let bindings: Vec<_> = bindings
+            .into_iter()
+            .map(|(key, value)| {
+                (
+                    Binding::Identifier(key.tag(span)).tag(span),
+                    Expr::Literal(value).tag(span),
+                )
+            })
+            .collect();
+
+        if bindings.is_empty() {
+            return self;
+        }
+
+        Self {
+            statements: self.statements,
+            expression: Expr::Let {
+                bindings,
+                expression: Box::new(self.expression),
+            }
+            .tag(span),
+        }
+    }
+
+    /// Lower the file to a function taking no arguments, ready for compilation.
     pub fn lower(self) -> Res<low::Function> {
+        self.lower_with_builtins(Map::new())
+    }
+
+    /// Lower the file like [`File::lower`], with extra builtins. Unbound names
+    /// are looked up in `builtins` before the standard builtins, so these may
+    /// shadow them, but any binding in the file shadows both.
+    pub fn lower_with_builtins(self, builtins: Map) -> Res<low::Function> {
+        self.lower_impl(builtins, false)
+    }
+
+    /// Lower the file like [`File::lower_with_builtins`], but so that the
+    /// elements of a top-level list or map are written to the output sink of
+    /// the virtual machine as they are produced.
+    pub(crate) fn lower_streaming(self, builtins: Map) -> Res<low::Function> {
+        self.lower_impl(builtins, true)
+    }
+
+    fn lower_impl(self, builtins: Map, stream: bool) -> Res<low::Function> {
+        lint::check_final(&self)?;
+
         let mut outer = low::FunctionBuilder::new(None);
+        outer.scope().set_builtins(builtins);
 
         let mut import_builder = low::ImportsBuilder::new(outer.scope());
         for statement in self.statements.iter() {
             let TopLevel::Import(_, binding) = statement;
+            binding.check_unique_names()?;
             binding.announce_bindings(import_builder.scope());
         }
         for statement in self.statements.into_iter() {
@@ -1094,6 +1450,9 @@ impl File {
         let mut inner_builder = low::FunctionBuilder::new(Some(import_builder.scope()));
         let expr = self.expression.lower(inner_builder.scope())?;
         inner_builder.expression(expr);
+        if stream {
+            inner_builder.stream();
+        }
         let inner_expr = low::Expr::Func(inner_builder.finalize()).tag(0);
 
         import_builder.expression(inner_expr);
@@ -1109,3 +1468,31 @@ impl File {
         Ok(outer.finalize())
     }
 }
+
+#[cfg(feature = "python")]
+#[pyclass(unsendable, name = "File")]
+#[derive(Clone)]
+/// Parsed Gold file that can be inspected by Python code.
+pub struct PyFile(File);
+
+#[cfg(feature = "python")]
+#[pymethods]
+impl PyFile {
+    /// The paths of all top-level imports, in order.
+    #[getter]
+    fn imports(&self) -> Vec<String> {
+        self.0.imports().map(str::to_owned).collect()
+    }
+
+    /// The file formatted as canonical source code.
+    fn __str__(&self) -> String {
+        super::print::file(&self.0)
+    }
+}
+
+#[cfg(feature = "python")]
+impl From<File> for PyFile {
+    fn from(value: File) -> Self {
+        Self(value)
+    }
+}
@@ -0,0 +1,345 @@
+//! Lint checks on high-level ASTs.
+//!
+//! Currently the only check is for shadowing: binding a name that is already
+//! bound, either earlier in the same let-block or in an enclosing scope. Names
+//! of builtins and the placeholder name `_` may be rebound freely.
+//!
+//! The same traversal also enforces `final` bindings: shadowing one of those
+//! is an error rather than a warning, see [`check_final`].
+
+use std::collections::HashMap;
+
+use crate::error::{Action, Error, Syntax, Taggable, Tagged, Warning};
+use crate::types::{Key, Res};
+
+use super::high::{
+    ArgElement, Binding, Expr, File, ListBinding, ListBindingElement, ListElement, MapBinding,
+    MapBindingElement, MapElement, StringElement, TopLevel, Transform,
+};
+
+struct Linter {
+    /// Names bound in each enclosing scope, innermost last, together with
+    /// whether they were declared `final`.
+    scopes: Vec<HashMap<Key, bool>>,
+    warnings: Vec<Tagged<Warning>>,
+
+    /// The first shadowing of a `final` name, if any.
+    error: Option<Error>,
+}
+
+impl Linter {
+    fn new() -> Self {
+        Self {
+            scopes: vec![HashMap::new()],
+            warnings: Vec::new(),
+            error: None,
+        }
+    }
+
+    fn scoped(&mut self, f: impl FnOnce(&mut Self)) {
+        self.scopes.push(HashMap::new());
+        f(self);
+        self.scopes.pop();
+    }
+
+    fn bind_name(&mut self, name: &Tagged<Key>, is_final: bool) {
+        let key = *name.as_ref();
+        if key.as_str() == "_" {
+            return;
+        }
+        let earlier: Vec<bool> = self
+            .scopes
+            .iter()
+            .filter_map(|scope| scope.get(&key).copied())
+            .collect();
+        if !earlier.is_empty() {
+            self.warnings.push(Warning::Shadowing(key).tag(name.span()));
+        }
+        if earlier.contains(&true) && self.error.is_none() {
+            self.error = Some(Error::new(Syntax::ShadowFinal(key)).tag(name.span(), Action::Parse));
+        }
+        self.scopes.last_mut().unwrap().insert(key, is_final);
+    }
+
+    fn list_binding(&mut self, binding: &ListBinding) {
+        for element in binding.elements() {
+            match element.as_ref() {
+                ListBindingElement::Binding { binding, default } => {
+                    if let Some(default) = default {
+                        self.expression(default);
+                    }
+                    self.binding(binding);
+                }
+                ListBindingElement::SlurpTo(name) => self.bind_name(name, false),
+                ListBindingElement::Slurp => {}
+            }
+        }
+    }
+
+    fn map_binding(&mut self, binding: &MapBinding) {
+        for element in binding.elements() {
+            match element.as_ref() {
+                MapBindingElement::Binding {
+                    binding, default, ..
+                } => {
+                    if let Some(default) = default {
+                        self.expression(default);
+                    }
+                    self.binding(binding);
+                }
+                MapBindingElement::SlurpTo(name) => self.bind_name(name, false),
+            }
+        }
+    }
+
+    fn binding(&mut self, binding: &Binding) {
+        match binding {
+            Binding::Identifier(name) => self.bind_name(name, false),
+            Binding::Final(name) => self.bind_name(name, true),
+            Binding::List(binding) => self.list_binding(binding),
+            Binding::Map(binding) => self.map_binding(binding),
+        }
+    }
+
+    fn list_element(&mut self, element: &ListElement) {
+        match element {
+            ListElement::Singleton(expr) | ListElement::Splat(expr) => self.expression(expr),
+            ListElement::Cond { condition, element } => {
+                self.expression(condition);
+                self.list_element(element);
+            }
+            ListElement::Loop {
+                index,
+                binding,
+                iterable,
+                element,
+            } => {
+                self.expression(iterable);
+                self.scoped(|this| {
+                    if let Some(index) = index {
+                        this.binding(index);
+                    }
+                    this.binding(binding);
+                    this.list_element(element);
+                });
+            }
+        }
+    }
+
+    fn map_element(&mut self, element: &MapElement) {
+        match element {
+            MapElement::Singleton { key, value } => {
+                self.expression(key);
+                self.expression(value);
+            }
+            MapElement::Splat(expr) => self.expression(expr),
+            MapElement::Cond { condition, element } => {
+                self.expression(condition);
+                self.map_element(element);
+            }
+            MapElement::Loop {
+                index,
+                binding,
+                iterable,
+                element,
+            } => {
+                self.expression(iterable);
+                self.scoped(|this| {
+                    if let Some(index) = index {
+                        this.binding(index);
+                    }
+                    this.binding(binding);
+                    this.map_element(element);
+                });
+            }
+        }
+    }
+
+    fn expression(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Literal(_) | Expr::Identifier(_) => {}
+            Expr::String(elements) => {
+                for element in elements {
+                    if let StringElement::Interpolate(expr, _) = element {
+                        self.expression(expr);
+                    }
+                }
+            }
+            Expr::List(elements) => {
+                for element in elements {
+                    self.list_element(element);
+                }
+            }
+            Expr::Map(elements) => {
+                for element in elements {
+                    self.map_element(element);
+                }
+            }
+            Expr::Let {
+                bindings,
+                expression,
+            } => self.scoped(|this| {
+                for (binding, expr) in bindings {
+                    this.expression(expr);
+                    this.binding(binding);
+                }
+                this.expression(expression);
+            }),
+            Expr::Transformed { operand, transform } => {
+                self.expression(operand);
+                match transform {
+                    Transform::UnOp(_) => {}
+                    Transform::BinOp(_, expr) => self.expression(expr),
+                    Transform::FunCall(args) => {
+                        for arg in args.as_ref() {
+                            match arg.as_ref() {
+                                ArgElement::Singleton(expr)
+                                | ArgElement::Keyword(_, expr)
+                                | ArgElement::Splat(expr) => self.expression(expr),
+                            }
+                        }
+                    }
+                }
+            }
+            Expr::Chain { operand, links } => {
+                self.expression(operand);
+                for (_, expr) in links {
+                    self.expression(expr);
+                }
+            }
+            Expr::Branch {
+                condition,
+                true_branch,
+                false_branch,
+            } => {
+                self.expression(condition);
+                self.expression(true_branch);
+                self.expression(false_branch);
+            }
+            Expr::Function {
+                positional,
+                keywords,
+                expression,
+            } => self.scoped(|this| {
+                this.list_binding(positional);
+                if let Some(keywords) = keywords {
+                    this.map_binding(keywords);
+                }
+                this.expression(expression);
+            }),
+        }
+    }
+}
+
+fn run(file: &File) -> Linter {
+    let mut linter = Linter::new();
+    for statement in &file.statements {
+        let TopLevel::Import(_, binding) = statement;
+        linter.binding(binding);
+    }
+    linter.expression(&file.expression);
+    linter
+}
+
+/// Find all lint warnings in a file.
+pub fn file(file: &File) -> Vec<Tagged<Warning>> {
+    run(file).warnings
+}
+
+/// Return an error pointing to the first binding that shadows a name declared
+/// `final`, if there is one.
+pub(crate) fn check_final(file: &File) -> Res<()> {
+    match run(file).error {
+        Some(err) => Err(err),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::check_final;
+    use crate::error::{Action, Error, Reason, Span, Syntax, Warning};
+    use crate::types::Key;
+    use crate::{lint, parse};
+
+    /// Return the shadowed names and their offsets.
+    fn shadowed(input: &str) -> Vec<(String, usize)> {
+        lint(input)
+            .unwrap()
+            .into_iter()
+            .map(|warning| {
+                let Warning::Shadowing(name) = *warning.as_ref();
+                (name.as_str().to_owned(), warning.span().offset())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn shadowing() {
+        assert_eq!(shadowed("let a = 1 let b = a in b"), vec![]);
+        assert_eq!(
+            shadowed("let a = 1 let a = a + 1 in a"),
+            vec![("a".to_owned(), 14)]
+        );
+        assert_eq!(
+            shadowed("let a = 1, [b, a] = [2, 3] in a"),
+            vec![("a".to_owned(), 15)]
+        );
+        assert_eq!(
+            shadowed("let x = 1 in fn (y, x) x + y"),
+            vec![("x".to_owned(), 20)]
+        );
+        assert_eq!(
+            shadowed("let x = 1 in [for {x} in y: x]"),
+            vec![("x".to_owned(), 19)]
+        );
+        assert_eq!(
+            shadowed("let x = 1 in {for i, x in y: $i: x}"),
+            vec![("x".to_owned(), 21)]
+        );
+
+        // Sibling scopes don't shadow each other
+        assert_eq!(shadowed("[for x in y: x, for x in z: x]"), vec![]);
+        assert_eq!(shadowed("[fn (x) x, let x = 1 in x]"), vec![]);
+
+        // Placeholders and builtins may be rebound
+        assert_eq!(shadowed("let _ = 1 let _ = 2 let len = 3 in len"), vec![]);
+
+        // Imports
+        assert_eq!(
+            shadowed("import \"a\" as x\nlet x = 1 in x"),
+            vec![("x".to_owned(), 20)]
+        );
+
+        assert_eq!(
+            Warning::Shadowing(Key::new("a")).to_string(),
+            "binding of 'a' shadows an earlier binding"
+        );
+    }
+
+    #[test]
+    fn final_bindings() {
+        let check = |input: &str| check_final(&parse(input).unwrap());
+
+        assert!(check("let final a = 1 let b = a in b").is_ok());
+        assert!(check("let a = 1 let a = 2 in a").is_ok());
+        assert!(check("[let final x = 1 in x, let x = 2 in x]").is_ok());
+        assert!(check("let final _ = 1 let _ = 2 in 3").is_ok());
+
+        assert_eq!(
+            check("import \"a\" as final x let x = 1 in x"),
+            Err(Error::new(Syntax::ShadowFinal(Key::new("x"))).tag(Span::from(26), Action::Parse))
+        );
+
+        // Shadowing a final binding is still a lint warning too
+        assert_eq!(
+            shadowed("let final a = 1 let a = 2 in a"),
+            vec![("a".to_owned(), 20)]
+        );
+
+        assert_eq!(
+            Reason::Syntax(Syntax::ShadowFinal(Key::new("a"))).to_string(),
+            "final name 'a' may not be shadowed"
+        );
+    }
+}
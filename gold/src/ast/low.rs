@@ -2,7 +2,7 @@ use super::scope::{BindingLoc, ClosureScope, LocalScope, Scope, SlotCatalog};
 use crate::compile::{CompiledFunction, Compiler};
 use crate::error::Tagged;
 use crate::formatting::FormatSpec;
-use crate::types::{BinOp, Key, Res, UnOp};
+use crate::types::{BinOp, EagerOp, Key, Res, UnOp};
 use crate::Object;
 
 #[derive(Debug, Clone)]
@@ -72,6 +72,7 @@ pub enum ListElement {
         element: Box<Tagged<ListElement>>,
     },
     Loop {
+        index: Option<Box<Tagged<Binding>>>,
         binding: Tagged<Binding>,
         iterable: Tagged<Expr>,
         element: Box<Tagged<ListElement>>,
@@ -91,6 +92,7 @@ pub enum MapElement {
         element: Box<Tagged<MapElement>>,
     },
     Loop {
+        index: Option<Box<Tagged<Binding>>>,
         binding: Tagged<Binding>,
         iterable: Tagged<Expr>,
         element: Box<Tagged<MapElement>>,
@@ -115,6 +117,10 @@ pub enum Expr {
         operand: Box<Tagged<Expr>>,
         transform: Transform,
     },
+    Chain {
+        operand: Box<Tagged<Expr>>,
+        links: Vec<(Tagged<EagerOp>, Tagged<Expr>)>,
+    },
     List(Vec<Tagged<ListElement>>),
     Map(Vec<Tagged<MapElement>>),
     Let {
@@ -144,6 +150,7 @@ pub struct Function {
     pub expression: Box<Tagged<Expr>>,
     pub slots: SlotCatalog,
     pub requires: Option<Vec<BindingLoc>>,
+    pub stream: bool,
 }
 
 impl Function {
@@ -157,6 +164,7 @@ pub struct FunctionBuilder<'a> {
     expression: Option<Tagged<Expr>>,
     positional: Option<Tagged<ListBinding>>,
     keywords: Option<Tagged<MapBinding>>,
+    stream: bool,
 }
 
 impl<'a> FunctionBuilder<'a> {
@@ -166,6 +174,7 @@ impl<'a> FunctionBuilder<'a> {
             expression: None,
             positional: None,
             keywords: None,
+            stream: false,
         }
     }
 
@@ -185,6 +194,10 @@ impl<'a> FunctionBuilder<'a> {
         self.expression = Some(expr);
     }
 
+    pub fn stream(&mut self) {
+        self.stream = true;
+    }
+
     pub fn finalize(self) -> Function {
         let (constants, fmt_specs, requires, slots) = self.scope.finalize();
         Function {
@@ -195,6 +208,7 @@ impl<'a> FunctionBuilder<'a> {
             expression: Box::new(self.expression.unwrap()),
             slots,
             requires: Some(requires),
+            stream: self.stream,
         }
     }
 }
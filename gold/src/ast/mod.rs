@@ -1,7 +1,11 @@
 pub mod high;
 
+pub mod lint;
+
 pub mod low;
 
+pub mod print;
+
 mod scope;
 
 pub use scope::{BindingLoc, SlotCatalog, SlotType};
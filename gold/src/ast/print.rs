@@ -0,0 +1,695 @@
+//! Conversion of high-level ASTs back to canonical Gold source code.
+//!
+//! The output depends only on the AST, not on the layout of the original
+//! source, which makes formatting idempotent. Parentheses are inserted where
+//! required by operator precedence and nowhere else.
+
+use crate::object::escape;
+use crate::parsing::{is_map_key, KEYWORDS};
+use crate::types::{BinOp, EagerOp, LogicOp, UnOp};
+use crate::Object;
+
+use super::high::{
+    ArgElement, Binding, Expr, File, ListBinding, ListBindingElement, ListElement, MapBinding,
+    MapBindingElement, MapElement, StringElement, TopLevel, Transform,
+};
+
+/// Number of spaces per indentation level.
+const INDENT: usize = 4;
+
+/// Collections are broken across multiple lines if they would otherwise
+/// exceed this width, including indentation.
+const MAX_WIDTH: usize = 80;
+
+// Precedence levels, from loosest to tightest binding. Composite expressions
+// (let-blocks, branches and functions) can only be operands if parenthesized.
+const COMPOSITE: u8 = 0;
+const OR: u8 = 1;
+const AND: u8 = 2;
+const NOT: u8 = 3;
+const CONTAINS: u8 = 4;
+const COMPARISON: u8 = 5;
+const SUM: u8 = 6;
+const PRODUCT: u8 = 7;
+const PREFIX: u8 = 8;
+const POWER: u8 = 9;
+const POSTFIX: u8 = 10;
+
+fn pad(level: usize) -> String {
+    " ".repeat(INDENT * level)
+}
+
+/// Return true if `name` can be written as a bare identifier.
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_')
+        && !KEYWORDS.contains(&name)
+}
+
+fn quote(value: &str) -> String {
+    format!("\"{}\"", escape(value))
+}
+
+/// Join a sequence of already formatted items, either on a single line or
+/// spread across multiple lines with a trailing comma.
+fn collection(open: &str, items: Vec<String>, close: &str, level: usize) -> String {
+    let items = items.into_iter().map(|item| (item, true)).collect();
+    collection_sep(open, items, close, level)
+}
+
+/// Like [`collection`], but each item is paired with a flag indicating
+/// whether it must be followed by a separator. Items without separators
+/// (multiline strings) force the multi-line layout.
+fn collection_sep(open: &str, items: Vec<(String, bool)>, close: &str, level: usize) -> String {
+    if items.is_empty() {
+        return format!("{}{}", open, close);
+    }
+
+    let texts: Vec<&str> = items.iter().map(|(item, _)| item.as_str()).collect();
+    let inline = format!("{}{}{}", open, texts.join(", "), close);
+    if !inline.contains('\n') && INDENT * level + inline.len() <= MAX_WIDTH {
+        return inline;
+    }
+
+    let mut out = open.to_string();
+    for (item, sep) in items {
+        out += "\n";
+        out += &pad(level + 1);
+        out += &item;
+        if sep {
+            out += ",";
+        }
+    }
+    out += "\n";
+    out += &pad(level);
+    out += close;
+    out
+}
+
+fn binop_info(op: &BinOp) -> (&'static str, u8) {
+    match op {
+        BinOp::Logic(LogicOp::Or) => ("or", OR),
+        BinOp::Logic(LogicOp::And) => ("and", AND),
+        BinOp::Eager(EagerOp::Contains) => ("has", CONTAINS),
+        BinOp::Eager(EagerOp::In) => ("in", CONTAINS),
+        BinOp::Eager(EagerOp::NotIn) => ("not in", CONTAINS),
+        BinOp::Eager(EagerOp::Equal) => ("==", COMPARISON),
+        BinOp::Eager(EagerOp::NotEqual) => ("!=", COMPARISON),
+        BinOp::Eager(EagerOp::Less) => ("<", COMPARISON),
+        BinOp::Eager(EagerOp::Greater) => (">", COMPARISON),
+        BinOp::Eager(EagerOp::LessEqual) => ("<=", COMPARISON),
+        BinOp::Eager(EagerOp::GreaterEqual) => (">=", COMPARISON),
+        BinOp::Eager(EagerOp::Add) => ("+", SUM),
+        BinOp::Eager(EagerOp::Subtract) => ("-", SUM),
+        BinOp::Eager(EagerOp::Multiply) => ("*", PRODUCT),
+        BinOp::Eager(EagerOp::Divide) => ("/", PRODUCT),
+        BinOp::Eager(EagerOp::IntegerDivide) => ("//", PRODUCT),
+        BinOp::Eager(EagerOp::Power) => ("^", POWER),
+        BinOp::Eager(EagerOp::Index) => ("", POSTFIX),
+        BinOp::Eager(EagerOp::Access) => ("", POSTFIX),
+    }
+}
+
+fn precedence(expr: &Expr) -> u8 {
+    match expr {
+        Expr::Let { .. } | Expr::Function { .. } | Expr::Branch { .. } => COMPOSITE,
+        Expr::Transformed { transform, .. } => match transform {
+            Transform::UnOp(op) if matches!(op.as_ref(), Some(UnOp::LogicalNegate)) => NOT,
+            Transform::UnOp(_) => PREFIX,
+            Transform::FunCall(_) => POSTFIX,
+            Transform::BinOp(op, _) => binop_info(op.as_ref()).1,
+        },
+        Expr::Chain { .. } => COMPARISON,
+        _ => POSTFIX,
+    }
+}
+
+/// Return true if `expr` is formatted with an `in` operator outside of any
+/// delimiters. As the value of a let-binding, where `in` ends the binding,
+/// such an expression must be parenthesized.
+fn has_bare_in(expr: &Expr) -> bool {
+    match expr {
+        Expr::Transformed { operand, transform } => match transform {
+            Transform::BinOp(op, rhs) => match op.as_ref() {
+                BinOp::Eager(EagerOp::In) => true,
+                BinOp::Eager(EagerOp::Index) => has_bare_in(operand),
+                _ => has_bare_in(operand) || has_bare_in(rhs),
+            },
+            Transform::UnOp(_) | Transform::FunCall(_) => has_bare_in(operand),
+        },
+        Expr::Chain { operand, links } => {
+            has_bare_in(operand) || links.iter().any(|(_, expr)| has_bare_in(expr))
+        }
+        Expr::Let { expression, .. } => has_bare_in(expression),
+        Expr::Branch { false_branch, .. } => has_bare_in(false_branch),
+        Expr::Function { expression, .. } => has_bare_in(expression),
+        _ => false,
+    }
+}
+
+/// Format an expression which must bind at least as tightly as `min`,
+/// parenthesizing it if necessary.
+fn operand(expr: &Expr, min: u8, level: usize) -> String {
+    let text = expression(expr, level);
+    if precedence(expr) < min {
+        format!("({})", text)
+    } else {
+        text
+    }
+}
+
+fn string(elements: &[StringElement], level: usize) -> String {
+    let mut out = "\"".to_string();
+    for element in elements {
+        match element {
+            StringElement::Raw(value) => out += &escape(value),
+            StringElement::Interpolate(expr, spec) => {
+                out += "${";
+                out += &expression(expr, level);
+                if let Some(spec) = spec {
+                    out += ":";
+                    out += &spec.to_string();
+                }
+                out += "}";
+            }
+        }
+    }
+    out += "\"";
+    out
+}
+
+fn literal(obj: &Object) -> String {
+    if let Some(x) = obj.get_float() {
+        // The debug representation always includes a decimal point or an
+        // exponent, so the value is parsed back as a float.
+        format!("{:?}", x)
+    } else {
+        obj.to_string()
+    }
+}
+
+fn list_element(element: &ListElement, level: usize) -> String {
+    match element {
+        ListElement::Singleton(expr) => expression(expr, level),
+        ListElement::Splat(expr) => format!("...{}", expression(expr, level)),
+        ListElement::Loop {
+            index,
+            binding: bnd,
+            iterable,
+            element,
+        } => {
+            let index = index
+                .as_ref()
+                .map(|index| format!("{}, ", binding(index, level)))
+                .unwrap_or_default();
+            format!(
+                "for {}{} in {}: {}",
+                index,
+                binding(bnd, level),
+                expression(iterable, level),
+                list_element(element, level),
+            )
+        }
+        ListElement::Cond { condition, element } => format!(
+            "when {}: {}",
+            expression(condition, level),
+            list_element(element, level),
+        ),
+    }
+}
+
+fn map_key(key: &Expr, level: usize) -> String {
+    match key {
+        Expr::Literal(obj) => match obj.get_str() {
+            Some(name) if is_map_key(name) => name.to_string(),
+            Some(name) => quote(name),
+            None => format!("${}", literal(obj)),
+        },
+        Expr::String(elements) => string(elements, level),
+        _ => format!("${}", operand(key, POSTFIX, level)),
+    }
+}
+
+/// Format a map element. The boolean is false if the element must not be
+/// followed by a comma.
+fn map_element(element: &MapElement, level: usize) -> (String, bool) {
+    match element {
+        MapElement::Singleton { key, value } => {
+            let key = map_key(key, level);
+            match value.as_ref() {
+                // Strings with newlines can only be written as multiline strings.
+                Expr::Literal(obj) if obj.get_str().is_some_and(|s| s.contains('\n')) => {
+                    let mut out = format!("{}::", key);
+                    for line in obj.get_str().unwrap().lines() {
+                        out += "\n";
+                        out += &pad(level + 1);
+                        out += line;
+                    }
+                    (out, false)
+                }
+                _ => (format!("{}: {}", key, expression(value, level)), true),
+            }
+        }
+        MapElement::Splat(expr) => (format!("...{}", expression(expr, level)), true),
+        MapElement::Loop {
+            index,
+            binding: bnd,
+            iterable,
+            element,
+        } => {
+            let index = index
+                .as_ref()
+                .map(|index| format!("{}, ", binding(index, level)))
+                .unwrap_or_default();
+            let (element, sep) = map_element(element, level);
+            let text = format!(
+                "for {}{} in {}: {}",
+                index,
+                binding(bnd, level),
+                expression(iterable, level),
+                element,
+            );
+            (text, sep)
+        }
+        MapElement::Cond { condition, element } => {
+            let (element, sep) = map_element(element, level);
+            let text = format!("when {}: {}", expression(condition, level), element);
+            (text, sep)
+        }
+    }
+}
+
+fn list_binding(bnd: &ListBinding, level: usize) -> Vec<String> {
+    bnd.elements()
+        .iter()
+        .map(|element| match element.as_ref() {
+            ListBindingElement::Binding {
+                binding: b,
+                default,
+            } => match default {
+                None => binding(b, level),
+                Some(default) => format!("{} = {}", binding(b, level), expression(default, level)),
+            },
+            ListBindingElement::SlurpTo(name) => format!("...{}", name.as_ref()),
+            ListBindingElement::Slurp => "...".to_string(),
+        })
+        .collect()
+}
+
+fn map_binding(bnd: &MapBinding, level: usize) -> Vec<String> {
+    bnd.elements()
+        .iter()
+        .map(|element| match element.as_ref() {
+            MapBindingElement::Binding {
+                key,
+                binding: b,
+                default,
+            } => {
+                let mut out = key.as_ref().to_string();
+                if !matches!(b.as_ref(), Binding::Identifier(name) if name.as_ref() == key.as_ref())
+                {
+                    out += " as ";
+                    out += &binding(b, level);
+                }
+                if let Some(default) = default {
+                    out += " = ";
+                    out += &expression(default, level);
+                }
+                out
+            }
+            MapBindingElement::SlurpTo(name) => format!("...{}", name.as_ref()),
+        })
+        .collect()
+}
+
+fn binding(bnd: &Binding, level: usize) -> String {
+    match bnd {
+        Binding::Identifier(name) => name.as_ref().to_string(),
+        Binding::Final(name) => format!("final {}", name.as_ref()),
+        Binding::List(elements) => collection("[", list_binding(elements, level + 1), "]", level),
+        Binding::Map(elements) => collection("{", map_binding(elements, level + 1), "}", level),
+    }
+}
+
+fn arg_element(element: &ArgElement, level: usize) -> String {
+    match element {
+        ArgElement::Singleton(expr) => expression(expr, level),
+        ArgElement::Keyword(name, expr) => {
+            format!("{}: {}", name.as_ref(), expression(expr, level))
+        }
+        ArgElement::Splat(expr) => format!("...{}", expression(expr, level)),
+    }
+}
+
+fn transformed(operand_expr: &Expr, transform: &Transform, level: usize) -> String {
+    match transform {
+        Transform::UnOp(op) => {
+            let min = match op.as_ref() {
+                Some(UnOp::LogicalNegate) => NOT,
+                _ => PREFIX,
+            };
+            let inner = operand(operand_expr, min, level);
+            match op.as_ref() {
+                Some(UnOp::LogicalNegate) => format!("not {}", inner),
+                Some(UnOp::ArithmeticalNegate) if inner.starts_with(['-', '+']) => {
+                    format!("- {}", inner)
+                }
+                Some(UnOp::ArithmeticalNegate) => format!("-{}", inner),
+                None if inner.starts_with(['-', '+']) => format!("+ {}", inner),
+                None => format!("+{}", inner),
+            }
+        }
+
+        Transform::FunCall(args) => {
+            let args = args
+                .as_ref()
+                .iter()
+                .map(|arg| arg_element(arg, level + 1))
+                .collect();
+            format!(
+                "{}{}",
+                operand(operand_expr, POSTFIX, level),
+                collection("(", args, ")", level),
+            )
+        }
+
+        Transform::BinOp(op, rhs) => {
+            let lhs_text = operand(operand_expr, POSTFIX, level);
+            match op.as_ref() {
+                BinOp::Eager(EagerOp::Access) => match rhs.as_ref().as_ref() {
+                    Expr::Literal(obj) if obj.get_str().is_some_and(is_identifier) => {
+                        format!("{}.{}", lhs_text, obj.get_str().unwrap())
+                    }
+                    subscript => format!("{}[{}]", lhs_text, expression(subscript, level)),
+                },
+
+                BinOp::Eager(EagerOp::Index) => {
+                    format!("{}[{}]", lhs_text, expression(rhs, level))
+                }
+
+                BinOp::Eager(EagerOp::Power) => {
+                    format!("{} ^ {}", lhs_text, operand(rhs, PREFIX, level))
+                }
+
+                // Comparisons don't associate, they chain
+                op @ BinOp::Eager(
+                    EagerOp::Less
+                    | EagerOp::Greater
+                    | EagerOp::LessEqual
+                    | EagerOp::GreaterEqual
+                    | EagerOp::Equal
+                    | EagerOp::NotEqual,
+                ) => {
+                    let (symbol, prec) = binop_info(op);
+                    format!(
+                        "{} {} {}",
+                        operand(operand_expr, prec + 1, level),
+                        symbol,
+                        operand(rhs, prec + 1, level),
+                    )
+                }
+
+                op => {
+                    let (symbol, prec) = binop_info(op);
+                    format!(
+                        "{} {} {}",
+                        operand(operand_expr, prec, level),
+                        symbol,
+                        operand(rhs, prec + 1, level),
+                    )
+                }
+            }
+        }
+    }
+}
+
+fn expression(expr: &Expr, level: usize) -> String {
+    match expr {
+        Expr::Literal(obj) => literal(obj),
+        Expr::String(elements) => string(elements, level),
+        Expr::Identifier(name) => name.as_ref().to_string(),
+
+        Expr::List(elements) => {
+            let items = elements
+                .iter()
+                .map(|e| list_element(e, level + 1))
+                .collect();
+            collection("[", items, "]", level)
+        }
+
+        Expr::Map(elements) => {
+            let items = elements.iter().map(|e| map_element(e, level + 1)).collect();
+            collection_sep("{", items, "}", level)
+        }
+
+        Expr::Let {
+            bindings,
+            expression: body,
+        } => {
+            let mut out = String::new();
+            for (bnd, value) in bindings {
+                let value = if has_bare_in(value) {
+                    format!("({})", expression(value, level))
+                } else {
+                    expression(value, level)
+                };
+                out += &format!("let {} = {}\n{}", binding(bnd, level), value, pad(level));
+            }
+            out += "in ";
+            out += &expression(body, level);
+            out
+        }
+
+        Expr::Transformed { operand, transform } => transformed(operand, transform, level),
+
+        Expr::Chain {
+            operand: first,
+            links,
+        } => {
+            let mut out = operand(first, COMPARISON + 1, level);
+            for (op, rhs) in links {
+                let (symbol, _) = binop_info(&BinOp::Eager(*op.as_ref()));
+                out += &format!(" {} {}", symbol, operand(rhs, COMPARISON + 1, level));
+            }
+            out
+        }
+
+        Expr::Function {
+            positional,
+            keywords,
+            expression: body,
+        } => {
+            let params = match keywords {
+                Some(kw) if positional.elements().is_empty() => {
+                    collection("{", map_binding(kw, level), "}", level)
+                }
+                Some(kw) => {
+                    let pos = list_binding(positional, level).join(", ");
+                    let kw = map_binding(kw, level).join(", ");
+                    if kw.is_empty() {
+                        format!("({};)", pos)
+                    } else {
+                        format!("({}; {})", pos, kw)
+                    }
+                }
+                None => format!("({})", list_binding(positional, level).join(", ")),
+            };
+            match body.as_ref().as_ref() {
+                Expr::Let { .. } => format!(
+                    "fn {}\n{}{}",
+                    params,
+                    pad(level + 1),
+                    expression(body, level + 1)
+                ),
+                _ => format!("fn {} {}", params, expression(body, level)),
+            }
+        }
+
+        Expr::Branch {
+            condition,
+            true_branch,
+            false_branch,
+        } => {
+            let condition = expression(condition, level);
+            let true_branch = expression(true_branch, level);
+            let false_branch = expression(false_branch, level);
+            let inline = format!(
+                "if {} then {} else {}",
+                condition, true_branch, false_branch
+            );
+            if !inline.contains('\n') && INDENT * level + inline.len() <= MAX_WIDTH {
+                inline
+            } else {
+                let pad = pad(level);
+                format!(
+                    "if {}\n{}then {}\n{}else {}",
+                    condition, pad, true_branch, pad, false_branch
+                )
+            }
+        }
+    }
+}
+
+/// Format a parsed file as canonical Gold source code.
+pub fn file(file: &File) -> String {
+    let mut out = String::new();
+    for statement in &file.statements {
+        let TopLevel::Import(path, bnd) = statement;
+        out += &format!("import {} as {}\n", quote(path.as_ref()), binding(bnd, 0));
+    }
+    if !file.statements.is_empty() {
+        out += "\n";
+    }
+    out += &expression(file.expression.as_ref(), 0);
+    out += "\n";
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{eval_raw, format_source};
+
+    fn fmt(input: &str) -> String {
+        format_source(input).unwrap()
+    }
+
+    #[test]
+    fn canonical() {
+        assert_eq!(fmt("1+2*3"), "1 + 2 * 3\n");
+        assert_eq!(fmt("((1+2))*3"), "(1 + 2) * 3\n");
+        assert_eq!(fmt("1-(2-3)"), "1 - (2 - 3)\n");
+        assert_eq!(fmt("(1-2)-3"), "1 - 2 - 3\n");
+        assert_eq!(fmt("(2^3)^4"), "(2 ^ 3) ^ 4\n");
+        assert_eq!(fmt("2^(3^4)"), "2 ^ 3 ^ 4\n");
+        assert_eq!(fmt("(-2)^2"), "(-2) ^ 2\n");
+        assert_eq!(fmt("- (-x)"), "- -x\n");
+        assert_eq!(fmt("not(a)and(b)or c"), "not a and b or c\n");
+        assert_eq!(fmt("a  has  b"), "a has b\n");
+        assert_eq!(fmt("a  in  b"), "a in b\n");
+        assert_eq!(fmt("not (a in b)"), "not a in b\n");
+        assert_eq!(fmt("(not a) in b"), "(not a) in b\n");
+        assert_eq!(fmt("- not a"), "-(not a)\n");
+        assert_eq!(fmt("let x = (a in b) in x"), "let x = (a in b)\nin x\n");
+        assert_eq!(fmt("let x = [a in b] in x"), "let x = [a in b]\nin x\n");
+        assert_eq!(fmt("a  <  b  <=c"), "a < b <= c\n");
+        assert_eq!(fmt("(a <b)<c"), "(a < b) < c\n");
+        assert_eq!(fmt("a ==(b <c)"), "a == (b < c)\n");
+        assert_eq!(fmt("(a <b <c)==d"), "(a < b < c) == d\n");
+        assert_eq!(fmt("(if a then b else c)+1"), "(if a then b else c) + 1\n");
+        assert_eq!(
+            fmt("a.b [\"c\"] [\"d e\"]( 1,k:2,...r )"),
+            "a.b[\"c\"][\"d e\"](1, k: 2, ...r)\n"
+        );
+
+        assert_eq!(fmt("1.0 + 2.5e-7"), "1.0 + 2.5e-7\n");
+        assert_eq!(fmt("\"a${x:>+#10,.3f}\\$\""), "\"a${x:>+#10,.3f}\\$\"\n");
+
+        assert_eq!(
+            fmt("[ 1,when x : 2 , for i,y in z:y, ...w, ]"),
+            "[1, when x: 2, for i, y in z: y, ...w]\n"
+        );
+        assert_eq!(
+            fmt("{a:1,\"b\":2,\"c d\":3,$x:4,for [k,v] in m:$k:v,}"),
+            "{a: 1, b: 2, \"c d\": 3, $x: 4, for [k, v] in m: $k: v}\n"
+        );
+        assert_eq!(fmt("{ a : 1 }"), "{a: 1}\n");
+
+        assert_eq!(
+            fmt("let x=1 let {a as [b], c=2,...d}=e in fn(y;z=x)y+z"),
+            concat!(
+                "let x = 1\n",
+                "let {a as [b], c = 2, ...d} = e\n",
+                "in fn (y; z = x) y + z\n",
+            )
+        );
+        assert_eq!(
+            fmt("let f = fn (x) let y = x in y in f(1)"),
+            concat!(
+                "let f = fn (x)\n",
+                "    let y = x\n",
+                "    in y\n",
+                "in f(1)\n",
+            )
+        );
+        assert_eq!(fmt("fn {a,b} a"), "fn {a, b} a\n");
+        assert_eq!(
+            fmt("f(let y = 1 in y)"),
+            concat!("f(\n", "    let y = 1\n", "    in y,\n", ")\n")
+        );
+        assert_eq!(
+            fmt("let [a = let y = 1 in y] = [] in a"),
+            concat!(
+                "let [\n",
+                "    a = let y = 1\n",
+                "    in y,\n",
+                "] = []\n",
+                "in a\n",
+            )
+        );
+
+        assert_eq!(
+            fmt("import \"a.gold\" as {x}\nimport \"b.gold\" as y\nx+y"),
+            "import \"a.gold\" as {x}\nimport \"b.gold\" as y\n\nx + y\n"
+        );
+
+        assert_eq!(
+            fmt("{a:: some\n   long\n     text\n b: 1}"),
+            concat!(
+                "{\n",
+                "    a::\n",
+                "        some\n",
+                "        long\n",
+                "          text\n",
+                "    b: 1,\n",
+                "}\n",
+            )
+        );
+
+        let long = format!(
+            "[{}]",
+            (0..30).map(|i| i.to_string()).collect::<Vec<_>>().join(",")
+        );
+        let formatted = fmt(&long);
+        assert!(formatted.starts_with("[\n    0,\n    1,\n"));
+        assert!(formatted.ends_with("    29,\n]\n"));
+    }
+
+    #[test]
+    fn idempotent() {
+        let inputs = [
+            "1+2*3-4//5/6",
+            "a.b.c[d](e)(f: g)",
+            "let [a, b = 2, ...rest] = [1] let {x as [y]} = {} in a",
+            "{x: {a:: multi\n        line\n    }, y: [fn (a) let b = 1 in if a then b else [1,2,3,4,5,6,7,8,9,10,11,12,13,14,15,16,17,18,19,20,21,22,23,24,25]]}",
+            "\"${a:0=5}${b:05d}${c:x<}${d: }\"",
+            "[for x in y: when x: {for k in x: $k: [...k]}]",
+            "if a then if b then c else d else (fn (x) x)(1)",
+            "f(let y = 1 in y, k: let z = 2 in z)",
+            "let [a = let y = 1 in y] = [] in a",
+            "let x = (a in b), y = fn (z) (z in c) in not x in (y in d)",
+        ];
+        for input in inputs {
+            let once = fmt(input);
+            assert_eq!(fmt(&once), once);
+        }
+    }
+
+    #[test]
+    fn preserves_meaning() {
+        let inputs = [
+            "1 - (2 - 3) * 2 ^ -1",
+            "(-2) ^ 2 + -(2 ^ 2)",
+            "let [a, ...b] = [1, 2, 3] in {a: a, b: b, c: \"x${a}y\", \"d e\": [for x in b: x * 2]}",
+            "let f = fn (x; y = 2) let z = x in z + y in [f(1), f(1, y: 3)]",
+            "{a:: some\n   long\n     text\n b: not true or false and 1 < 2}",
+            "[1 < 2 < 3, (1 < 2) == (3 < 2), 1 == 1.0 < 2 != 3]",
+            "let f = fn (x; y) [x, y] in f(let y = 1 in y, y: let z = 2 in z)",
+            "let [a = let y = 1 in y] = [] in a",
+            "let x = (1 in [1]), y = if 2 in [x] then 1 else 2 in [not x in [x], (not x) in [y]]",
+        ];
+        for input in inputs {
+            assert_eq!(eval_raw(&fmt(input)), eval_raw(input));
+        }
+    }
+}
@@ -1,7 +1,7 @@
 use std::collections::HashMap;
 
 use crate::formatting::FormatSpec;
-use crate::types::Key;
+use crate::types::{Key, Map};
 use crate::Object;
 
 #[derive(Debug, Clone, Copy)]
@@ -16,6 +16,7 @@ pub trait Scope {
     fn lookup_store(&mut self, name: Key) -> Option<usize>;
     fn lookup_load(&mut self, name: Key, require_cell: bool) -> Option<BindingLoc>;
     fn next_slot(&self) -> usize;
+    fn lookup_builtin(&self, name: Key) -> Option<Object>;
 }
 
 pub trait SubScope {
@@ -29,6 +30,7 @@ pub struct ClosureScope<'a> {
     fmt_specs: Vec<FormatSpec>,
     enclosed: HashMap<Key, usize>,
     requires: Vec<BindingLoc>,
+    builtins: Map,
 }
 
 impl<'a> ClosureScope<'a> {
@@ -40,9 +42,16 @@ impl<'a> ClosureScope<'a> {
             fmt_specs: Vec::new(),
             enclosed: HashMap::new(),
             requires: Vec::new(),
+            builtins: Map::new(),
         }
     }
 
+    /// Make extra builtins available to this scope and all its children.
+    /// These take precedence over the global builtins.
+    pub fn set_builtins(&mut self, builtins: Map) {
+        self.builtins = builtins;
+    }
+
     pub fn finalize(self) -> (Vec<Object>, Vec<FormatSpec>, Vec<BindingLoc>, SlotCatalog) {
         let Self {
             manager,
@@ -105,6 +114,13 @@ impl<'a> Scope for ClosureScope<'a> {
     fn next_slot(&self) -> usize {
         self.manager.next_slot
     }
+
+    fn lookup_builtin(&self, name: Key) -> Option<Object> {
+        self.builtins
+            .get(&name)
+            .cloned()
+            .or_else(|| self.parent.as_ref().and_then(|p| p.lookup_builtin(name)))
+    }
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -264,4 +280,8 @@ impl<'a> Scope for LocalScope<'a> {
     fn next_slot(&self) -> usize {
         self.manager.next_slot
     }
+
+    fn lookup_builtin(&self, name: Key) -> Option<Object> {
+        self.parent.lookup_builtin(name)
+    }
 }
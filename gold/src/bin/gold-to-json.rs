@@ -11,6 +11,10 @@ struct Cli {
     #[arg(short = 'c')]
     code: Option<String>,
 
+    /// Sort the keys of all maps in the output
+    #[arg(long)]
+    sort_keys: bool,
+
     path: Option<PathBuf>,
 }
 
@@ -26,6 +30,12 @@ fn main() {
         exit(1);
     };
 
+    let obj = if args.sort_keys {
+        obj.map(|x| x.with_sorted_keys())
+    } else {
+        obj
+    };
+
     match obj.and_then(JsonValue::try_from) {
         Ok(val) => println!("{}", stringify_pretty(val, 4)),
         Err(error) => match error.rendered() {
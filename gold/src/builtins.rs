@@ -1,30 +1,61 @@
 use std::borrow::Borrow;
+use std::cmp::Ordering;
 use std::collections::HashMap;
+use std::rc::Rc;
 use std::str::FromStr;
+use std::sync::Mutex;
 
-use crate::error::{Error, TypeMismatch, Types, Value};
-use crate::object::Int;
-use crate::types::{Builtin, Key, List, Map, Res};
-use crate::{Object, Type};
+use regex::Regex;
+
+use crate::error::{BindingType, Error, Reason, TypeMismatch, Types, Unpack, Value};
+use crate::eval::Context;
+use crate::object::{repeated_len, Int, MapPart, Range};
+use crate::types::{BinOp, Builtin, EagerOp, Key, List, Map, NativeClosure, Res};
+use crate::{ImportConfig, Object, Type};
 
 /// Convert a function by name to a [`Builtin`] object and append it to a
-/// mapping.
+/// mapping, along with the number of positional parameters it accepts.
 ///
 /// ```ignore
 /// fn myfunc(args: &List, kwargs: Option<&Map>) -> Result<Object, Error> {
 ///     todo!();
 /// }
 /// let mut map = HashMap::new();
-/// builtin!(map, func)
+/// builtin!(map, func, params = 1)
 /// // map["func"] is now available
+///
+/// // Between one and three parameters, or at least one
+/// builtin!(map, func, params = 1..=3)
+/// builtin!(map, func, params = 1..)
+///
+/// // With partial application: func(x) is equivalent to fn (y) func(x, y)
+/// builtin!(map, func, params = 2, curried = 2)
 /// ```
 macro_rules! builtin {
-    ($m: ident, $t: ident, $e: ident) => {
+    ($m: ident, $t: ident, $e: ident, params = $min: literal) => {
+        builtin!(@push $m, $t, $e, $min, Some($min));
+    };
+    ($m: ident, $t: ident, $e: ident, params = $min: literal ..= $max: literal) => {
+        builtin!(@push $m, $t, $e, $min, Some($max));
+    };
+    ($m: ident, $t: ident, $e: ident, params = $min: literal ..) => {
+        builtin!(@push $m, $t, $e, $min, None);
+    };
+    ($m: ident, $t: ident, $e: ident, params = $min: literal, curried = $count: expr) => {
+        let index = $t.len();
+        $t.push(
+            Builtin::new($e, $crate::types::Key::new(stringify!($e).to_string()))
+                .params($min, Some($min))
+                .curried($count),
+        );
+        $m.insert(stringify!($e), index);
+    };
+    (@push $m: ident, $t: ident, $e: ident, $min: expr, $max: expr) => {
         let index = $t.len();
-        $t.push(Builtin::new(
-            $e,
-            $crate::types::Key::new(stringify!($e).to_string()),
-        ));
+        $t.push(
+            Builtin::new($e, $crate::types::Key::new(stringify!($e).to_string()))
+                .params($min, $max),
+        );
         $m.insert(stringify!($e), index);
     };
 }
@@ -37,32 +68,148 @@ lazy_static! {
     ) = {
         let mut m = HashMap::new();
         let mut t = Vec::new();
-        builtin!(m, t, len);
-        builtin!(m, t, range);
-        builtin!(m, t, int);
-        builtin!(m, t, float);
-        builtin!(m, t, bool);
-        builtin!(m, t, str);
-        builtin!(m, t, map);
-        builtin!(m, t, filter);
-        builtin!(m, t, items);
-        builtin!(m, t, exp);
-        builtin!(m, t, log);
-        builtin!(m, t, ord);
-        builtin!(m, t, chr);
-        builtin!(m, t, isint);
-        builtin!(m, t, isstr);
-        builtin!(m, t, isnull);
-        builtin!(m, t, isbool);
-        builtin!(m, t, isfloat);
-        builtin!(m, t, isnumber);
-        builtin!(m, t, isobject);
-        builtin!(m, t, islist);
-        builtin!(m, t, isfunc);
+        builtin!(m, t, len, params = 1);
+        builtin!(m, t, bytelen, params = 1);
+        builtin!(m, t, deep_len, params = 1);
+        builtin!(m, t, isempty, params = 1);
+        builtin!(m, t, range, params = 1..=3);
+        builtin!(m, t, repeat, params = 2);
+        builtin!(m, t, unique, params = 1);
+        builtin!(m, t, chunk, params = 2);
+        builtin!(m, t, windows, params = 2);
+        builtin!(m, t, int, params = 1);
+        builtin!(m, t, float, params = 1);
+        builtin!(m, t, number, params = 1);
+        builtin!(m, t, bool, params = 1);
+        builtin!(m, t, str, params = 1);
+        builtin!(m, t, format_number, params = 1);
+        builtin!(m, t, map, params = 2, curried = 2);
+        builtin!(m, t, filter, params = 2, curried = 2);
+        builtin!(m, t, filter_keys, params = 2, curried = 2);
+        builtin!(m, t, filter_values, params = 2, curried = 2);
+        builtin!(m, t, group_by, params = 2, curried = 2);
+        builtin!(m, t, count, params = 2);
+        builtin!(m, t, first, params = 1..=2);
+        builtin!(m, t, last, params = 1..=2);
+        builtin!(m, t, nth, params = 2..=3);
+        builtin!(m, t, sum, params = 1);
+        builtin!(m, t, product, params = 1);
+        builtin!(m, t, path, params = 2..=3);
+        builtin!(m, t, coalesce, params = 0..);
+        builtin!(m, t, items, params = 1);
+        builtin!(m, t, keys, params = 1);
+        builtin!(m, t, values, params = 1);
+        builtin!(m, t, iter_items, params = 1);
+        builtin!(m, t, iter_keys, params = 1);
+        builtin!(m, t, iter_values, params = 1);
+        builtin!(m, t, dict, params = 1..=2);
+        builtin!(m, t, deep_merge, params = 0..);
+        builtin!(m, t, zip_longest, params = 0..);
+        builtin!(m, t, getattr, params = 2..=3);
+        builtin!(m, t, hasattr, params = 2);
+        builtin!(m, t, min, params = 1..);
+        builtin!(m, t, max, params = 1..);
+        builtin!(m, t, sort, params = 1);
+        builtin!(m, t, wrapping_add, params = 2);
+        builtin!(m, t, checked_add, params = 2);
+        builtin!(m, t, exp, params = 1);
+        builtin!(m, t, log, params = 1);
+        builtin!(m, t, ljust, params = 2..=3);
+        builtin!(m, t, rjust, params = 2..=3);
+        builtin!(m, t, center, params = 2..=3);
+        builtin!(m, t, lower, params = 1);
+        builtin!(m, t, upper, params = 1);
+        builtin!(m, t, split, params = 1..=2);
+        builtin!(m, t, regex_match, params = 2);
+        builtin!(m, t, regex_replace, params = 3);
+        builtin!(m, t, regex_split, params = 2);
+        builtin!(m, t, interpolate, params = 2..=3);
+        builtin!(m, t, ord, params = 1);
+        builtin!(m, t, chr, params = 1);
+        builtin!(m, t, isint, params = 1);
+        builtin!(m, t, isstr, params = 1);
+        builtin!(m, t, isnull, params = 1);
+        builtin!(m, t, isbool, params = 1);
+        builtin!(m, t, isfloat, params = 1);
+        builtin!(m, t, isnumber, params = 1);
+        builtin!(m, t, isobject, params = 1);
+        builtin!(m, t, islist, params = 1);
+        builtin!(m, t, isfunc, params = 1);
+        builtin!(m, t, arity, params = 1);
+        builtin!(m, t, check, params = 2);
+        builtin!(m, t, env, params = 1..=2);
+        builtin!(m, t, read_file, params = 1);
         (m, t)
     };
 }
 
+/// Builtins that can be called with method syntax on strings: `x.f(...)` is
+/// equivalent to `f(x, ...)`.
+const STRING_METHODS: &[&str] = &[
+    "len",
+    "bytelen",
+    "lower",
+    "upper",
+    "split",
+    "count",
+    "first",
+    "last",
+    "ljust",
+    "rjust",
+    "center",
+    "number",
+    "int",
+    "float",
+    "ord",
+    "regex_match",
+    "regex_replace",
+    "regex_split",
+    "interpolate",
+];
+
+/// Builtins that can be called with method syntax on lists.
+const LIST_METHODS: &[&str] = &[
+    "len", "sort", "unique", "count", "first", "last", "nth", "sum", "product", "min", "max",
+];
+
+/// Builtins that can be called with method syntax on maps.
+const MAP_METHODS: &[&str] = &[
+    "len",
+    "items",
+    "keys",
+    "values",
+    "iter_items",
+    "iter_keys",
+    "iter_values",
+    "getattr",
+    "hasattr",
+];
+
+/// Look up a method on an object, returning a function that calls the builtin
+/// of the same name with the object as its first argument.
+pub fn method(receiver: &Object, name: &str) -> Option<Object> {
+    let names = match receiver.type_of() {
+        Type::String => STRING_METHODS,
+        Type::List => LIST_METHODS,
+        Type::Map => MAP_METHODS,
+        _ => return None,
+    };
+    if !names.contains(&name) {
+        return None;
+    }
+
+    let (index, builtins) = &*BUILTINS;
+    let builtin = builtins[index[name]];
+    let receiver = receiver.clone();
+    let closure: Rc<NativeClosure> = Rc::new(move |args: &List, kwargs: Option<&Map>| {
+        let mut all = Vec::with_capacity(args.len() + 1);
+        all.push(receiver.clone());
+        all.extend_from_slice(args);
+        builtin.call(&all, kwargs)
+    });
+    Some(Object::new_func(closure))
+}
+
 /// Return an error indicating wrong type of positional parameter.
 ///
 /// ```ignore
@@ -94,7 +241,7 @@ macro_rules! expected_pos {
 macro_rules! expected_kw {
     ($name:expr, $kwargs:ident, $($types:ident),*) => {
         return Err(Error::new(TypeMismatch::ExpectedKwarg {
-            name: Key::new(stringify!(name)),
+            name: Key::new(stringify!($name)),
             allowed: Types::from(($(Type::$types),*)),
             received: $name.type_of(),
         }))
@@ -128,11 +275,19 @@ macro_rules! argcount {
 }
 
 /// Return the size of a collection or the length of a string.
+///
+/// The length of a string is its number of characters (unicode codepoints).
+/// This takes time proportional to the length of the string, since strings
+/// are stored as UTF-8. Use `bytelen` for a constant-time alternative.
 fn len(args: &List, _: Option<&Map>) -> Res<Object> {
     signature!(args = [x: str] {
         return Ok(Object::from(x.chars().count()))
     });
 
+    signature!(args = [x: range] {
+        return Ok(Object::from(x.len()))
+    });
+
     signature!(args = [x: list] {
         return Ok(Object::from(x.len()))
     });
@@ -146,9 +301,90 @@ fn len(args: &List, _: Option<&Map>) -> Res<Object> {
     argcount!(1, args)
 }
 
+/// Check whether a string, list or map is empty.
+fn isempty(args: &List, _: Option<&Map>) -> Res<Object> {
+    signature!(args = [x: str] {
+        return Ok(Object::from(x.is_empty()))
+    });
+
+    signature!(args = [x: range] {
+        return Ok(Object::from(x.len() == 0))
+    });
+
+    signature!(args = [x: list] {
+        return Ok(Object::from(x.is_empty()))
+    });
+
+    signature!(args = [x: map] {
+        return Ok(Object::from(x.len() == 0))
+    });
+
+    signature!(args = [x: any] { expected_pos!(0, x, String, List, Map) });
+
+    argcount!(1, args)
+}
+
+/// Return the number of bytes in the UTF-8 encoding of a string. Unlike `len`,
+/// this takes constant time, and the two agree for ASCII strings.
+fn bytelen(args: &List, _: Option<&Map>) -> Res<Object> {
+    signature!(args = [x: str] { return Ok(Object::from(x.len())) });
+    signature!(args = [x: any] { expected_pos!(0, x, String) });
+    argcount!(1, args)
+}
+
+/// Count the leaves of a nested structure, that is, all values which are
+/// neither lists nor maps. Any other value counts as a single leaf.
+fn deep_len(args: &List, _: Option<&Map>) -> Res<Object> {
+    signature!(args = [x: any] { return Ok(Object::from(count_leaves(x))) });
+    argcount!(1, args)
+}
+
+fn count_leaves(x: &Object) -> usize {
+    if let Some(r) = x.get_range() {
+        r.len()
+    } else if let Some(l) = x.get_list() {
+        l.iter().map(count_leaves).sum()
+    } else if let Some(m) = x.get_map() {
+        m.iter().map(|(_, v)| count_leaves(v)).sum()
+    } else {
+        1
+    }
+}
+
 /// Works similarly to Python's function of the same name.
+///
+/// With three arguments, the third is the step, which may be negative for a
+/// descending range, but not zero.
+///
+/// The result behaves like a list, but its elements are computed on demand, so
+/// long ranges are cheap to construct, measure and iterate over. Ranges with
+/// bounds outside the 64-bit integer range are constructed eagerly.
 fn range(args: &List, _: Option<&Map>) -> Res<Object> {
+    signature!(args = [start: int, stop: int, step: int] {
+        if !step.nonzero() {
+            return Err(Error::new(Value::OutOfRange))
+        }
+        if let Some(ret) = lazy_range(start, stop, step) {
+            return Ok(ret)
+        }
+        let ascending = step > &Int::from(0);
+        let ret = Object::new_list();
+        let mut i = start.clone();
+        while (ascending && &i < stop) || (!ascending && &i > stop) {
+            ret.push_unchecked(Object::from(i.clone()));
+            i = i.add(step);
+        }
+        return Ok(ret)
+    });
+
+    signature!(args = [x: any, _y: int, _z: int] { expected_pos!(0, x, Integer) });
+    signature!(args = [_x: any, y: any, _z: int] { expected_pos!(1, y, Integer) });
+    signature!(args = [_x: any, _y: any, z: any] { expected_pos!(2, z, Integer) });
+
     signature!(args = [start: int, stop: int] {
+        if let Some(ret) = lazy_range(start, stop, &Int::from(1)) {
+            return Ok(ret)
+        }
         return Ok((start.clone()..stop.clone()).map(Object::from).collect())
     });
 
@@ -156,12 +392,103 @@ fn range(args: &List, _: Option<&Map>) -> Res<Object> {
     signature!(args = [_x: any, y: any] { expected_pos!(1, y, Integer) });
 
     signature!(args = [stop: int] {
+        if let Some(ret) = lazy_range(&Int::from(0), stop, &Int::from(1)) {
+            return Ok(ret)
+        }
         return Ok((Int::from(0)..stop.clone()).map(Object::from).collect())
     });
 
     signature!(args = [x: any] { expected_pos!(0, x, Integer) });
 
-    argcount!(1, 2, args)
+    argcount!(1, 3, args)
+}
+
+/// Construct a lazy range, if all the parameters fit in 64 bits.
+fn lazy_range(start: &Int, stop: &Int, step: &Int) -> Option<Object> {
+    let start = i64::try_from(start).ok()?;
+    let stop = i64::try_from(stop).ok()?;
+    let step = i64::try_from(step).ok()?;
+    Range::new(start, stop, step).map(Object::new_range)
+}
+
+/// Return a list consisting of `n` copies of a value.
+fn repeat(args: &List, _: Option<&Map>) -> Res<Object> {
+    signature!(args = [x: any, n: int] {
+        if n < &Int::from(0) {
+            return Err(Error::new(Value::OutOfRange))
+        }
+        let n = n.repeat_count()?;
+        Context::current().charge(n)?;
+        let mut ret = List::new();
+        ret.try_reserve_exact(n).map_err(|_| Error::new(Value::TooLarge))?;
+        ret.extend(std::iter::repeat_n(x.clone(), n));
+        return Ok(Object::from(ret))
+    });
+
+    signature!(args = [_x: any, n: any] { expected_pos!(1, n, Integer) });
+
+    argcount!(2, args)
+}
+
+/// Split a list into consecutive, non-overlapping sublists of length `n`. The
+/// last sublist is shorter if the length of the list is not divisible by `n`.
+fn chunk(args: &List, _: Option<&Map>) -> Res<Object> {
+    signature!(args = [x: list, n: int] {
+        let n = usize::try_from(n).ok().filter(|n| *n > 0).ok_or_else(|| Error::new(Value::OutOfRange))?;
+        return Ok(x.chunks(n).map(|chunk| chunk.iter().cloned().collect::<Object>()).collect())
+    });
+
+    signature!(args = [_x: list, n: any] { expected_pos!(1, n, Integer) });
+    signature!(args = [x: any, _n: any] { expected_pos!(0, x, List) });
+
+    argcount!(2, args)
+}
+
+/// The maximal total number of elements in the result of `windows`. Since the
+/// windows overlap, this can be much larger than the length of the list.
+const MAX_WINDOWS_ELEMENTS: usize = 10_000_000;
+
+/// Return all overlapping sublists of length `n`, in order. If the list is
+/// shorter than `n`, there are none. Fails if the sublists would have more
+/// than [`MAX_WINDOWS_ELEMENTS`] elements in total.
+fn windows(args: &List, _: Option<&Map>) -> Res<Object> {
+    signature!(args = [x: list, n: int] {
+        let n = usize::try_from(n).ok().filter(|n| *n > 0).ok_or_else(|| Error::new(Value::OutOfRange))?;
+        let total = repeated_len((x.len() + 1).saturating_sub(n), n)?;
+        Context::current().charge(total)?;
+        if total > MAX_WINDOWS_ELEMENTS {
+            return Err(Error::new(Value::TooLarge))
+        }
+        return Ok(x.windows(n).map(|window| window.iter().cloned().collect::<Object>()).collect())
+    });
+
+    signature!(args = [_x: list, n: any] { expected_pos!(1, n, Integer) });
+    signature!(args = [x: any, _n: any] { expected_pos!(0, x, List) });
+
+    argcount!(2, args)
+}
+
+/// Return the distinct elements of a list, in order of first appearance.
+///
+/// Elements are compared as with `==`, so e.g. `1` and `1.0` are considered
+/// the same. All elements must be hashable.
+fn unique(args: &List, _: Option<&Map>) -> Res<Object> {
+    signature!(args = [x: list] {
+        let mut seen: HashMap<u64, Vec<Object>> = HashMap::new();
+        let ret = Object::new_list();
+        for element in x.iter() {
+            let bucket = seen.entry(element.hash()?).or_default();
+            if !bucket.iter().any(|y| y.user_eq(element)) {
+                bucket.push(element.clone());
+                ret.push_unchecked(element.clone());
+            }
+        }
+        return Ok(ret)
+    });
+
+    signature!(args = [x: any] { expected_pos!(0, x, List) });
+
+    argcount!(1, args)
 }
 
 /// Convert the argument to an integer
@@ -214,6 +541,23 @@ fn float(args: &List, _: Option<&Map>) -> Res<Object> {
     argcount!(1, args)
 }
 
+/// Parse a string as a number. Unlike `int` and `float`, the type of the result
+/// depends on the input: integral strings produce integers (of arbitrary
+/// precision if necessary), while others such as `"4.2"` or `"1e9"` produce
+/// floats. Leading and trailing whitespace is ignored.
+fn number(args: &List, _: Option<&Map>) -> Res<Object> {
+    signature!(args = [x: str] {
+        let x = x.trim();
+        return Object::new_int_from_str(x)
+            .or_else(|| f64::from_str(x).ok().map(Object::from))
+            .ok_or_else(|| Error::new(Value::Convert(Type::Float)))
+    });
+
+    signature!(args = [x: any] { expected_pos!(0, x, String) });
+
+    argcount!(1, args)
+}
+
 /// Convert the argument to a bool (this never fails, see Gold's truthiness rules)
 fn bool(args: &List, _: Option<&Map>) -> Res<Object> {
     signature!(args = [x: any] {
@@ -223,25 +567,139 @@ fn bool(args: &List, _: Option<&Map>) -> Res<Object> {
     argcount!(1, args)
 }
 
-/// Convert the argument to a string
-fn str(args: &List, _: Option<&Map>) -> Res<Object> {
+/// Convert the argument to a string. Strings are returned unchanged, while
+/// strings nested in collections are quoted (see [`Object::render`]).
+///
+/// Lists and maps are rendered compactly on a single line by default, while
+/// `str(x, pretty: true)` spreads them across multiple indented lines (see
+/// [`Object::pretty`]).
+fn str(args: &List, kwargs: Option<&Map>) -> Res<Object> {
+    signature!(args = [x: any] kwargs = {pretty: bool} {
+        if pretty && x.get_str().is_none() {
+            return Ok(Object::from(x.pretty(4)))
+        }
+        return Ok(Object::from(x.render()))
+    });
+
+    signature!(args = [_x: any] kwargs = {pretty: any} { expected_kw!(pretty, kwargs, Boolean) });
+
     signature!(args = [x: str] {
         return Ok(Object::from(x))
     });
 
     signature!(args = [x: any] {
-        return Ok(Object::from(x.to_string()))
+        return Ok(Object::from(x.render()))
+    });
+
+    argcount!(1, args)
+}
+
+/// Convert a number to a string for human readers.
+///
+/// Without keyword arguments, this is the same as `str`. With `grouping:
+/// true`, the digits of the integer part are grouped in threes, separated by
+/// commas or by the keyword argument `separator`, so that
+/// `format_number(1234567, grouping: true)` is `"1,234,567"`. The keyword
+/// argument `decimal` replaces the decimal point of floats. Non-finite floats
+/// are not affected.
+fn format_number(args: &List, kwargs: Option<&Map>) -> Res<Object> {
+    let grouping = match extractkw!(kwargs, grouping, any) {
+        None => false,
+        Some(grouping) => match grouping.get_bool() {
+            Some(grouping) => grouping,
+            None => expected_kw!(grouping, kwargs, Boolean),
+        },
+    };
+    let separator = match extractkw!(kwargs, separator, any) {
+        None => ",",
+        Some(separator) => match separator.get_str() {
+            Some(separator) => separator,
+            None => expected_kw!(separator, kwargs, String),
+        },
+    };
+    let decimal = match extractkw!(kwargs, decimal, any) {
+        None => ".",
+        Some(decimal) => match decimal.get_str() {
+            Some(decimal) => decimal,
+            None => expected_kw!(decimal, kwargs, String),
+        },
+    };
+    let separator = grouping.then_some(separator);
+
+    signature!(args = [x: int] {
+        return Ok(Object::from(format_digits(&x.to_string(), separator, decimal)))
+    });
+
+    signature!(args = [x: float] {
+        if !x.is_finite() {
+            return Ok(Object::from(Object::from(x).render()))
+        }
+        return Ok(Object::from(format_digits(&Object::from(x).render(), separator, decimal)))
     });
 
+    signature!(args = [x: any] { expected_pos!(0, x, Integer, Float) });
+
     argcount!(1, args)
 }
 
+/// Insert group separators in the integer part of a formatted number, and
+/// replace its decimal point.
+fn format_digits(text: &str, separator: Option<&str>, decimal: &str) -> String {
+    let (sign, digits) = match text.strip_prefix('-') {
+        Some(digits) => ("-", digits),
+        None => ("", text),
+    };
+    let (integer, fraction) = match digits.split_once('.') {
+        Some((integer, fraction)) => (integer, Some(fraction)),
+        None => (digits, None),
+    };
+
+    let mut out = String::from(sign);
+    for (i, c) in integer.chars().enumerate() {
+        if let Some(separator) = separator {
+            if i > 0 && (integer.len() - i).is_multiple_of(3) {
+                out.push_str(separator);
+            }
+        }
+        out.push(c);
+    }
+    if let Some(fraction) = fraction {
+        out.push_str(decimal);
+        out.push_str(fraction);
+    }
+    out
+}
+
 /// Map a function over a list. This can also be achieved in Gold with
 ///
 /// ```ignore
 /// [for x in y: f(x)]
 /// ```
-fn map(args: &List, _: Option<&Map>) -> Res<Object> {
+///
+/// With `map(f, y, index: true)`, the function is called with the index of
+/// each element as well, as in `f(i, x)`.
+///
+/// Maps can also be mapped over, in which case the function is called with
+/// key-value pairs `[key, value]` and must return new key-value pairs, from
+/// which the resulting map is built.
+fn map(args: &List, kwargs: Option<&Map>) -> Res<Object> {
+    signature!(args = [f: func, x: list] kwargs = {index: bool} {
+        let ret = Object::new_list();
+        for (i, obj) in x.borrow().iter().enumerate() {
+            let elt = if index {
+                f.call(&vec![Object::from(i), obj.clone()], None)?
+            } else {
+                f.call(&vec![obj.clone()], None)?
+            };
+            ret.push_unchecked(elt);
+        }
+        return Ok(ret)
+    });
+
+    signature!(args = [_f: func, _x: list] kwargs = {index: any} {
+        expected_kw!(index, kwargs, Boolean)
+    });
+
     signature!(args = [f: func, x: list] {
         let ret = Object::new_list();
         for obj in x.borrow().iter() {
@@ -251,8 +709,25 @@ fn map(args: &List, _: Option<&Map>) -> Res<Object> {
         return Ok(ret)
     });
 
+    signature!(args = [f: func, x: map] {
+        let ret = Object::new_map();
+        for (key, val) in x.borrow().iter() {
+            let pair = f.call(&vec![Object::from(vec![Object::from(*key), val.clone()])], None)?;
+            let pair_list = pair.get_list().ok_or_else(
+                || Error::new(Unpack::TypeMismatch(BindingType::List, pair.type_of()))
+            )?;
+            match pair_list.as_slice() {
+                [key, value] => ret.insert(key.clone(), value.clone())?,
+                [_] | [] => return Err(Error::new(Unpack::ListTooShort)),
+                _ => return Err(Error::new(Unpack::ListTooLong)),
+            }
+        }
+        return Ok(ret)
+    });
+
     signature!(args = [f: any, _x: list] { expected_pos!(0, f, Function) });
-    signature!(args = [_f: any, x: any] { expected_pos!(1, x, List) });
+    signature!(args = [f: any, _x: map] { expected_pos!(0, f, Function) });
+    signature!(args = [_f: any, x: any] { expected_pos!(1, x, List, Map) });
 
     argcount!(2, args)
 }
@@ -262,7 +737,33 @@ fn map(args: &List, _: Option<&Map>) -> Res<Object> {
 /// ```ignore
 /// [for x in y: when f(x): x]
 /// ```
-fn filter(args: &List, _: Option<&Map>) -> Res<Object> {
+///
+/// With `filter(f, y, index: true)`, the function is called with the index of
+/// each element as well, as in `f(i, x)`.
+///
+/// Maps can also be filtered, in which case the function is called with
+/// key-value pairs `[key, value]`, and the result is a map. With `index: true`,
+/// the position of each entry is passed as well, as in `f(i, [key, value])`.
+fn filter(args: &List, kwargs: Option<&Map>) -> Res<Object> {
+    signature!(args = [f: func, x: list] kwargs = {index: bool} {
+        let ret = Object::new_list();
+        for (i, obj) in x.borrow().iter().enumerate() {
+            let elt = if index {
+                f.call(&vec![Object::from(i), obj.clone()], None)?
+            } else {
+                f.call(&vec![obj.clone()], None)?
+            };
+            if elt.truthy() {
+                ret.push_unchecked(obj.clone());
+            }
+        }
+        return Ok(ret)
+    });
+
+    signature!(args = [_f: func, _x: list] kwargs = {index: any} {
+        expected_kw!(index, kwargs, Boolean)
+    });
+
     signature!(args = [f: func, x: list] {
         let ret = Object::new_list();
         for obj in x.borrow().iter() {
@@ -274,104 +775,1071 @@ fn filter(args: &List, _: Option<&Map>) -> Res<Object> {
         return Ok(ret)
     });
 
+    signature!(args = [f: func, x: map] kwargs = {index: bool} {
+        let mut i = 0;
+        return filter_map(x.borrow().iter(), |key, val| {
+            let pair = Object::from(vec![Object::from(*key), val.clone()]);
+            let elt = if index {
+                f.call(&vec![Object::from(i), pair], None)
+            } else {
+                f.call(&vec![pair], None)
+            };
+            i += 1;
+            elt
+        })
+    });
+
+    signature!(args = [_f: func, _x: map] kwargs = {index: any} {
+        expected_kw!(index, kwargs, Boolean)
+    });
+
+    signature!(args = [f: func, x: map] {
+        return filter_map(x.borrow().iter(), |key, val| {
+            f.call(&vec![Object::from(vec![Object::from(*key), val.clone()])], None)
+        })
+    });
+
     signature!(args = [f: any, _x: list] { expected_pos!(0, f, Function) });
-    signature!(args = [_f: any, x: any] { expected_pos!(1, x, List) });
+    signature!(args = [f: any, _x: map] { expected_pos!(0, f, Function) });
+    signature!(args = [_f: any, x: any] { expected_pos!(1, x, List, Map) });
 
     argcount!(2, args)
 }
 
-/// Return a list of key-value pairs from a map.
-fn items(args: &List, _: Option<&Map>) -> Res<Object> {
-    signature!(args = [x: map] {
-        let ret = Object::new_list();
-        for (key, val) in x.borrow().iter() {
-            ret.push_unchecked(Object::from(vec![
-                Object::from(*key),
-                val.clone(),
-            ]));
-        }
-        return Ok(ret)
+/// Filter a map by its keys. This can also be achieved in Gold with
+///
+/// ```ignore
+/// {for [k, v] in items(y): when f(k): $k: v}
+/// ```
+fn filter_keys(args: &List, _: Option<&Map>) -> Res<Object> {
+    signature!(args = [f: func, x: map] {
+        return filter_map(x.borrow().iter(), |key, _| f.call(&vec![Object::from(*key)], None))
     });
 
-    signature!(args = [x: any] { expected_pos!(0, x, Map) });
+    signature!(args = [f: any, _x: map] { expected_pos!(0, f, Function) });
+    signature!(args = [_f: any, x: any] { expected_pos!(1, x, Map) });
 
-    argcount!(1, args)
+    argcount!(2, args)
 }
 
-/// Compute the exponential function. This supports two signatures:
+/// Filter a map by its values. This can also be achieved in Gold with
 ///
-/// `exp(x)` is equivalent to `exp(x, base: 2.71828...)` while `exp(x, base: y)`
-/// computes y to the power x (which is the same as `y^x`).
-fn exp(args: &List, kwargs: Option<&Map>) -> Res<Object> {
-    signature!(args = [exp: tofloat] kwargs = {base: tofloat} {
-        return Ok(Object::from(base.powf(exp)))
+/// ```ignore
+/// {for [k, v] in items(y): when f(v): $k: v}
+/// ```
+fn filter_values(args: &List, _: Option<&Map>) -> Res<Object> {
+    signature!(args = [f: func, x: map] {
+        return filter_map(x.borrow().iter(), |_, val| f.call(&vec![val.clone()], None))
     });
 
-    signature!(args = [_x: tofloat] kwargs = {base: any} { expected_kw!(base, kwargs, Integer, Float) });
+    signature!(args = [f: any, _x: map] { expected_pos!(0, f, Function) });
+    signature!(args = [_f: any, x: any] { expected_pos!(1, x, Map) });
 
-    signature!(args = [x: tofloat] {
-        return Ok(Object::from(x.exp()))
+    argcount!(2, args)
+}
+
+/// Collect the entries of a map for which a predicate is truthy into a new map.
+fn filter_map<'a>(
+    entries: impl Iterator<Item = (&'a Key, &'a Object)>,
+    mut predicate: impl FnMut(&Key, &Object) -> Res<Object>,
+) -> Res<Object> {
+    let ret = Object::new_map();
+    for (key, val) in entries {
+        if predicate(key, val)?.truthy() {
+            ret.insert_key(*key, val.clone())?;
+        }
+    }
+    Ok(ret)
+}
+
+/// Group the elements of a list by the result of a function, returning a map
+/// from each result to the list of elements that produced it, in order. The
+/// results are converted to map keys as when building maps (see
+/// [`Object::to_key`]), so e.g.
+///
+/// ```ignore
+/// group_by(fn (x) x - x // 2 * 2, [1, 2, 3, 4])
+/// ```
+///
+/// evaluates to `{"1": [1, 3], "0": [2, 4]}`. Note that this means the
+/// integer `1` and the string `"1"` fall in the same group. In addition,
+/// booleans and null are grouped under the keys `"true"`, `"false"` and
+/// `"null"`, so that e.g. `group_by(fn (x) x > 2, x)` splits a list in two.
+/// Other results that can't be map keys (e.g. floats or functions) are an
+/// error.
+fn group_by(args: &List, _: Option<&Map>) -> Res<Object> {
+    signature!(args = [f: func, x: list] {
+        let mut ret = Map::new();
+        for obj in x.borrow().iter() {
+            let result = f.call(&vec![obj.clone()], None)?;
+            let key = match result.get_bool() {
+                Some(true) => Key::new("true"),
+                Some(false) => Key::new("false"),
+                None if result.is_null() => Key::new("null"),
+                None => result.to_key()?,
+            };
+            match ret.get_mut(&key) {
+                Some(elements) => elements.push(obj.clone())?,
+                None => {
+                    ret.insert(key, Object::from(vec![obj.clone()]));
+                }
+            }
+        }
+        return Ok(Object::from(ret))
     });
 
-    signature!(args = [x: any] { expected_pos!(0, x, Integer, Float) });
+    signature!(args = [f: any, _x: list] { expected_pos!(0, f, Function) });
+    signature!(args = [_f: any, x: any] { expected_pos!(1, x, List) });
 
-    argcount!(1, args)
+    argcount!(2, args)
 }
 
-/// Compute the logaritm. This supports two signatures:
+/// Count the number of occurrences of something.
 ///
-/// `log(x)` is equivalent to `log(x, base: 2.71828...)` (the natural logarithm),
-/// while `log(x, base: y)` computes the logarith of `x` to the base `y`.
-fn log(args: &List, kwargs: Option<&Map>) -> Res<Object> {
-    signature!(args = [num: tofloat] kwargs = {base: tofloat} {
-        return Ok(Object::from(num.log(base)))
+/// `count(x, y)` counts the elements of the list `x` that are equal to `y`, or
+/// if `x` and `y` are strings, the number of non-overlapping occurrences of `y`
+/// in `x`. `count(f, x)` counts the elements of `x` for which `f` returns a
+/// truthy value.
+fn count(args: &List, _: Option<&Map>) -> Res<Object> {
+    signature!(args = [f: func, x: list] {
+        let mut n = 0usize;
+        for obj in x.iter() {
+            if f.call(&vec![obj.clone()], None)?.truthy() {
+                n += 1;
+            }
+        }
+        return Ok(Object::from(n))
     });
 
-    signature!(args = [_x: tofloat] kwargs = {base: any} { expected_kw!(base, kwargs, Integer, Float) });
+    signature!(args = [x: list, y: any] {
+        return Ok(Object::from(x.iter().filter(|obj| obj.user_eq(y)).count()))
+    });
 
-    signature!(args = [x: tofloat] {
-        return Ok(Object::from(x.ln()))
+    signature!(args = [x: str, y: str] {
+        return Ok(Object::from(x.matches(y).count()))
     });
 
-    signature!(args = [x: any] { expected_pos!(0, x, Integer, Float) });
+    signature!(args = [_x: str, y: any] { expected_pos!(1, y, String) });
+    signature!(args = [x: any, _y: any] { expected_pos!(0, x, List, String, Function) });
 
-    argcount!(1, args)
+    argcount!(2, args)
 }
 
-/// Return the unicode codepoint corresponding to a single-character string.
-fn ord(args: &List, _: Option<&Map>) -> Res<Object> {
-    signature!(args = [x: str] {
-        let mut chars = x.chars();
-        let c = chars.next();
-        if c.is_none() || chars.next().is_some() {
-            return Err(Error::new(Value::TooLong))
-        }
-        return Ok(Object::from(c.unwrap() as i64))
+/// Return the first element of a list, or the first character of a string.
+///
+/// With `first(x, default)`, the default is returned if `x` is empty.
+/// Otherwise, this is an error.
+fn first(args: &List, _: Option<&Map>) -> Res<Object> {
+    signature!(args = [x: range] { return or_out_of_range(x.get(0).map(Object::from)) });
+    signature!(args = [x: range, default: any] {
+        return Ok(x.get(0).map(Object::from).unwrap_or_else(|| default.clone()))
     });
 
-    signature!(args = [x: any] { expected_pos!(0, x, String) });
-
-    argcount!(1, args)
-}
+    signature!(args = [x: list] { return or_out_of_range(x.first().cloned()) });
+    signature!(args = [x: list, default: any] {
+        return Ok(x.first().cloned().unwrap_or_else(|| default.clone()))
+    });
 
-/// Return the character (as a single-character string) that corresponds to
-/// a unicode codepoint.
-fn chr(args: &List, _: Option<&Map>) -> Res<Object> {
-    signature!(args = [x: int] {
-        let codepoint = u32::try_from(x).map_err(|_| Error::new(Value::OutOfRange))?;
-        let c = char::try_from(codepoint).map_err(|_| Error::new(Value::OutOfRange))?;
-        return Ok(Object::from(c.to_string()))
+    signature!(args = [x: str] { return or_out_of_range(x.chars().next().map(char_object)) });
+    signature!(args = [x: str, default: any] {
+        return Ok(x.chars().next().map(char_object).unwrap_or_else(|| default.clone()))
     });
 
-    signature!(args = [x: any] { expected_pos!(0, x, Integer) });
+    signature!(args = [x: any] { expected_pos!(0, x, List, String) });
+    signature!(args = [x: any, _default: any] { expected_pos!(0, x, List, String) });
 
-    argcount!(1, args)
+    argcount!(1, 2, args)
 }
 
-/// Check whether the argument is an integer.
-fn isint(args: &List, _: Option<&Map>) -> Res<Object> {
-    signature!(args = [_x: int] { return Ok(Object::from(true)); });
-    signature!(args = [_x: any] { return Ok(Object::from(false)); });
+/// Return the last element of a list, or the last character of a string.
+/// Works like `first`.
+fn last(args: &List, _: Option<&Map>) -> Res<Object> {
+    signature!(args = [x: range] {
+        return or_out_of_range(x.len().checked_sub(1).and_then(|i| x.get(i)).map(Object::from))
+    });
+    signature!(args = [x: range, default: any] {
+        let element = x.len().checked_sub(1).and_then(|i| x.get(i)).map(Object::from);
+        return Ok(element.unwrap_or_else(|| default.clone()))
+    });
+
+    signature!(args = [x: list] { return or_out_of_range(x.last().cloned()) });
+    signature!(args = [x: list, default: any] {
+        return Ok(x.last().cloned().unwrap_or_else(|| default.clone()))
+    });
+
+    signature!(args = [x: str] { return or_out_of_range(x.chars().last().map(char_object)) });
+    signature!(args = [x: str, default: any] {
+        return Ok(x.chars().last().map(char_object).unwrap_or_else(|| default.clone()))
+    });
+
+    signature!(args = [x: any] { expected_pos!(0, x, List, String) });
+    signature!(args = [x: any, _default: any] { expected_pos!(0, x, List, String) });
+
+    argcount!(1, 2, args)
+}
+
+/// Return the element of a list at a given index.
+///
+/// With `nth(x, i, default)`, the default is returned if the index is out of
+/// range. Otherwise, this is an error.
+fn nth(args: &List, _: Option<&Map>) -> Res<Object> {
+    signature!(args = [x: range, i: int] {
+        return or_out_of_range(usize::try_from(i).ok().and_then(|i| x.get(i)).map(Object::from))
+    });
+    signature!(args = [x: range, i: int, default: any] {
+        let element = usize::try_from(i).ok().and_then(|i| x.get(i)).map(Object::from);
+        return Ok(element.unwrap_or_else(|| default.clone()))
+    });
+    signature!(args = [_x: range, i: any] { expected_pos!(1, i, Integer) });
+    signature!(args = [_x: range, i: any, _default: any] { expected_pos!(1, i, Integer) });
+
+    signature!(args = [x: list, i: int] {
+        return or_out_of_range(usize::try_from(i).ok().and_then(|i| x.get(i)).cloned())
+    });
+    signature!(args = [x: list, i: int, default: any] {
+        let element = usize::try_from(i).ok().and_then(|i| x.get(i)).cloned();
+        return Ok(element.unwrap_or_else(|| default.clone()))
+    });
+
+    signature!(args = [_x: list, i: any] { expected_pos!(1, i, Integer) });
+    signature!(args = [x: any, _i: any] { expected_pos!(0, x, List) });
+    signature!(args = [_x: list, i: any, _default: any] { expected_pos!(1, i, Integer) });
+    signature!(args = [x: any, _i: any, _default: any] { expected_pos!(0, x, List) });
+
+    argcount!(2, 3, args)
+}
+
+fn char_object(c: char) -> Object {
+    Object::from(c.to_string())
+}
+
+fn or_out_of_range(value: Option<Object>) -> Res<Object> {
+    value.ok_or_else(|| Error::new(Value::OutOfRange))
+}
+
+/// Add all the elements of a list, which must be numbers. The sum of an
+/// empty list is zero. Like the `+` operator, integers are promoted to
+/// arbitrary precision as needed.
+fn sum(args: &List, _: Option<&Map>) -> Res<Object> {
+    accumulate(args, Object::from(0), Object::add)
+}
+
+/// Multiply all the elements of a list, which must be numbers. The product of
+/// an empty list is one. Like the `*` operator, integers are promoted to
+/// arbitrary precision as needed.
+fn product(args: &List, _: Option<&Map>) -> Res<Object> {
+    accumulate(args, Object::from(1), Object::mul)
+}
+
+/// Fold the numeric elements of a list with a binary operator.
+fn accumulate(
+    args: &List,
+    initial: Object,
+    op: fn(&Object, &Object) -> Res<Object>,
+) -> Res<Object> {
+    let context = Context::current();
+
+    signature!(args = [x: range] {
+        let mut acc = initial;
+        for i in 0..x.len() {
+            context.charge(1)?;
+            acc = op(&acc, &Object::from(x.get(i).unwrap()))?;
+        }
+        return Ok(acc)
+    });
+
+    signature!(args = [x: list] {
+        let mut acc = initial;
+        for (index, element) in x.iter().enumerate() {
+            context.charge(1)?;
+            let received = element.type_of();
+            if !matches!(received, Type::Integer | Type::Float) {
+                return Err(Error::new(TypeMismatch::ExpectedElement {
+                    index,
+                    allowed: Types::Two(Type::Integer, Type::Float),
+                    received,
+                }))
+            }
+            acc = op(&acc, element)?;
+        }
+        return Ok(acc)
+    });
+
+    signature!(args = [x: any] { expected_pos!(0, x, List) });
+
+    argcount!(1, args)
+}
+
+/// Walk nested maps and lists along a dotted path, such as `"a.b.0.c"`, which
+/// is equivalent to `x.a.b[0].c`. Segments that are integers index lists, all
+/// others look up map keys. The empty path refers to `x` itself.
+///
+/// With `path(x, p, default)`, the default is returned if the path doesn't
+/// lead anywhere. Otherwise, this is an error.
+fn path(args: &List, _: Option<&Map>) -> Res<Object> {
+    signature!(args = [x: any, p: str] { return walk_path(x, p) });
+    signature!(args = [x: any, p: str, default: any] {
+        return Ok(walk_path(x, p).unwrap_or_else(|_| default.clone()))
+    });
+
+    signature!(args = [_x: any, p: any] { expected_pos!(1, p, String) });
+    signature!(args = [_x: any, p: any, _default: any] { expected_pos!(1, p, String) });
+
+    argcount!(2, 3, args)
+}
+
+fn walk_path(x: &Object, path: &str) -> Res<Object> {
+    let mut current = x.clone();
+    if path.is_empty() {
+        return Ok(current);
+    }
+    for segment in path.split('.') {
+        let index = match (current.type_of(), i64::from_str(segment)) {
+            (Type::List, Ok(i)) => Object::from(i),
+            _ => Object::from(segment),
+        };
+        current = current.index(&index)?;
+    }
+    Ok(current)
+}
+
+/// Return the first argument that is not null, or null if there is none.
+///
+/// Unlike a chain of conditionals, all the arguments are evaluated before the
+/// call.
+fn coalesce(args: &List, _: Option<&Map>) -> Res<Object> {
+    Ok(args
+        .iter()
+        .find(|x| x.get_null().is_none())
+        .cloned()
+        .unwrap_or_else(Object::null))
+}
+
+/// Return a list of key-value pairs from a map.
+fn items(args: &List, _: Option<&Map>) -> Res<Object> {
+    signature!(args = [x: map] {
+        let ret = Object::new_list();
+        for (key, val) in x.borrow().iter() {
+            ret.push_unchecked(Object::from(vec![
+                Object::from(*key),
+                val.clone(),
+            ]));
+        }
+        return Ok(ret)
+    });
+
+    signature!(args = [x: any] { expected_pos!(0, x, Map) });
+
+    argcount!(1, args)
+}
+
+/// Return a list of the keys of a map, in order.
+fn keys(args: &List, _: Option<&Map>) -> Res<Object> {
+    signature!(args = [x: map] {
+        return Ok(x.borrow().iter().map(|(key, _)| Object::from(*key)).collect())
+    });
+
+    signature!(args = [x: any] { expected_pos!(0, x, Map) });
+
+    argcount!(1, args)
+}
+
+/// Return a list of the values of a map, in order.
+fn values(args: &List, _: Option<&Map>) -> Res<Object> {
+    signature!(args = [x: map] {
+        return Ok(x.borrow().iter().map(|(_, val)| val.clone()).collect())
+    });
+
+    signature!(args = [x: any] { expected_pos!(0, x, Map) });
+
+    argcount!(1, args)
+}
+
+/// Return a lazy list of the key-value pairs of a map. This is equal to
+/// `items(x)`, but `[for [k, v] in iter_items(x): ...]` iterates over the map
+/// without building the list of pairs first. Operations that need the whole
+/// list (e.g. `sort`) build it on first use.
+fn iter_items(args: &List, _: Option<&Map>) -> Res<Object> {
+    map_iterator(args, MapPart::Items)
+}
+
+/// Return a lazy list of the keys of a map. See `iter_items`.
+fn iter_keys(args: &List, _: Option<&Map>) -> Res<Object> {
+    map_iterator(args, MapPart::Keys)
+}
+
+/// Return a lazy list of the values of a map. See `iter_items`.
+fn iter_values(args: &List, _: Option<&Map>) -> Res<Object> {
+    map_iterator(args, MapPart::Values)
+}
+
+fn map_iterator(args: &List, part: MapPart) -> Res<Object> {
+    signature!(args = [x: any] {
+        match Object::new_map_view(x, part) {
+            Some(view) => return Ok(view),
+            None => expected_pos!(0, x, Map),
+        }
+    });
+
+    argcount!(1, args)
+}
+
+/// Construct a map. This supports two signatures:
+///
+/// `dict(pairs)` is the inverse of `items`, constructing a map from a list of
+/// key-value pairs, while `dict(keys, values)` zips a list of keys with a list
+/// of values of the same length. Keys must be strings or integers, the latter
+/// being converted to strings, and when a key occurs more than once, the last
+/// value wins.
+fn dict(args: &List, _: Option<&Map>) -> Res<Object> {
+    signature!(args = [pairs: list] {
+        let ret = Object::new_map();
+        for pair in pairs.iter() {
+            let pair_list = pair.get_list().ok_or_else(
+                || Error::new(Unpack::TypeMismatch(BindingType::List, pair.type_of()))
+            )?;
+            match pair_list.as_slice() {
+                [key, value] => ret.insert(key.clone(), value.clone())?,
+                [_] | [] => return Err(Error::new(Unpack::ListTooShort)),
+                _ => return Err(Error::new(Unpack::ListTooLong)),
+            }
+        }
+        return Ok(ret)
+    });
+
+    signature!(args = [x: any] { expected_pos!(0, x, List) });
+
+    signature!(args = [keys: list, values: list] {
+        if keys.len() < values.len() {
+            return Err(Error::new(Unpack::ListTooShort))
+        } else if keys.len() > values.len() {
+            return Err(Error::new(Unpack::ListTooLong))
+        }
+        let ret = Object::new_map();
+        for (key, value) in keys.iter().zip(values.iter()) {
+            ret.insert(key.clone(), value.clone())?;
+        }
+        return Ok(ret)
+    });
+
+    signature!(args = [x: any, _y: list] { expected_pos!(0, x, List) });
+    signature!(args = [_x: any, y: any] { expected_pos!(1, y, List) });
+
+    argcount!(1, 2, args)
+}
+
+/// Recursively merge two maps. Values in `over` take precedence, except when
+/// both values are maps, in which case they are merged in turn.
+fn merge_maps(base: &Map, over: &Map) -> Res<Object> {
+    let ret = Object::new_map();
+    for (key, value) in base {
+        ret.insert_key(*key, value.clone())?;
+    }
+    for (key, value) in over {
+        let base_value = base.get(key).and_then(|x| x.get_map());
+        let merged = match (base_value, value.get_map()) {
+            (Some(x), Some(y)) => merge_maps(&x, &y)?,
+            _ => value.clone(),
+        };
+        ret.insert_key(*key, merged)?;
+    }
+    Ok(ret)
+}
+
+/// Merge maps recursively, from left to right. This is unlike `{...x, ...y}`,
+/// which replaces sub-maps wholesale: sub-maps are merged key by key, while
+/// all other values (including lists) from later maps replace those from
+/// earlier ones.
+fn deep_merge(args: &List, _: Option<&Map>) -> Res<Object> {
+    let mut ret = Object::new_map();
+    for (index, arg) in args.iter().enumerate() {
+        let Some(over) = arg.get_map() else {
+            expected_pos!(index, arg, Map)
+        };
+        let merged = merge_maps(&ret.get_map().unwrap(), &over)?;
+        ret = merged;
+    }
+    Ok(ret)
+}
+
+/// Combine lists elementwise: `zip_longest(x, y, ...)` returns a list of lists
+/// `[x[i], y[i], ...]`, continuing until the longest list is exhausted.
+/// Missing elements of shorter lists are filled in with null, or with the
+/// keyword argument `fill` if given.
+fn zip_longest(args: &List, kwargs: Option<&Map>) -> Res<Object> {
+    let fill = extractkw!(kwargs, fill, any)
+        .cloned()
+        .unwrap_or_else(Object::null);
+
+    let mut lists = Vec::with_capacity(args.len());
+    for (index, arg) in args.iter().enumerate() {
+        let Some(list) = arg.get_list() else {
+            expected_pos!(index, arg, List)
+        };
+        lists.push(list);
+    }
+
+    let len = lists.iter().map(|list| list.len()).max().unwrap_or(0);
+    Ok((0..len)
+        .map(|i| {
+            lists
+                .iter()
+                .map(|list| list.get(i).cloned().unwrap_or_else(|| fill.clone()))
+                .collect::<Object>()
+        })
+        .collect())
+}
+
+/// Substitute values from a map into a template string.
+///
+/// `interpolate(template, values)` replaces each placeholder `${name}` in
+/// `template` with the value of `name` in `values`, converted to a string as
+/// with `str`, raising an error if a key is missing. With
+/// `interpolate(template, values, default)`, missing keys are replaced by
+/// `default` instead. A `$` that doesn't start a complete placeholder is left
+/// as is.
+///
+/// Note that placeholders in string literals must be escaped, e.g.
+/// `interpolate("\${name}", {name: "x"})`, or they will be interpolated when
+/// the literal is evaluated.
+fn interpolate(args: &List, _: Option<&Map>) -> Res<Object> {
+    signature!(args = [template: str, values: map] {
+        return interpolate_template(template, &values, None)
+    });
+
+    signature!(args = [template: str, values: map, default: any] {
+        return interpolate_template(template, &values, Some(default))
+    });
+
+    signature!(args = [_template: str, values: any] { expected_pos!(1, values, Map) });
+    signature!(args = [template: any, _values: any] { expected_pos!(0, template, String) });
+    signature!(args = [_template: str, values: any, _default: any] { expected_pos!(1, values, Map) });
+    signature!(args = [template: any, _values: any, _default: any] { expected_pos!(0, template, String) });
+
+    argcount!(2, 3, args)
+}
+
+fn interpolate_template(template: &str, values: &Map, default: Option<&Object>) -> Res<Object> {
+    let mut out = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("${") {
+        let Some(len) = rest[start + 2..].find('}') else {
+            break;
+        };
+        out.push_str(&rest[..start]);
+        let key = Key::new(&rest[start + 2..start + 2 + len]);
+        let value = match (values.get(&key), default) {
+            (Some(value), _) | (None, Some(value)) => value,
+            (None, None) => return Err(Error::new(Reason::Unassigned(key))),
+        };
+        out.push_str(&value.render());
+        rest = &rest[start + len + 3..];
+    }
+    out.push_str(rest);
+    Ok(Object::new_str_natural(out))
+}
+
+/// Look up a key in a map, where the key is a string computed at runtime.
+///
+/// `getattr(x, key)` is equivalent to `x[key]`, raising an error if the key is
+/// missing, while `getattr(x, key, default)` returns `default` instead.
+fn getattr(args: &List, _: Option<&Map>) -> Res<Object> {
+    signature!(args = [x: map, key: str] {
+        let key = Key::new(key);
+        return x.borrow().get(&key).cloned().ok_or_else(|| Error::new(Reason::Unassigned(key)))
+    });
+
+    signature!(args = [x: map, key: str, default: any] {
+        return Ok(x.borrow().get(&Key::new(key)).cloned().unwrap_or_else(|| default.clone()))
+    });
+
+    signature!(args = [_x: map, key: any] { expected_pos!(1, key, String) });
+    signature!(args = [x: any, _key: any] { expected_pos!(0, x, Map) });
+    signature!(args = [_x: map, key: any, _default: any] { expected_pos!(1, key, String) });
+    signature!(args = [x: any, _key: any, _default: any] { expected_pos!(0, x, Map) });
+
+    argcount!(2, 3, args)
+}
+
+/// Check whether a map contains a key, where the key is a string computed at
+/// runtime. This is the function form of the `has` operator (which, being a
+/// keyword, can't be used as a function name).
+fn hasattr(args: &List, _: Option<&Map>) -> Res<Object> {
+    signature!(args = [x: map, key: str] {
+        return Ok(Object::from(x.borrow().get(&Key::new(key)).is_some()))
+    });
+
+    signature!(args = [_x: map, key: any] { expected_pos!(1, key, String) });
+    signature!(args = [x: any, _key: any] { expected_pos!(0, x, Map) });
+
+    argcount!(2, args)
+}
+
+/// Shared implementation of `min` and `max`. Returns the first element that
+/// no later element compares as `ordering` to, optionally comparing the
+/// results of a key function rather than the elements themselves.
+fn extremum(args: &List, kwargs: Option<&Map>, ordering: Ordering) -> Res<Object> {
+    let func = match kwargs.and_then(|kws| kws.get(&Key::new("key"))) {
+        Some(key) => match key.get_func() {
+            Some(func) => Some(func),
+            None => expected_kw!(key, kwargs, Function),
+        },
+        None => None,
+    };
+
+    let list;
+    let elements = match args.as_slice() {
+        [] => argcount!(1, usize::MAX, args),
+        [x] => match x.get_list() {
+            Some(x) => {
+                list = x;
+                list.as_slice()
+            }
+            None => expected_pos!(0, x, List),
+        },
+        _ => args.as_slice(),
+    };
+
+    let mut best: Option<(&Object, Object)> = None;
+    for element in elements {
+        let value = match func {
+            Some(func) => func.call(&vec![element.clone()], None)?,
+            None => element.clone(),
+        };
+        if let Some((_, best_value)) = &best {
+            let cmp = value.partial_cmp(best_value).ok_or_else(|| {
+                let op = match ordering {
+                    Ordering::Less => EagerOp::Less,
+                    _ => EagerOp::Greater,
+                };
+                Error::new(TypeMismatch::BinOp(
+                    value.type_of(),
+                    best_value.type_of(),
+                    BinOp::Eager(op),
+                ))
+            })?;
+            if cmp != ordering {
+                continue;
+            }
+        }
+        best = Some((element, value));
+    }
+
+    best.map(|(element, _)| element.clone())
+        .ok_or_else(|| Error::new(Unpack::ListTooShort))
+}
+
+/// Return the smallest element. This supports two signatures:
+///
+/// `min(x)` finds the smallest element of the list `x`, while `min(x, y, ...)`
+/// finds the smallest of two or more arguments. In both cases, the keyword
+/// argument `key` may be a function, in which case elements are compared by
+/// the values it returns. Of several equally small elements, the first wins.
+fn min(args: &List, kwargs: Option<&Map>) -> Res<Object> {
+    extremum(args, kwargs, Ordering::Less)
+}
+
+/// Return the largest element. Works like `min`.
+fn max(args: &List, kwargs: Option<&Map>) -> Res<Object> {
+    extremum(args, kwargs, Ordering::Greater)
+}
+
+/// Sort a list in ascending order.
+///
+/// The keyword argument `key` may be a function, in which case elements are
+/// compared by the values it returns, and `reverse: true` sorts in descending
+/// order. The sort is stable: elements that compare equal keep their relative
+/// order from the input, also when sorting in reverse.
+///
+/// Strings are compared by Unicode scalar value, so that e.g. all uppercase
+/// ASCII letters sort before all lowercase ones. With `casefold: true`,
+/// strings (or string keys) are compared by their lowercase forms instead,
+/// ignoring case.
+fn sort(args: &List, kwargs: Option<&Map>) -> Res<Object> {
+    let func = match kwargs.and_then(|kws| kws.get(&Key::new("key"))) {
+        Some(key) => match key.get_func() {
+            Some(func) => Some(func),
+            None => expected_kw!(key, kwargs, Function),
+        },
+        None => None,
+    };
+
+    let reverse = match kwargs.and_then(|kws| kws.get(&Key::new("reverse"))) {
+        Some(reverse) => match reverse.get_bool() {
+            Some(reverse) => reverse,
+            None => expected_kw!(reverse, kwargs, Boolean),
+        },
+        None => false,
+    };
+
+    let casefold = match kwargs.and_then(|kws| kws.get(&Key::new("casefold"))) {
+        Some(casefold) => match casefold.get_bool() {
+            Some(casefold) => casefold,
+            None => expected_kw!(casefold, kwargs, Boolean),
+        },
+        None => false,
+    };
+
+    let (x, len) = match args.as_slice() {
+        [x] => match x.list_len() {
+            Some(len) => (x, len),
+            None => expected_pos!(0, x, List),
+        },
+        _ => argcount!(1, args),
+    };
+
+    // Charge for the comparisons before materializing lazy lists
+    Context::current().charge(len.saturating_mul(len.max(1).ilog2() as usize + 1))?;
+    let list = x.get_list().unwrap();
+
+    let keys = match func {
+        Some(func) => list
+            .iter()
+            .map(|element| func.call(&vec![element.clone()], None))
+            .collect::<Res<Vec<_>>>()?,
+        None => list.to_vec(),
+    };
+
+    let keys = if casefold {
+        keys.into_iter()
+            .map(|key| match key.get_str() {
+                Some(s) => Object::from(s.to_lowercase()),
+                None => key,
+            })
+            .collect()
+    } else {
+        keys
+    };
+
+    // Sort indices rather than elements, so that keys are computed only once
+    let mut error = None;
+    let mut indices: Vec<usize> = (0..list.len()).collect();
+    indices.sort_by(|&i, &j| {
+        let ordering = keys[i].partial_cmp(&keys[j]).unwrap_or_else(|| {
+            error.get_or_insert_with(|| {
+                Error::new(TypeMismatch::BinOp(
+                    keys[i].type_of(),
+                    keys[j].type_of(),
+                    BinOp::Eager(EagerOp::Less),
+                ))
+            });
+            Ordering::Equal
+        });
+        if reverse {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    });
+
+    match error {
+        Some(error) => Err(error),
+        None => Ok(indices.into_iter().map(|i| list[i].clone()).collect()),
+    }
+}
+
+/// Add two integers with fixed 64-bit semantics, wrapping around on overflow
+/// (two's complement), so that `wrapping_add(9223372036854775807, 1)` is
+/// `-9223372036854775808`. Unlike `+`, this never produces integers outside
+/// the 64-bit range.
+fn wrapping_add(args: &List, _: Option<&Map>) -> Res<Object> {
+    signature!(args = [x: int, y: int] {
+        return Ok(Object::from(x.add(y).wrap()))
+    });
+
+    signature!(args = [_x: int, y: any] { expected_pos!(1, y, Integer) });
+    signature!(args = [x: any, _y: any] { expected_pos!(0, x, Integer) });
+
+    argcount!(2, args)
+}
+
+/// Add two integers with fixed 64-bit semantics, returning null if the sum
+/// doesn't fit in 64 bits.
+fn checked_add(args: &List, _: Option<&Map>) -> Res<Object> {
+    signature!(args = [x: int, y: int] {
+        let sum = x.add(y);
+        if i64::try_from(&sum).is_err() {
+            return Ok(Object::null())
+        }
+        return Ok(Object::from(sum))
+    });
+
+    signature!(args = [_x: int, y: any] { expected_pos!(1, y, Integer) });
+    signature!(args = [x: any, _y: any] { expected_pos!(0, x, Integer) });
+
+    argcount!(2, args)
+}
+
+/// Compute the exponential function. This supports two signatures:
+///
+/// `exp(x)` is equivalent to `exp(x, base: 2.71828...)` while `exp(x, base: y)`
+/// computes y to the power x (which is the same as `y^x`).
+fn exp(args: &List, kwargs: Option<&Map>) -> Res<Object> {
+    signature!(args = [exp: tofloat] kwargs = {base: tofloat} {
+        return Ok(Object::from(base.powf(exp)))
+    });
+
+    signature!(args = [_x: tofloat] kwargs = {base: any} { expected_kw!(base, kwargs, Integer, Float) });
+
+    signature!(args = [x: tofloat] {
+        return Ok(Object::from(x.exp()))
+    });
+
+    signature!(args = [x: any] { expected_pos!(0, x, Integer, Float) });
+
+    argcount!(1, args)
+}
+
+/// Compute the logaritm. This supports two signatures:
+///
+/// `log(x)` is equivalent to `log(x, base: 2.71828...)` (the natural logarithm),
+/// while `log(x, base: y)` computes the logarith of `x` to the base `y`.
+fn log(args: &List, kwargs: Option<&Map>) -> Res<Object> {
+    signature!(args = [num: tofloat] kwargs = {base: tofloat} {
+        return Ok(Object::from(num.log(base)))
+    });
+
+    signature!(args = [_x: tofloat] kwargs = {base: any} { expected_kw!(base, kwargs, Integer, Float) });
+
+    signature!(args = [x: tofloat] {
+        return Ok(Object::from(x.ln()))
+    });
+
+    signature!(args = [x: any] { expected_pos!(0, x, Integer, Float) });
+
+    argcount!(1, args)
+}
+
+/// Pad a string with a fill character to a given width (measured in
+/// characters), placing `left` out of every two fill characters on the left.
+fn pad(args: &List, left: fn(usize) -> usize) -> Res<Object> {
+    let (x, width, fill) = match args.as_slice() {
+        [x, width] => (x, width, ' '),
+        [x, width, fill] => {
+            let Some(fill) = fill.get_str() else {
+                expected_pos!(2, fill, String)
+            };
+            let mut chars = fill.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => (x, width, c),
+                (None, _) => return Err(Error::new(Value::TooShort)),
+                _ => return Err(Error::new(Value::TooLong)),
+            }
+        }
+        _ => argcount!(2, 3, args),
+    };
+
+    let Some(x) = x.get_str() else {
+        expected_pos!(0, x, String)
+    };
+    let Some(width) = width.get_int() else {
+        expected_pos!(1, width, Integer)
+    };
+
+    let padding = width.repeat_count()?.saturating_sub(x.chars().count());
+    let len = repeated_len(fill.len_utf8(), padding)?
+        .checked_add(x.len())
+        .ok_or_else(|| Error::new(Value::TooLarge))?;
+    Context::current().check_str_len(len)?;
+
+    let num_left = left(padding);
+    let mut ret = String::new();
+    ret.try_reserve_exact(len)
+        .map_err(|_| Error::new(Value::TooLarge))?;
+    ret.extend(std::iter::repeat_n(fill, num_left));
+    ret.push_str(x);
+    ret.extend(std::iter::repeat_n(fill, padding - num_left));
+    Ok(Object::from(ret))
+}
+
+/// Left-justify a string: `ljust(s, width)` pads `s` on the right with spaces
+/// to the given width, while `ljust(s, width, fill)` pads with another
+/// character. Strings already at least as wide are returned unchanged.
+fn ljust(args: &List, _: Option<&Map>) -> Res<Object> {
+    pad(args, |_| 0)
+}
+
+/// Right-justify a string. Works like `ljust`, but pads on the left.
+fn rjust(args: &List, _: Option<&Map>) -> Res<Object> {
+    pad(args, |n| n)
+}
+
+/// Center a string. Works like `ljust`, but pads on both sides (with the extra
+/// fill character on the right, if any).
+fn center(args: &List, _: Option<&Map>) -> Res<Object> {
+    pad(args, |n| n / 2)
+}
+
+/// Convert a string to lowercase.
+fn lower(args: &List, _: Option<&Map>) -> Res<Object> {
+    signature!(args = [x: str] { return Ok(Object::from(x.to_lowercase())) });
+    signature!(args = [x: any] { expected_pos!(0, x, String) });
+    argcount!(1, args)
+}
+
+/// Convert a string to uppercase.
+fn upper(args: &List, _: Option<&Map>) -> Res<Object> {
+    signature!(args = [x: str] { return Ok(Object::from(x.to_uppercase())) });
+    signature!(args = [x: any] { expected_pos!(0, x, String) });
+    argcount!(1, args)
+}
+
+/// Split a string into a list of strings: `split(s, sep)` splits at each
+/// occurrence of the non-empty separator `sep`, while `split(s)` splits at
+/// runs of whitespace, ignoring leading and trailing whitespace.
+fn split(args: &List, _: Option<&Map>) -> Res<Object> {
+    signature!(args = [x: str] {
+        return Ok(x.split_whitespace().map(Object::from).collect())
+    });
+
+    signature!(args = [x: str, sep: str] {
+        if sep.is_empty() {
+            return Err(Error::new(Value::OutOfRange))
+        }
+        return Ok(x.split(sep).map(Object::from).collect())
+    });
+
+    signature!(args = [x: any] { expected_pos!(0, x, String) });
+    signature!(args = [_x: str, sep: any] { expected_pos!(1, sep, String) });
+    signature!(args = [x: any, _sep: any] { expected_pos!(0, x, String) });
+
+    argcount!(1, 2, args)
+}
+
+/// Maximal number of compiled regular expressions to keep in [`REGEX_CACHE`].
+const REGEX_CACHE_SIZE: usize = 256;
+
+lazy_static! {
+    /// Cache of compiled regular expressions, keyed by pattern.
+    static ref REGEX_CACHE: Mutex<HashMap<String, Regex>> = Mutex::new(HashMap::new());
+}
+
+/// Compile a regular expression, or fetch it from the cache if it has been
+/// compiled before. The cache is emptied when it grows too large.
+fn regex(pattern: &str) -> Res<Regex> {
+    let mut cache = REGEX_CACHE.lock().unwrap();
+    if let Some(regex) = cache.get(pattern) {
+        return Ok(regex.clone());
+    }
+
+    let regex = Regex::new(pattern).map_err(|e| {
+        // Syntax errors span several lines, pointing out the error in the
+        // pattern, followed by the actual message
+        let msg = e.to_string();
+        let msg = msg.lines().last().unwrap_or_default();
+        Error::new(Value::Regex(msg.trim_start_matches("error: ").to_owned()))
+    })?;
+    if cache.len() >= REGEX_CACHE_SIZE {
+        cache.clear();
+    }
+    cache.insert(pattern.to_owned(), regex.clone());
+    Ok(regex)
+}
+
+/// Search a string for a regular expression. Returns null if there is no
+/// match, or else a list of the whole match followed by all capture groups,
+/// with null for groups that didn't participate.
+fn regex_match(args: &List, _: Option<&Map>) -> Res<Object> {
+    signature!(args = [x: str, pattern: str] {
+        let regex = regex(pattern)?;
+        return Ok(regex.captures(x).map_or_else(Object::null, |captures| {
+            captures
+                .iter()
+                .map(|group| group.map_or_else(Object::null, |m| Object::from(m.as_str())))
+                .collect()
+        }))
+    });
+
+    signature!(args = [_x: str, pattern: any] { expected_pos!(1, pattern, String) });
+    signature!(args = [x: any, _pattern: any] { expected_pos!(0, x, String) });
+
+    argcount!(2, args)
+}
+
+/// Replace all matches of a regular expression in a string. The replacement
+/// may refer to capture groups with `$1`, `$name` or `${name}`.
+fn regex_replace(args: &List, _: Option<&Map>) -> Res<Object> {
+    signature!(args = [x: str, pattern: str, replacement: str] {
+        let regex = regex(pattern)?;
+        return Ok(Object::from(regex.replace_all(x, replacement).into_owned()))
+    });
+
+    signature!(args = [_x: str, _pattern: str, replacement: any] {
+        expected_pos!(2, replacement, String)
+    });
+    signature!(args = [_x: str, pattern: any, _replacement: any] {
+        expected_pos!(1, pattern, String)
+    });
+    signature!(args = [x: any, _pattern: any, _replacement: any] { expected_pos!(0, x, String) });
+
+    argcount!(3, args)
+}
+
+/// Split a string at each match of a regular expression. Unlike `split`, the
+/// pattern may match the empty string. Such matches separate adjacent
+/// characters, and don't cause infinite loops.
+fn regex_split(args: &List, _: Option<&Map>) -> Res<Object> {
+    signature!(args = [x: str, pattern: str] {
+        let regex = regex(pattern)?;
+        return Ok(regex.split(x).map(Object::from).collect())
+    });
+
+    signature!(args = [_x: str, pattern: any] { expected_pos!(1, pattern, String) });
+    signature!(args = [x: any, _pattern: any] { expected_pos!(0, x, String) });
+
+    argcount!(2, args)
+}
+
+/// Return the unicode codepoint corresponding to a single-character string.
+fn ord(args: &List, _: Option<&Map>) -> Res<Object> {
+    signature!(args = [x: str] {
+        let mut chars = x.chars();
+        let c = chars.next();
+        if c.is_none() || chars.next().is_some() {
+            return Err(Error::new(Value::TooLong))
+        }
+        return Ok(Object::from(c.unwrap() as i64))
+    });
+
+    signature!(args = [x: any] { expected_pos!(0, x, String) });
+
+    argcount!(1, args)
+}
+
+/// Return the character (as a single-character string) that corresponds to
+/// a unicode codepoint.
+fn chr(args: &List, _: Option<&Map>) -> Res<Object> {
+    signature!(args = [x: int] {
+        let codepoint = u32::try_from(x).map_err(|_| Error::new(Value::OutOfRange))?;
+        let c = char::try_from(codepoint).map_err(|_| Error::new(Value::OutOfRange))?;
+        return Ok(Object::from(c.to_string()))
+    });
+
+    signature!(args = [x: any] { expected_pos!(0, x, Integer) });
+
+    argcount!(1, args)
+}
+
+/// Return the number of positional arguments a function accepts, as a list
+/// `[min, max]`, where `max` is null if there is no upper bound.
+fn arity(args: &List, _: Option<&Map>) -> Res<Object> {
+    signature!(args = [f: func] {
+        let (min, max) = f.arity();
+        return Ok(Object::from(vec![
+            Object::from(min),
+            max.map(Object::from).unwrap_or_else(Object::null),
+        ]))
+    });
+
+    signature!(args = [f: any] { expected_pos!(0, f, Function) });
+
+    argcount!(1, args)
+}
+
+/// Check whether the argument is an integer.
+fn isint(args: &List, _: Option<&Map>) -> Res<Object> {
+    signature!(args = [_x: int] { return Ok(Object::from(true)); });
+    signature!(args = [_x: any] { return Ok(Object::from(false)); });
     argcount!(1, args)
 }
 
@@ -431,3 +1899,204 @@ fn isfunc(args: &List, _: Option<&Map>) -> Res<Object> {
     signature!(args = [_x: any] { return Ok(Object::from(false)); });
     argcount!(1, args)
 }
+
+/// Validate a value against a schema, returning the value unchanged if it
+/// conforms, or raising an error locating the first offending element.
+///
+/// A schema is a map. The key `type` may restrict the type to one of `int`,
+/// `float`, `number` (int or float), `str`, `bool`, `null`, `list`, `map`,
+/// `function` or `any` (the default). In addition:
+///
+/// - numbers may be bounded with `min` and `max` (both inclusive),
+/// - lists may have a schema `items` for all their elements,
+/// - maps may have `fields`, a map of keys to schemas. Fields are required
+///   unless their schema has `optional: true`, and other keys are allowed.
+///
+/// A schema without a `type` key is shorthand for a map with the given fields,
+/// so that `{a: {type: "int"}}` is equivalent to `{type: "map", fields: {a:
+/// {type: "int"}}}`.
+fn check(args: &List, _: Option<&Map>) -> Res<Object> {
+    signature!(args = [x: any, schema: map] {
+        check_schema(x, &schema, "")?;
+        return Ok(x.clone())
+    });
+
+    signature!(args = [_x: any, schema: any] { expected_pos!(1, schema, Map) });
+
+    argcount!(2, args)
+}
+
+/// Validate a value against a schema (see [`check`]). The path of the value
+/// within the top-level checked value is used for error messages.
+fn check_schema(x: &Object, schema: &Map, path: &str) -> Res<()> {
+    let fail = |reason: String| {
+        Err(Error::new(Value::Check {
+            path: path.to_owned(),
+            reason,
+        }))
+    };
+    let field = |name: &str| schema.get(&Key::new(name));
+
+    let Some(kind) = field("type") else {
+        return check_fields(x, schema, path);
+    };
+    let Some(kind) = kind.get_str() else {
+        return Err(Error::new(Value::Schema(
+            "type must be a string".to_owned(),
+        )));
+    };
+
+    let allowed: &[Type] = match kind {
+        "any" => &[],
+        "int" => &[Type::Integer],
+        "float" => &[Type::Float],
+        "number" => &[Type::Integer, Type::Float],
+        "str" => &[Type::String],
+        "bool" => &[Type::Boolean],
+        "null" => &[Type::Null],
+        "list" => &[Type::List],
+        "map" => &[Type::Map],
+        "function" => &[Type::Function],
+        _ => {
+            return Err(Error::new(Value::Schema(format!(
+                "unknown type '{}'",
+                kind
+            ))))
+        }
+    };
+    if !allowed.is_empty() && !allowed.contains(&x.type_of()) {
+        return fail(format!("expected {}, found {}", kind, x.type_of()));
+    }
+
+    if let Some(min) = field("min") {
+        if x.partial_cmp(min).is_none_or(|o| o == Ordering::Less) {
+            return fail(format!("expected at least {}, found {}", min, x));
+        }
+    }
+    if let Some(max) = field("max") {
+        if x.partial_cmp(max).is_none_or(|o| o == Ordering::Greater) {
+            return fail(format!("expected at most {}, found {}", max, x));
+        }
+    }
+
+    if let Some(items) = field("items") {
+        let Some(items) = items.get_map() else {
+            return Err(Error::new(Value::Schema("items must be a map".to_owned())));
+        };
+        if let Some(list) = x.get_list() {
+            for (i, element) in list.iter().enumerate() {
+                check_schema(element, &items, &format!("{}[{}]", path, i))?;
+            }
+        }
+    }
+
+    if let Some(fields) = field("fields") {
+        let Some(fields) = fields.get_map() else {
+            return Err(Error::new(Value::Schema("fields must be a map".to_owned())));
+        };
+        check_fields(x, &fields, path)?;
+    }
+
+    Ok(())
+}
+
+/// Validate that a value is a map with the given fields (see [`check`]).
+fn check_fields(x: &Object, fields: &Map, path: &str) -> Res<()> {
+    let Some(map) = x.get_map() else {
+        return Err(Error::new(Value::Check {
+            path: path.to_owned(),
+            reason: format!("expected map, found {}", x.type_of()),
+        }));
+    };
+
+    for (key, schema) in fields.iter() {
+        let Some(schema) = schema.get_map() else {
+            return Err(Error::new(Value::Schema(format!(
+                "schema for '{}' must be a map",
+                key
+            ))));
+        };
+        let subpath = if path.is_empty() {
+            key.as_str().to_owned()
+        } else {
+            format!("{}.{}", path, key)
+        };
+        match map.get(key) {
+            Some(value) => check_schema(value, &schema, &subpath)?,
+            None if schema
+                .get(&Key::new("optional"))
+                .is_some_and(Object::truthy) => {}
+            None => {
+                return Err(Error::new(Value::Check {
+                    path: subpath,
+                    reason: "missing field".to_owned(),
+                }))
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Builtins that give access to the host system, keyed by name.
+///
+/// The versions of these in the builtin table always fail. When a capability
+/// is enabled, the working version is made available under the same name,
+/// shadowing the disabled one.
+pub(crate) fn capability_builtins(importer: &ImportConfig) -> Map {
+    let capabilities = importer.capabilities();
+    let mut builtins = Map::new();
+    if capabilities.env {
+        let key = Key::new("env");
+        let func = Builtin::new(read_env, key).params(1, Some(2));
+        builtins.insert(key, Object::new_func(func));
+    }
+    if capabilities.read_file {
+        let importer = importer.clone();
+        let func: Rc<NativeClosure> = Rc::new(move |args, _| read_file_with(&importer, args));
+        builtins.insert(Key::new("read_file"), Object::new_func(func));
+    }
+    builtins
+}
+
+/// Read an environment variable (disabled version, see [`read_env`]).
+fn env(_: &List, _: Option<&Map>) -> Res<Object> {
+    Err(Error::new(Reason::Disabled(Key::new("env"))))
+}
+
+/// Read an environment variable. `env(name)` returns null if the variable is
+/// unset, while `env(name, default)` returns `default` instead.
+fn read_env(args: &List, _: Option<&Map>) -> Res<Object> {
+    signature!(args = [name: str] { return lookup_env(name, Object::null()) });
+    signature!(args = [name: str, default: any] { return lookup_env(name, default.clone()) });
+
+    signature!(args = [name: any] { expected_pos!(0, name, String) });
+    signature!(args = [name: any, _default: any] { expected_pos!(0, name, String) });
+
+    argcount!(1, 2, args)
+}
+
+fn lookup_env(name: &str, default: Object) -> Res<Object> {
+    match std::env::var(name) {
+        Ok(value) => Ok(Object::from(value)),
+        Err(std::env::VarError::NotPresent) => Ok(default),
+        Err(std::env::VarError::NotUnicode(_)) => Err(Error::new(Value::Convert(Type::String))),
+    }
+}
+
+/// Read a file as a string (disabled version, see [`read_file_with`]).
+fn read_file(_: &List, _: Option<&Map>) -> Res<Object> {
+    Err(Error::new(Reason::Disabled(Key::new("read_file"))))
+}
+
+/// Read a file as a string. The path is resolved by the import config, and
+/// must be relative to its root path.
+fn read_file_with(importer: &ImportConfig, args: &List) -> Res<Object> {
+    signature!(args = [path: str] {
+        return importer.read_file(path).map(Object::from)
+    });
+
+    signature!(args = [path: any] { expected_pos!(0, path, String) });
+
+    argcount!(1, args)
+}
@@ -10,7 +10,7 @@ use crate::ast::low::{
 use crate::ast::{BindingLoc, SlotCatalog, SlotType};
 use crate::error::{Action, IntervalTree, Reason, Span, Tagged, Unpack};
 use crate::formatting::FormatSpec;
-use crate::types::{BinOp, Key, LogicOp, Res};
+use crate::types::{BinOp, EagerOp, Key, LogicOp, Res};
 use crate::Object;
 
 #[derive(Clone, Debug, Serialize, Deserialize, Trace, Finalize)]
@@ -30,6 +30,10 @@ pub struct CompiledFunction {
     pub num_locals: usize,
     pub num_cells: usize,
 
+    /// Minimal and maximal (if any) number of positional arguments.
+    #[unsafe_ignore_trace]
+    pub arity: (usize, Option<usize>),
+
     #[unsafe_ignore_trace]
     pub trace: IntervalTree<usize, (Span, Action), Reason>,
 }
@@ -106,6 +110,10 @@ pub enum Instruction {
     /// Interchange the top two objects on the stack.
     Interchange,
 
+    /// Clone the object on the top of the stack and insert it below the
+    /// second object, so that `[x, y]` becomes `[y, x, y]`.
+    Tuck,
+
     /// Call the function at `stack[2]` using `stack[0]` as arguments (must be a
     /// list) and `stack[1]` as keyword arguments (must be a map).
     Call,
@@ -123,6 +131,11 @@ pub enum Instruction {
     /// given minimum length and at most the given maximum length.
     AssertListMinMaxLength(usize, usize),
 
+    /// Throw an error unless the top of the stack is a list of positional
+    /// arguments with at least the given minimum length and, if given, at most
+    /// the given maximum length.
+    AssertArgCount(usize, Option<usize>),
+
     /// Throw an error unless the top of the stack is a map.
     AssertMap,
 
@@ -183,11 +196,20 @@ pub enum Instruction {
     /// Pop y and x from the stack, then push `x has y`.
     Contains,
 
+    /// Pop y and x from the stack, then push `y has x`.
+    In,
+
+    /// Pop y and x from the stack, then push `not (y has x)`.
+    NotIn,
+
     // Other operators
     // ------------------------------------------------------------------------------------------------
     /// Pop y and x from the stack, then push `x[y]`.
     Index,
 
+    /// Pop y and x from the stack, then push `x.y`.
+    Access,
+
     // Constructors
     // ------------------------------------------------------------------------------------------------
     /// Push a new empty list on the stack.
@@ -218,6 +240,11 @@ pub enum Instruction {
     /// Remove the key from the map at the top of the stack, if it exists.
     DelKeyIfExists(Key),
 
+    /// If the virtual machine has an output sink, write all elements of the
+    /// collection at the top of the stack to it, and remove them from the
+    /// collection. Otherwise, do nothing.
+    FlushToSink,
+
     /// Load the cell at the given index and push it to the closure at the top
     /// of the stack. Important: the cell must be pushed as-is. It should not be
     /// unwrapped.
@@ -232,6 +259,10 @@ pub enum Instruction {
     /// it on the stack. If there are no more elements, jump the given delta.
     NextOrJump(usize),
 
+    /// Like [`Instruction::NextOrJump`], but also push the index of the
+    /// element on the stack, after the element itself.
+    NextIndexedOrJump(usize),
+
     // Shortcuts for internal use
     // ------------------------------------------------------------------------------------------------
     /// Get the element from the list at the top of the stack at the given
@@ -419,6 +450,11 @@ trait WrapCompiler: Sized {
         Ok(self)
     }
 
+    fn emit_chain(mut self, links: Vec<(Tagged<EagerOp>, Tagged<Expr>)>) -> Res<Self> {
+        self.compiler().emit_chain(links)?;
+        Ok(self)
+    }
+
     fn store_instruction(mut self, index: usize) -> Option<Self> {
         self.compiler().store_instruction(index)?;
         Some(self)
@@ -516,6 +552,11 @@ pub struct Compiler {
 
     num_locals: usize,
     num_cells: usize,
+    arity: (usize, Option<usize>),
+
+    /// Whether to flush collection elements as they are emitted (see
+    /// [`Compiler::emit_streamed`]).
+    stream: bool,
 
     actions: Vec<(usize, usize, Span, Action)>,
     reasons: Vec<(usize, usize, Reason)>,
@@ -530,6 +571,7 @@ impl Compiler {
             fmt_specs,
             positional,
             keywords,
+            stream,
             ..
         } = function;
         let mut compiler = Compiler {
@@ -541,27 +583,99 @@ impl Compiler {
             slots: Vec::new(),
             num_locals: 0,
             num_cells: 0,
+            arity: (0, Some(0)),
+            stream: false,
             actions: Vec::new(),
             reasons: Vec::new(),
         };
         compiler.push_slots(slots);
 
         if let Some(args) = positional {
-            compiler.emit_list_binding(args.unwrap())?;
+            compiler.emit_positional_parameters(args.unwrap())?;
         }
         compiler.instruction(Instruction::Discard);
 
         if let Some(kwargs) = keywords {
-            compiler.emit_map_binding(kwargs.unwrap())?;
+            compiler.instruction(Instruction::AssertMap);
+            compiler.emit_map_binding_elements(kwargs.unwrap(), Unpack::KwargMissing)?;
         }
         compiler.instruction(Instruction::Discard);
 
-        compiler.emit_expression(expression.unwrap())?;
+        if stream {
+            compiler.emit_streamed(expression.unwrap())?;
+        } else {
+            compiler.emit_expression(expression.unwrap())?;
+        }
         compiler.pop_slots();
         Ok(compiler.finalize())
     }
 
+    /// Emit an expression whose value may be written to an output sink while
+    /// it's being built. If the expression is a list or map (possibly in the
+    /// body of let-blocks), each of its elements is flushed to the sink as
+    /// soon as it's been added, including each element produced by a loop.
+    /// Any other expression is emitted as usual.
+    fn emit_streamed(&mut self, expr: Expr) -> Res<usize> {
+        match expr {
+            Expr::Let {
+                bindings,
+                expression,
+                slots,
+            } => {
+                self.push_slots(slots);
+                let mut len = 0;
+                for (binding, expr) in bindings {
+                    len += self.emit_expression(expr.unwrap())?;
+                    len += self.emit_binding(binding)?;
+                }
+                len += self.emit_streamed(expression.unwrap())?;
+                len += self.pop_slots();
+                Ok(len)
+            }
+
+            Expr::List(elements) => {
+                let mut len = self.instruction(Instruction::NewList);
+                self.stream = true;
+                for element in elements {
+                    len += self.emit_list_element(element.unwrap())?;
+                }
+                self.stream = false;
+                Ok(len)
+            }
+
+            Expr::Map(elements) => {
+                let mut len = self.instruction(Instruction::NewMap);
+                self.stream = true;
+                for element in elements {
+                    len += self.emit_map_element(element.unwrap())?;
+                }
+                self.stream = false;
+                Ok(len)
+            }
+
+            expr => self.emit_expression(expr),
+        }
+    }
+
+    /// Emit an instruction to flush the collection at the top of the stack, if
+    /// compiling the elements of a streamed collection.
+    fn flush_instruction(&mut self) -> usize {
+        if self.stream {
+            self.instruction(Instruction::FlushToSink)
+        } else {
+            0
+        }
+    }
+
     fn emit_expression(&mut self, expr: Expr) -> Res<usize> {
+        // Collections nested in a streamed collection are built in full
+        let stream = std::mem::replace(&mut self.stream, false);
+        let len = self.emit_nonstreamed(expr);
+        self.stream = stream;
+        len
+    }
+
+    fn emit_nonstreamed(&mut self, expr: Expr) -> Res<usize> {
         match expr {
             Expr::Constant(index) => Ok(self.instruction(Instruction::LoadConst(index))),
             Expr::Slot(loc) => Ok(self.load_instruction(loc).unwrap()),
@@ -581,6 +695,12 @@ impl Compiler {
                 Ok(len)
             }
 
+            Expr::Chain { operand, links } => {
+                let mut len = self.emit_expression(operand.unwrap())?;
+                len += self.emit_chain(links)?;
+                Ok(len)
+            }
+
             Expr::Branch {
                 condition,
                 true_branch,
@@ -690,18 +810,41 @@ impl Compiler {
     }
 
     fn emit_list_binding(&mut self, binding: ListBinding) -> Res<usize> {
-        let mut len = 0;
-
-        if binding.slurp.is_some() {
-            len += self.instruction(Instruction::AssertListMinLength(
+        let len = if binding.slurp.is_some() {
+            self.instruction(Instruction::AssertListMinLength(
                 binding.num_front + binding.num_back,
-            ));
+            ))
         } else {
-            len += self.instruction(Instruction::AssertListMinMaxLength(
+            self.instruction(Instruction::AssertListMinMaxLength(
                 binding.num_front,
                 binding.num_front + binding.def_front,
-            ));
-        }
+            ))
+        };
+
+        Ok(len + self.emit_list_binding_elements(binding)?)
+    }
+
+    /// Bind the positional parameters of a function. This is like a list
+    /// binding, except with more specific errors on argument count mismatch.
+    fn emit_positional_parameters(&mut self, binding: ListBinding) -> Res<usize> {
+        let max = if binding.slurp.is_some() {
+            None
+        } else {
+            Some(binding.num_front + binding.def_front)
+        };
+        self.arity = (binding.num_front + binding.num_back, max);
+        let len = self.instruction(Instruction::AssertArgCount(
+            binding.num_front + binding.num_back,
+            max,
+        ));
+
+        Ok(len + self.emit_list_binding_elements(binding)?)
+    }
+
+    /// Bind the elements of a list binding, assuming that the list on top of
+    /// the stack has an acceptable length.
+    fn emit_list_binding_elements(&mut self, binding: ListBinding) -> Res<usize> {
+        let mut len = 0;
 
         if let Some(Some(slot)) = binding.slurp {
             len += self.instruction(Instruction::IntSlice {
@@ -747,7 +890,19 @@ impl Compiler {
     }
 
     fn emit_map_binding(&mut self, binding: MapBinding) -> Res<usize> {
-        let mut len = self.instruction(Instruction::AssertMap);
+        let len = self.instruction(Instruction::AssertMap);
+        Ok(len + self.emit_map_binding_elements(binding, Unpack::KeyMissing)?)
+    }
+
+    /// Bind the elements of a map binding, assuming that the object on top of
+    /// the stack is a map. The `missing` function produces the error reason
+    /// for keys without default values that are missing.
+    fn emit_map_binding_elements(
+        &mut self,
+        binding: MapBinding,
+        missing: fn(Key) -> Unpack,
+    ) -> Res<usize> {
+        let mut len = 0;
 
         if let Some(slot) = binding.slurp {
             len += self.instruction(Instruction::Duplicate);
@@ -774,7 +929,7 @@ impl Compiler {
                 let (key, span) = key.decompose();
                 len += self
                     .with_trace(span, Action::Bind)
-                    .reason(Unpack::KeyMissing(key))
+                    .reason(missing(key))
                     .instruction(Instruction::IntIndexM(key))
                     .finalize();
             }
@@ -794,17 +949,17 @@ impl Compiler {
 
             StringElement::Interpolate(expr, spec) => {
                 let (expr, span) = expr.decompose();
-                let mut len = self.emit_expression(expr)?;
-                if let Some(index) = spec {
-                    len += self.instruction(Instruction::FormatWithSpec(index));
-                } else {
-                    len += self.instruction(Instruction::FormatWithDefault);
-                }
-                len += self
-                    .with_trace(span, Action::Format)
-                    .instruction(Instruction::Add)
-                    .finalize();
-                Ok(len)
+                let len = self.emit_expression(expr)?;
+                let format = match spec {
+                    Some(index) => Instruction::FormatWithSpec(index),
+                    None => Instruction::FormatWithDefault,
+                };
+                Ok(len
+                    + self
+                        .with_trace(span, Action::Format)
+                        .instruction(format)
+                        .instruction(Instruction::Add)
+                        .finalize())
             }
         }
     }
@@ -814,6 +969,7 @@ impl Compiler {
             ListElement::Singleton(expr) => {
                 let mut len = self.emit_expression(expr.unwrap())?;
                 len += self.instruction(Instruction::PushToList);
+                len += self.flush_instruction();
                 Ok(len)
             }
 
@@ -824,6 +980,7 @@ impl Compiler {
                     .with_trace(span, Action::Splat)
                     .instruction(Instruction::SplatToCollection)
                     .finalize();
+                len += self.flush_instruction();
                 Ok(len)
             }
 
@@ -838,6 +995,7 @@ impl Compiler {
             }
 
             ListElement::Loop {
+                index,
                 binding,
                 iterable,
                 element,
@@ -853,14 +1011,24 @@ impl Compiler {
 
                 self.push_slots(slots);
 
-                len += self
-                    .with_jump()
+                let indexed = index.is_some();
+                let mut wrapper = self.with_jump();
+                if let Some(index) = index {
+                    wrapper = wrapper.emit_binding(*index)?;
+                }
+                len += wrapper
                     .emit_binding(binding)?
                     .instruction(Instruction::Interchange)
                     .emit_list_element(element.unwrap())?
                     .instruction(Instruction::Interchange)
                     .to_start(Instruction::JumpBack)
-                    .finalize(Instruction::NextOrJump);
+                    .finalize(|delta| {
+                        if indexed {
+                            Instruction::NextIndexedOrJump(delta)
+                        } else {
+                            Instruction::NextOrJump(delta)
+                        }
+                    });
 
                 len += self.instruction(Instruction::Discard);
                 len += self.pop_slots();
@@ -879,6 +1047,7 @@ impl Compiler {
                     .finalize();
                 len += self.emit_expression(value.unwrap())?;
                 len += self.instruction(Instruction::PushToMap);
+                len += self.flush_instruction();
                 Ok(len)
             }
 
@@ -889,6 +1058,7 @@ impl Compiler {
                     .with_trace(span, Action::Splat)
                     .instruction(Instruction::SplatToCollection)
                     .finalize();
+                len += self.flush_instruction();
                 Ok(len)
             }
 
@@ -903,6 +1073,7 @@ impl Compiler {
             }
 
             MapElement::Loop {
+                index,
                 binding,
                 iterable,
                 element,
@@ -918,14 +1089,24 @@ impl Compiler {
 
                 self.push_slots(slots);
 
-                len += self
-                    .with_jump()
+                let indexed = index.is_some();
+                let mut wrapper = self.with_jump();
+                if let Some(index) = index {
+                    wrapper = wrapper.emit_binding(*index)?;
+                }
+                len += wrapper
                     .emit_binding(binding)?
                     .instruction(Instruction::Interchange)
                     .emit_map_element(element.unwrap())?
                     .instruction(Instruction::Interchange)
                     .to_start(Instruction::JumpBack)
-                    .finalize(Instruction::NextOrJump);
+                    .finalize(|delta| {
+                        if indexed {
+                            Instruction::NextIndexedOrJump(delta)
+                        } else {
+                            Instruction::NextOrJump(delta)
+                        }
+                    });
 
                 len += self.instruction(Instruction::Discard);
                 len += self.pop_slots();
@@ -934,6 +1115,44 @@ impl Compiler {
         }
     }
 
+    /// Emit the comparisons of a chain, assuming the leftmost operand is on
+    /// the top of the stack. Each operand except the last is kept on the
+    /// stack for the next comparison, and evaluation stops at the first
+    /// comparison that fails.
+    fn emit_chain(&mut self, mut links: Vec<(Tagged<EagerOp>, Tagged<Expr>)>) -> Res<usize> {
+        let rest = links.split_off(1);
+        let (op, operand) = links.pop().unwrap();
+        let (op, span) = op.decompose();
+
+        let mut len = self.emit_expression(operand.unwrap())?;
+        if rest.is_empty() {
+            len += self
+                .with_trace(span, Action::Evaluate)
+                .instruction(op.instruction())
+                .finalize();
+            return Ok(len);
+        }
+
+        len += self.instruction(Instruction::Tuck);
+        len += self
+            .with_trace(span, Action::Evaluate)
+            .instruction(op.instruction())
+            .finalize();
+
+        // On failure, drop the kept operand and leave the result. On success,
+        // drop the result and continue with the kept operand.
+        len += self.instruction(Instruction::Duplicate);
+        len += self.instruction(Instruction::CondJump(3));
+        len += self.instruction(Instruction::Interchange);
+        len += self.instruction(Instruction::Discard);
+        len += self
+            .with_jump()
+            .instruction(Instruction::Discard)
+            .emit_chain(rest)?
+            .finalize(Instruction::Jump);
+        Ok(len)
+    }
+
     fn emit_transform(&mut self, transform: Transform) -> Res<usize> {
         match transform {
             Transform::UnOp(op) => {
@@ -1171,6 +1390,7 @@ impl Compiler {
             import_paths: self.import_paths,
             num_locals: self.num_locals,
             num_cells: self.num_cells,
+            arity: self.arity,
             trace,
         }
     }
@@ -13,8 +13,8 @@ use pyo3::PyErr;
 
 #[cfg(feature = "python")]
 use pyo3::exceptions::{
-    PyException, PyImportError, PyKeyError, PyNameError, PyOSError, PySyntaxError, PyTypeError,
-    PyValueError,
+    PyException, PyImportError, PyKeyError, PyNameError, PyOSError, PyPermissionError,
+    PySyntaxError, PyTypeError, PyValueError,
 };
 
 use crate::lexing::TokenType;
@@ -47,21 +47,22 @@ impl Position {
         }
     }
 
-    /// Add a positive displacement to a position and return a new one.
+    /// Advance a position past the given text and return a new one.
     ///
-    /// Use `adjust(offset, 0)` to move within a line. Use `adjuct(offset, n)`
-    /// to move to the beginning of a line.
+    /// Use `adjust(text, 0)` to move within a line. Use `adjust(text, n)` to
+    /// move to the beginning of a line, where the text ends with a newline.
+    /// Columns count characters, not bytes.
     ///
     /// Do NOT use this method to jump to the middle of a new line. To do that,
     /// compose two calls to `adjust`.
-    pub fn adjust(&self, offset: usize, delta_line: u32) -> Position {
+    pub fn adjust(&self, text: &str, delta_line: u32) -> Position {
         Position {
-            offset: self.offset + offset,
+            offset: self.offset + text.len(),
             line: self.line + delta_line,
             column: if delta_line > 0 {
                 0
             } else {
-                self.column + offset as u32
+                self.column + text.chars().count() as u32
             },
         }
     }
@@ -81,11 +82,17 @@ impl Position {
         self.column
     }
 
-    /// Return a new span starting at this position with a certain length.
+    /// Return a new span starting at this position with a certain length,
+    /// assuming it doesn't cross a line boundary. Use subtraction of
+    /// positions to construct spans that might.
     pub fn with_length(&self, length: usize) -> Span {
         Span {
             start: *self,
-            length,
+            end: Position {
+                offset: self.offset + length,
+                line: self.line,
+                column: self.column + length as u32,
+            },
         }
     }
 
@@ -113,15 +120,26 @@ impl Sub<Position> for Position {
 
     /// Create a span marking the interval between two positions.
     fn sub(self, rhs: Position) -> Self::Output {
-        rhs.with_length(self.offset - rhs.offset)
+        Span {
+            start: rhs,
+            end: self,
+        }
     }
 }
 
-/// Mark an interval of text in a buffer starting at a `Position` with a length.
-#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+/// Mark an interval of text in a buffer between two `Position`s.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct Span {
     start: Position,
-    length: usize,
+    end: Position,
+}
+
+/// Spans are equal if they cover the same text. The line and column of the end
+/// are determined by the buffer, so they don't participate.
+impl PartialEq for Span {
+    fn eq(&self, other: &Self) -> bool {
+        self.start == other.start && self.length() == other.length()
+    }
 }
 
 impl Span {
@@ -131,38 +149,56 @@ impl Span {
     }
 
     /// The offset of the start of the span into the buffer.
-    fn offset(&self) -> usize {
+    pub fn offset(&self) -> usize {
         self.start.offset()
     }
 
     /// The zero-indexed line number of the start of the span.
-    fn line(&self) -> u32 {
+    pub fn line(&self) -> u32 {
         self.start.line()
     }
 
     /// The zero-indexed column number of the start of the span.
-    fn column(&self) -> u32 {
+    pub fn column(&self) -> u32 {
         self.start.column()
     }
 
+    /// The zero-indexed line number of the end of the span (exclusive).
+    pub fn end_line(&self) -> u32 {
+        self.end.line()
+    }
+
+    /// The zero-indexed column number of the end of the span (exclusive).
+    pub fn end_column(&self) -> u32 {
+        self.end.column()
+    }
+
     /// The length of the span.
     pub fn length(&self) -> usize {
-        self.length
+        self.end.offset() - self.start.offset()
     }
 
-    /// Return a new span by changing the line number.
+    /// Return a new span by changing the line number of the start. The end
+    /// moves by the same number of lines.
     fn with_line(self, line: u32) -> Self {
         Span {
             start: self.start.with_line(line),
-            length: self.length,
+            end: self.end.with_line(line + (self.end.line - self.start.line)),
         }
     }
 
-    /// Return a new span by changing the column number.
+    /// Return a new span by changing the column number of the start. If the
+    /// span ends on the same line, the end moves by the same number of columns.
     fn with_column(self, col: u32) -> Self {
+        let end = if self.end.line == self.start.line {
+            self.end
+                .with_column(col + (self.end.column - self.start.column))
+        } else {
+            self.end
+        };
         Span {
             start: self.start.with_column(col),
-            length: self.length,
+            end,
         }
     }
 
@@ -171,22 +207,19 @@ impl Span {
         self.with_line(line).with_column(col)
     }
 
+    /// Return a new span with the same start and a certain length, assuming
+    /// it doesn't cross a line boundary.
     pub fn with_length(self, length: usize) -> Self {
-        Span {
-            start: self.start,
-            length: length,
-        }
+        self.start.with_length(length)
     }
 }
 
 impl From<Range<u32>> for Span {
     /// Convert a range of offsets to a text span, assuming the interval begins
-    /// on the first line. Use `with_line` if not.
+    /// and ends on the first line. Use `with_line` if not.
     fn from(value: Range<u32>) -> Self {
-        Span {
-            start: Position::new(value.start as usize, 0, value.start),
-            length: (value.end - value.start) as usize,
-        }
+        Position::new(value.start as usize, 0, value.start)
+            .with_length((value.end - value.start) as usize)
     }
 }
 
@@ -194,10 +227,7 @@ impl From<usize> for Span {
     /// Convert an offset to a text span with length one, assuming the interval
     /// begins on the first line. Use `with_line` if not.
     fn from(value: usize) -> Self {
-        Span {
-            start: Position::new(value, 0, value as u32),
-            length: 1,
-        }
+        Position::new(value, 0, value as u32).with_length(1)
     }
 }
 
@@ -211,7 +241,7 @@ impl From<Range<Span>> for Span {
     fn from(Range { start, end }: Range<Span>) -> Self {
         Span {
             start: start.start(),
-            length: end.offset() + end.length() - start.offset(),
+            end: end.end,
         }
     }
 }
@@ -251,7 +281,8 @@ impl<T> Tagged<T> {
         (self.contents, self.span)
     }
 
-    /// Wrapper for [`Span::with_coord`].
+    /// Return a new tagged wrapper with the line and column numbers of the
+    /// span changed.
     pub fn with_coord(self, line: u32, col: u32) -> Tagged<T> {
         let loc = self.span.with_coord(line, col);
         self.retag(loc)
@@ -324,9 +355,19 @@ impl<T: Debug> Debug for Tagged<T> {
         self.contents.fmt(f)?;
         let span = self.span;
         f.write_fmt(format_args!(
-            ".tag({}:{}, {}..{})",
+            ".tag({}:{}",
             span.line() + 1,
-            span.column() + 1,
+            span.column() + 1
+        ))?;
+        if span.end_line() != span.line() {
+            f.write_fmt(format_args!(
+                "-{}:{}",
+                span.end_line() + 1,
+                span.end_column() + 1
+            ))?;
+        }
+        f.write_fmt(format_args!(
+            ", {}..{})",
             span.offset(),
             span.offset() + span.length()
         ))
@@ -499,6 +540,19 @@ pub enum Syntax {
 
     /// Non-default followed by default in list binding (thrown by the validator)
     DefaultSequence,
+
+    /// The same key appears twice in a map literal (thrown by the validator)
+    DuplicateKey(Key),
+
+    /// The same name is bound twice in one pattern (thrown by the validator)
+    DuplicateBinding(Key),
+
+    /// A name declared with `final` is bound again in its scope (thrown by the
+    /// validator)
+    ShadowFinal(Key),
+
+    /// An integer literal has too many digits (thrown by the parser)
+    NumberTooLong,
 }
 
 impl<T> From<T> for Syntax
@@ -586,6 +640,9 @@ pub enum Internal {
 
     /// Attempted to use the append operation on a non-list. (014)
     AppendNotList,
+
+    /// A module body evaluated to something other than a map. (015)
+    ModuleNotMap,
 }
 
 impl Internal {
@@ -613,6 +670,7 @@ impl Internal {
             Self::InsertNotMap => 12,
             Self::NextNotIterator => 13,
             Self::AppendNotList => 14,
+            Self::ModuleNotMap => 15,
         }
     }
 }
@@ -642,6 +700,17 @@ pub enum Unpack {
     /// The map was missing a key.
     KeyMissing(Key),
 
+    /// A function was called with the wrong number of positional arguments.
+    /// If there's no upper limit, `high` is `usize::MAX`.
+    ArgCount {
+        low: usize,
+        high: usize,
+        received: usize,
+    },
+
+    /// A function was called without a required keyword argument.
+    KwargMissing(Key),
+
     /// The binding type did not correspond to the object type (e.g. a list
     /// binding with a map object).
     TypeMismatch(BindingType, Type),
@@ -697,6 +766,9 @@ impl From<(Type, Type, Type, Type)> for Types {
 }
 
 /// Enumerates different type mismatch reasons.
+///
+/// These refer to the types of the offending values, never to the values
+/// themselves, so that error messages stay short even for large objects.
 #[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
 pub enum TypeMismatch {
     /// Attempted to iterate over a non-iterable.
@@ -714,6 +786,9 @@ pub enum TypeMismatch {
     /// Attempted to use a non-string as a map key.
     MapKey(Type),
 
+    /// Attempted to hash an unhashable type.
+    Hash(Type),
+
     /// Attempted to string interpolate an exotic type.
     Interpolate(Type),
 
@@ -726,6 +801,9 @@ pub enum TypeMismatch {
     /// A type was incompatible with a unary operator.
     UnOp(Type, UnOp),
 
+    /// Attempted to index a type with an unsuitable key type.
+    Index(Type, Type),
+
     /// Attempted to call a non-function.
     Call(Type),
 
@@ -744,6 +822,18 @@ pub enum TypeMismatch {
         received: Type,
     },
 
+    /// Expected a list element to have a certain type, but it didn't.
+    ExpectedElement {
+        /// The zero-based index of the element.
+        index: usize,
+
+        /// Allowed types.
+        allowed: Types,
+
+        /// Actual type of the element.
+        received: Type,
+    },
+
     /// Expected a keyword function parameter to have a certain type, but it didn't.
     ExpectedKwarg {
         /// The name of the parameter.
@@ -770,7 +860,7 @@ pub enum TypeMismatch {
 }
 
 /// Enumerates different value-based error reasons.
-#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Value {
     /// Value was out of range.
     OutOfRange,
@@ -781,8 +871,40 @@ pub enum Value {
     /// Value was too long
     TooLong,
 
+    /// Value was too short
+    TooShort,
+
+    /// A key was given more than once in a map that was written out while
+    /// being built, so the first entry couldn't be replaced.
+    DuplicateKey(Key),
+
     /// Unable to convert value to a given type.
     Convert(Type),
+
+    /// Evaluation exceeded the maximal number of steps.
+    TooManySteps,
+
+    /// Evaluation exceeded the maximal recursion depth.
+    TooDeep,
+
+    /// Evaluation did not finish before the deadline.
+    DeadlineExceeded,
+
+    /// Division (or integer division) by zero.
+    DivisionByZero,
+
+    /// Unable to serialize value, e.g. because it contains a native function.
+    Serialize,
+
+    /// Invalid regular expression, with the error message from compiling it.
+    Regex(String),
+
+    /// A value didn't conform to a schema. The path locates the offending
+    /// element within the checked value, and is empty for the value itself.
+    Check { path: String, reason: String },
+
+    /// A schema was malformed.
+    Schema(String),
 }
 
 /// Enumerates different file system error reasons.
@@ -794,6 +916,9 @@ pub enum FileSystem {
 
     /// Unable to read from file.
     Read(PathBuf),
+
+    /// Unable to write output.
+    Write,
 }
 
 /// Grand enumeration of all possible error reasons.
@@ -811,6 +936,9 @@ pub enum Reason {
     /// A key was not assigned to a value.
     Unassigned(Key),
 
+    /// Attempted to index a container with a key it doesn't have.
+    NoField(Key, Type),
+
     /// Unpacking (pattern matching) error.
     Unpack(Unpack),
 
@@ -832,6 +960,13 @@ pub enum Reason {
 
     /// Import errors.
     UnknownImport(String),
+
+    /// An import path resolved to more than one file.
+    AmbiguousImport(String),
+
+    /// A builtin that requires a capability was called without it being
+    /// enabled.
+    Disabled(Key),
 }
 
 impl From<Syntax> for Reason {
@@ -870,6 +1005,14 @@ impl From<Value> for Reason {
     }
 }
 
+/// Enumerates lint warnings: code that is valid, but possibly a mistake.
+/// Warnings never prevent evaluation.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Warning {
+    /// A binding shadows an earlier binding with the same name.
+    Shadowing(Key),
+}
+
 /// Enumerates all different 'actions' - things that Gold might try to do which
 /// can cause an error.
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
@@ -1019,6 +1162,7 @@ impl Error {
             Some(Reason::Syntax(_)) => PySyntaxError::new_err(pystr),
             Some(Reason::Unbound(_)) => PyNameError::new_err(pystr),
             Some(Reason::Unassigned(_)) => PyKeyError::new_err(pystr),
+            Some(Reason::NoField(_, _)) => PyKeyError::new_err(pystr),
             Some(Reason::Unpack(_)) => PyTypeError::new_err(pystr),
             Some(Reason::Internal(_)) => PyException::new_err(pystr),
             Some(Reason::External(_)) => PyException::new_err(pystr),
@@ -1026,6 +1170,8 @@ impl Error {
             Some(Reason::Value(_)) => PyValueError::new_err(pystr),
             Some(Reason::FileSystem(_)) => PyOSError::new_err(pystr),
             Some(Reason::UnknownImport(_)) => PyImportError::new_err(pystr),
+            Some(Reason::AmbiguousImport(_)) => PyImportError::new_err(pystr),
+            Some(Reason::Disabled(_)) => PyPermissionError::new_err(pystr),
         }
     }
 }
@@ -1068,16 +1214,13 @@ impl Display for BindingType {
     }
 }
 
-fn fmt_expected_arg(
+fn fmt_expected_type(
     f: &mut std::fmt::Formatter,
-    name: impl Display,
+    subject: impl Display,
     allowed: &Vec<Type>,
     received: &Type,
 ) -> std::fmt::Result {
-    f.write_fmt(format_args!(
-        "unsuitable type for parameter {} - expected ",
-        name
-    ))?;
+    f.write_fmt(format_args!("unsuitable type for {} - expected ", subject))?;
     match allowed[..] {
         [] => {}
         [t] => f.write_fmt(format_args!("{}", t))?,
@@ -1113,14 +1256,52 @@ impl Display for Reason {
             Self::Syntax(Syntax::DefaultSequence) => {
                 f.write_str("binding without default value follows binding with default value")
             }
+            Self::Syntax(Syntax::DuplicateKey(key)) => {
+                f.write_fmt(format_args!("duplicate key '{}' in map", key))
+            }
+            Self::Syntax(Syntax::DuplicateBinding(key)) => {
+                f.write_fmt(format_args!("name '{}' is bound more than once", key))
+            }
+            Self::Syntax(Syntax::ShadowFinal(key)) => {
+                f.write_fmt(format_args!("final name '{}' may not be shadowed", key))
+            }
+            Self::Syntax(Syntax::NumberTooLong) => f.write_str("integer literal too long"),
 
             Self::Unbound(key) => f.write_fmt(format_args!("unbound name '{}'", key)),
 
             Self::Unassigned(key) => f.write_fmt(format_args!("unbound key '{}'", key)),
 
+            Self::NoField(key, x) => f.write_fmt(format_args!("no field \"{}\" on {}", key, x)),
+
             Self::Unpack(Unpack::KeyMissing(key)) => {
                 f.write_fmt(format_args!("unbound key '{}'", key))
             }
+            Self::Unpack(Unpack::KwargMissing(key)) => {
+                f.write_fmt(format_args!("missing keyword argument '{}'", key))
+            }
+            Self::Unpack(Unpack::ArgCount {
+                low,
+                high,
+                received,
+            }) => {
+                let expected = if low == high {
+                    format!("{}", low)
+                } else if *high == usize::MAX {
+                    format!("at least {}", low)
+                } else {
+                    format!("{} to {}", low, high)
+                };
+                f.write_fmt(format_args!(
+                    "{} positional argument (expected {}, got {})",
+                    if received > high {
+                        "unexpected"
+                    } else {
+                        "missing"
+                    },
+                    expected,
+                    received
+                ))
+            }
             Self::Unpack(Unpack::ListTooLong) => f.write_str("list too long"),
             Self::Unpack(Unpack::ListTooShort) => f.write_str("list too short"),
             Self::Unpack(Unpack::TypeMismatch(x, y)) => {
@@ -1143,6 +1324,13 @@ impl Display for Reason {
                     f.write_fmt(format_args!("expected 1 argument, got {}", received))
                 } else if low == high {
                     f.write_fmt(format_args!("expected {} arguments, got {}", low, received))
+                } else if *high == usize::MAX {
+                    f.write_fmt(format_args!(
+                        "expected at least {} argument{}, got {}",
+                        low,
+                        if *low == 1 { "" } else { "s" },
+                        received
+                    ))
                 } else {
                     f.write_fmt(format_args!(
                         "expected {} to {} arguments, got {}",
@@ -1154,6 +1342,9 @@ impl Display for Reason {
                 "unsuitable types for '{}': {} and {}",
                 op, l, r
             )),
+            Self::TypeMismatch(TypeMismatch::Index(x, y)) => {
+                f.write_fmt(format_args!("cannot index {} with {}", x, y))
+            }
             Self::TypeMismatch(TypeMismatch::Call(x)) => {
                 f.write_fmt(format_args!("unsuitable type for function call: {}", x))
             }
@@ -1161,12 +1352,32 @@ impl Display for Reason {
                 index,
                 allowed,
                 received,
-            }) => fmt_expected_arg(f, index + 1, &allowed.vec(), received),
+            }) => fmt_expected_type(
+                f,
+                format_args!("parameter {}", index + 1),
+                &allowed.vec(),
+                received,
+            ),
+            Self::TypeMismatch(TypeMismatch::ExpectedElement {
+                index,
+                allowed,
+                received,
+            }) => fmt_expected_type(
+                f,
+                format_args!("element at index {}", index),
+                &allowed.vec(),
+                received,
+            ),
             Self::TypeMismatch(TypeMismatch::ExpectedKwarg {
                 name,
                 allowed,
                 received,
-            }) => fmt_expected_arg(f, name, &allowed.vec(), received),
+            }) => fmt_expected_type(
+                f,
+                format_args!("parameter {}", name),
+                &allowed.vec(),
+                received,
+            ),
             Self::TypeMismatch(TypeMismatch::Interpolate(x)) => {
                 f.write_fmt(format_args!("unsuitable type for interpolation: {}", x))
             }
@@ -1183,6 +1394,9 @@ impl Display for Reason {
             Self::TypeMismatch(TypeMismatch::MapKey(x)) => {
                 f.write_fmt(format_args!("unsuitable type for map key: {}", x))
             }
+            Self::TypeMismatch(TypeMismatch::Hash(x)) => {
+                f.write_fmt(format_args!("unhashable type: {}", x))
+            }
             Self::TypeMismatch(TypeMismatch::SplatArg(x)) => {
                 f.write_fmt(format_args!("unsuitable type for splatting: {}", x))
             }
@@ -1198,7 +1412,26 @@ impl Display for Reason {
 
             Self::Value(Value::TooLarge) => f.write_str("value too large"),
             Self::Value(Value::TooLong) => f.write_str("value too long"),
+            Self::Value(Value::TooShort) => f.write_str("value too short"),
+            Self::Value(Value::DuplicateKey(key)) => {
+                f.write_fmt(format_args!("duplicate key '{}' in streamed map", key))
+            }
             Self::Value(Value::OutOfRange) => f.write_str("value out of range"),
+            Self::Value(Value::TooManySteps) => f.write_str("step limit exceeded"),
+            Self::Value(Value::TooDeep) => f.write_str("recursion limit exceeded"),
+            Self::Value(Value::DeadlineExceeded) => f.write_str("deadline exceeded"),
+            Self::Value(Value::DivisionByZero) => f.write_str("division by zero"),
+            Self::Value(Value::Serialize) => f.write_str("couldn't serialize value"),
+            Self::Value(Value::Regex(msg)) => {
+                f.write_fmt(format_args!("invalid regular expression: {}", msg))
+            }
+            Self::Value(Value::Check { path, reason }) if path.is_empty() => {
+                f.write_fmt(format_args!("check failed: {}", reason))
+            }
+            Self::Value(Value::Check { path, reason }) => {
+                f.write_fmt(format_args!("check failed at '{}': {}", path, reason))
+            }
+            Self::Value(Value::Schema(msg)) => f.write_fmt(format_args!("invalid schema: {}", msg)),
             Self::Value(Value::Convert(t)) => {
                 f.write_fmt(format_args!("couldn't convert to {}", t))
             }
@@ -1209,8 +1442,22 @@ impl Display for Reason {
             Self::FileSystem(FileSystem::Read(p)) => {
                 f.write_fmt(format_args!("couldn't read file: {}", p.display()))
             }
+            Self::FileSystem(FileSystem::Write) => f.write_str("couldn't write output"),
 
             Self::UnknownImport(p) => f.write_fmt(format_args!("unknown import: '{}'", p)),
+            Self::AmbiguousImport(p) => f.write_fmt(format_args!("ambiguous import: '{}'", p)),
+            Self::Disabled(name) => f.write_fmt(format_args!("'{}' is disabled", name)),
+        }
+    }
+}
+
+impl Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Shadowing(name) => f.write_fmt(format_args!(
+                "binding of '{}' shadows an earlier binding",
+                name
+            )),
         }
     }
 }
@@ -1252,7 +1499,7 @@ impl<'a> Display for ErrorRenderer<'a> {
             for (loc, act) in locs.iter() {
                 if let Some(code) = code {
                     // Offset of the beginning of the line
-                    let bol = loc.offset() - loc.column() as usize;
+                    let bol = code[..loc.offset()].rfind('\n').map_or(0, |x| x + 1);
 
                     // Offset of the end of the line
                     let eol = code[bol + 1..]
@@ -1270,7 +1517,7 @@ impl<'a> Display for ErrorRenderer<'a> {
                     for _ in 0..loc.column() {
                         f.write_char(' ')?;
                     }
-                    for _ in 0..span_end {
+                    for _ in code[loc.offset()..loc.offset() + span_end].chars() {
                         f.write_char('^')?;
                     }
                 }
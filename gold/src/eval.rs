@@ -1,6 +1,11 @@
+use std::cell::RefCell;
 use std::cmp::Ordering;
-use std::path::PathBuf;
+use std::collections::HashSet;
+use std::fs::read_to_string;
+use std::io::Write;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
+use std::time::Instant;
 
 #[cfg(feature = "python")]
 use pyo3::{pyclass, pymethods, FromPyObject, Py, PyAny, PyResult, Python};
@@ -9,14 +14,15 @@ use pyo3::{pyclass, pymethods, FromPyObject, Py, PyAny, PyResult, Python};
 use pyo3::exceptions::PyTypeError;
 
 #[cfg(feature = "python")]
-use pyo3::types::{PyString, PyTuple, PyAnyMethods};
+use pyo3::types::{PyAnyMethods, PyString, PyTuple};
 
-use crate::builtins::BUILTINS;
+use crate::builtins::{capability_builtins, BUILTINS};
 use crate::compile::{CompiledFunction, Instruction};
-use crate::error::{BindingType, Error, Internal, Reason, TypeMismatch, Unpack};
+use crate::error::{BindingType, Error, FileSystem, Internal, Reason, TypeMismatch, Unpack, Value};
 use crate::formatting::FormatSpec;
-use crate::types::{BinOp, Cell, EagerOp, GcCell, Res};
-use crate::{eval_file, eval_raw as eval_str};
+use crate::parsing::parse_interactive;
+use crate::types::{BinOp, Cell, EagerOp, GcCell, Key, Res};
+use crate::{eval_raw as eval_str, parse};
 use crate::{List, Map, Object, Type};
 
 /// Source code of the standard library (imported under the name 'std')
@@ -24,12 +30,35 @@ const STDLIB: &str = include_str!("std.gold");
 
 type ImportCallable = dyn Fn(&str) -> Res<Option<Object>>;
 
-/// Configure the import behavior when evaluating Gold code.
+/// Capabilities that give Gold code access to the host system. All of them
+/// are disabled by default, so that untrusted code can't use them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Capabilities {
+    /// Allow reading environment variables with the `env` builtin.
+    pub env: bool,
+
+    /// Allow reading files with the `read_file` builtin. Files are resolved
+    /// relative to the root path of the [`ImportConfig`], and may not be
+    /// outside of it. This is the same root for imported files, wherever they
+    /// are located.
+    ///
+    /// Files are always read from the file system. The custom import
+    /// resolver is not consulted, so with a config that has no root path,
+    /// `read_file` always fails.
+    pub read_file: bool,
+}
+
+/// Configure the import behavior and the capabilities available when
+/// evaluating Gold code.
 #[derive(Clone, Default)]
 pub struct ImportConfig {
     /// If set, unresolved imports will be loaded relative to this path.
     root_path: Option<PathBuf>,
 
+    /// Root directory for the `read_file` builtin. Unlike `root_path`, this
+    /// is passed on to imported files unchanged.
+    read_root: Option<PathBuf>,
+
     /// If set, this function will be called to resolve unknown imports.
     ///
     /// It should return Ok(None) to indicate that the path was unknown. In this
@@ -37,6 +66,19 @@ pub struct ImportConfig {
     /// possible. If the function returns an error, import resolution will be
     /// aborted.
     custom: Option<Rc<ImportCallable>>,
+
+    /// Candidate extensions to try when resolving an import to a path, in
+    /// order. The empty string stands for the path as written.
+    ///
+    /// If empty, the path is used as written.
+    extensions: Vec<String>,
+
+    /// Enabled capabilities. These also apply to imported files.
+    capabilities: Capabilities,
+
+    /// If set, imports that can't be found evaluate to this object instead of
+    /// failing. This also applies to imported files.
+    fallback: Option<Object>,
 }
 
 impl ImportConfig {
@@ -44,13 +86,129 @@ impl ImportConfig {
     /// import behavior.
     pub fn with_path(path: PathBuf) -> Self {
         Self {
-            root_path: Some(path),
+            root_path: Some(path.clone()),
+            read_root: Some(path),
             ..Default::default()
         }
     }
 
-    /// Resolve an import path.
-    fn resolve(&self, path: &str) -> Res<Object> {
+    /// Set the candidate extensions to try when resolving an import to a path.
+    ///
+    /// For example, with extensions `["", "gold"]`, the import path `foo` will
+    /// resolve to either `foo` or `foo.gold`, whichever exists. It is an error
+    /// if more than one candidate exists.
+    pub fn with_extensions<I, S>(mut self, extensions: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.extensions = extensions.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Set the enabled capabilities.
+    pub fn with_capabilities(mut self, capabilities: Capabilities) -> Self {
+        self.capabilities = capabilities;
+        self
+    }
+
+    /// Set a default object for imports that can't be found, instead of
+    /// failing with an unknown import error.
+    ///
+    /// This only applies when no module exists for a path. If one does, any
+    /// errors from evaluating it are reported as usual.
+    pub fn with_default(mut self, default: Object) -> Self {
+        self.fallback = Some(default);
+        self
+    }
+
+    /// The enabled capabilities.
+    pub(crate) fn capabilities(&self) -> Capabilities {
+        self.capabilities
+    }
+
+    /// Builtins made available by the enabled capabilities. These should
+    /// be used when lowering code to be evaluated with this config.
+    pub(crate) fn builtins(&self) -> Map {
+        capability_builtins(self)
+    }
+
+    /// Read a file relative to the root path of the outermost config. The
+    /// file must be inside the root directory, also after resolving symbolic
+    /// links. This bypasses the custom import resolver, which produces objects
+    /// rather than file contents.
+    ///
+    /// Files outside the root fail with the same error as missing files, so
+    /// as not to reveal which files exist there.
+    pub(crate) fn read_file(&self, path: &str) -> Res<String> {
+        let read_err = || Error::new(FileSystem::Read(PathBuf::from(path)));
+        let root = self
+            .read_root
+            .as_ref()
+            .and_then(|root| root.canonicalize().ok())
+            .ok_or_else(read_err)?;
+        let target = root.join(path).canonicalize().map_err(|_| read_err())?;
+        if !target.starts_with(&root) {
+            return Err(read_err());
+        }
+        read_to_string(&target).map_err(|_| read_err())
+    }
+
+    /// Find the file that an import path refers to, relative to a root, if
+    /// there is one.
+    fn find_file(&self, root: &Path, path: &str) -> Res<Option<PathBuf>> {
+        if self.extensions.is_empty() {
+            let target = root.join(path);
+            return Ok(target.is_file().then_some(target));
+        }
+
+        let mut candidates = self
+            .extensions
+            .iter()
+            .map(|ext| {
+                if ext.is_empty() {
+                    root.join(path)
+                } else {
+                    root.join(format!("{}.{}", path, ext))
+                }
+            })
+            .filter(|target| target.is_file());
+
+        match (candidates.next(), candidates.next()) {
+            (Some(target), None) => Ok(Some(target)),
+            (Some(_), Some(_)) => Err(Error::new(Reason::AmbiguousImport(path.to_owned()))),
+            (None, _) => Ok(None),
+        }
+    }
+
+    /// Evaluate a file. Imports from within the file are resolved relative to
+    /// its parent, with the same candidate extensions. The file is evaluated
+    /// in the context of the importing virtual machine, so that it's subject
+    /// to the same limits.
+    fn eval_file(&self, target: &Path, context: Context) -> Res<Object> {
+        let contents =
+            read_to_string(target).map_err(|_| Error::new(FileSystem::Read(target.to_owned())))?;
+        let parent = target
+            .parent()
+            .ok_or_else(|| Error::new(FileSystem::NoParent(target.to_owned())))?;
+        let importer = Self {
+            root_path: Some(parent.to_owned()),
+            read_root: self.read_root.clone(),
+            custom: None,
+            extensions: self.extensions.clone(),
+            capabilities: self.capabilities,
+            fallback: self.fallback.clone(),
+        };
+        let code = parse(&contents)?
+            .lower_with_builtins(importer.builtins())?
+            .compile()?;
+        let mut vm = Vm::new(&importer);
+        vm.context = context;
+        vm.eval(code)
+    }
+
+    /// Resolve an import path. Files are evaluated in the given context.
+    fn resolve(&self, path: &str, context: Context) -> Res<Object> {
         // Gold reserves all import paths starting with 'std'
         if path.starts_with("std") {
             match path {
@@ -66,11 +224,14 @@ impl ImportConfig {
             }
 
             // Import by path
-            if let Some(root) = &self.root_path {
-                let target = root.join(path);
-                eval_file(&target)
-            } else {
-                Err(Error::new(Reason::UnknownImport(path.to_owned())))
+            let target = match &self.root_path {
+                Some(root) => self.find_file(root, path)?,
+                None => None,
+            };
+            match (target, &self.fallback) {
+                (Some(target), _) => self.eval_file(&target, context),
+                (None, Some(default)) => Ok(default.clone()),
+                (None, None) => Err(Error::new(Reason::UnknownImport(path.to_owned()))),
             }
         }
     }
@@ -112,17 +273,23 @@ impl<'s> FromPyObject<'s> for PyImportCallable {
 pub struct PyImportConfig {
     root_path: Option<String>,
     custom: Option<PyImportCallable>,
+    extensions: Option<Vec<String>>,
 }
 
 #[cfg(feature = "python")]
 #[pymethods]
 impl PyImportConfig {
     #[new]
-    #[pyo3(signature = (root = None, custom = None))]
-    fn new(root: Option<String>, custom: Option<PyImportCallable>) -> Self {
+    #[pyo3(signature = (root = None, custom = None, extensions = None))]
+    fn new(
+        root: Option<String>,
+        custom: Option<PyImportCallable>,
+        extensions: Option<Vec<String>>,
+    ) -> Self {
         PyImportConfig {
             root_path: root,
             custom: custom,
+            extensions,
         }
     }
 }
@@ -133,8 +300,143 @@ impl PyImportConfig {
     pub fn to_gold(&self) -> ImportConfig {
         ImportConfig {
             root_path: self.root_path.as_ref().map(PathBuf::from),
+            read_root: self.root_path.as_ref().map(PathBuf::from),
             custom: self.custom.as_ref().map(|x| x.0.clone()),
+            extensions: self.extensions.clone().unwrap_or_default(),
+            capabilities: Capabilities::default(),
+            fallback: None,
+        }
+    }
+}
+
+/// Resource limits for evaluating Gold code, for protecting the host against
+/// malicious or buggy documents.
+///
+/// The limits also cover functions called from builtins (e.g. `map`): their
+/// steps count towards the same total, and their call depth is added to that
+/// of the caller. Builtins that loop without calling functions (e.g. `sum` or
+/// `sort`) count one step per element they process. Imported files are
+/// evaluated under the same limits.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Limits {
+    /// If set, evaluation will fail after this many instructions.
+    pub max_steps: Option<usize>,
+
+    /// If set, evaluation will fail when the function call stack grows deeper
+    /// than this.
+    pub max_depth: Option<usize>,
+
+    /// If set, evaluation will fail when a string built by interpolation,
+    /// concatenation, repetition or a builtin function grows longer than this
+    /// many bytes.
+    pub max_string_length: Option<usize>,
+
+    /// If set, evaluation will fail after this point in time. The clock is
+    /// only checked now and then on loop iterations and function calls, so
+    /// evaluation may overrun the deadline slightly.
+    pub deadline: Option<Instant>,
+}
+
+/// Options for evaluating Gold code with [`crate::eval_with()`].
+#[derive(Clone, Debug, Default)]
+pub struct EvalOptions {
+    /// Resource limits. See [`Limits`].
+    pub limits: Limits,
+
+    /// Integer overflow behavior. See [`Overflow`].
+    pub overflow: Overflow,
+
+    /// Additional names bound to the given objects, visible to the code as if
+    /// it were wrapped in a let block. They shadow imported names.
+    pub bindings: Vec<(Key, Object)>,
+
+    /// Additional builtins (typically functions made with
+    /// [`Object::new_func`]). They shadow standard builtins of the same name,
+    /// including those enabled by capabilities, but not bindings made in the
+    /// code itself. Reserved keywords can't be overridden.
+    pub builtins: Vec<(Key, Object)>,
+}
+
+/// Number of loop iterations and function calls between each time the virtual
+/// machine checks the clock against [`Limits::deadline`].
+const DEADLINE_INTERVAL: usize = 1024;
+
+/// Integer overflow behavior of the addition, subtraction and multiplication
+/// operators.
+///
+/// Like [`Limits`], this also applies to functions called from builtins (e.g.
+/// `map`) and to imported files.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Overflow {
+    /// Integer results that don't fit in 64 bits are promoted to
+    /// arbitrary-precision integers. This is the default.
+    #[default]
+    Promote,
+
+    /// Integer results are reduced to the signed 64-bit range with two's
+    /// complement wrapping, so that `9223372036854775807 + 1` is
+    /// `-9223372036854775808`. This applies to `+`, `-` and `*` when both
+    /// operands are integers, and also when an operand is an integer literal
+    /// too large for 64 bits. All other operators (including unary negation,
+    /// integer division and exponentiation) still promote.
+    Wrap,
+}
+
+/// A persistent environment for evaluating Gold code interactively, e.g. in a
+/// REPL.
+///
+/// Each input is evaluated with [`Environment::eval`]. Inputs consisting only
+/// of let bindings without a final expression (module bodies, such as `let a =
+/// 1`) add their names to the environment, so that later inputs can refer to
+/// them. Other inputs are evaluated as ordinary expressions and leave the
+/// environment unchanged.
+///
+/// Redefining a name replaces its value for subsequent inputs. Values that
+/// were computed from the old value (including functions that refer to it)
+/// keep referring to the old value. If evaluation fails, the environment is
+/// left unchanged, even if some bindings in the same input could have been
+/// evaluated.
+#[derive(Clone)]
+pub struct Environment {
+    importer: ImportConfig,
+    bindings: Map,
+}
+
+impl Environment {
+    /// Construct an empty environment that uses `importer` to resolve imports.
+    pub fn new(importer: ImportConfig) -> Self {
+        Self {
+            importer,
+            bindings: Map::new(),
+        }
+    }
+
+    /// Evaluate a single input and return the result. For module bodies, the
+    /// result is a map of the newly bound names.
+    pub fn eval(&mut self, input: &str) -> Res<Object> {
+        let (ast, is_module) = parse_interactive(input)?;
+        let ast = ast.with_bindings(self.bindings.iter().map(|(k, v)| (*k, v.clone())));
+        let code = ast
+            .lower_with_builtins(self.importer.builtins())?
+            .compile()?;
+        let mut vm = Vm::new(&self.importer);
+        let result = vm.eval(code)?;
+
+        if is_module {
+            let new_bindings = result
+                .get_map()
+                .ok_or_else(|| Internal::ModuleNotMap.err())?;
+            for (key, value) in new_bindings.iter() {
+                self.bindings.insert(*key, value.clone());
+            }
         }
+
+        Ok(result)
+    }
+
+    /// Look up the current value of a name.
+    pub fn get(&self, key: Key) -> Option<&Object> {
+        self.bindings.get(&key)
     }
 }
 
@@ -173,10 +475,146 @@ impl Frame {
     }
 }
 
+/// Evaluation state that carries over from a virtual machine to the nested
+/// virtual machines that evaluate closures called from builtins.
+#[derive(Clone, Default)]
+pub(crate) struct Context {
+    limits: Limits,
+    overflow: Overflow,
+
+    /// Number of instructions executed so far (for [`Limits::max_steps`]).
+    steps: Rc<std::cell::Cell<usize>>,
+
+    /// Number of deadline checkpoints passed so far.
+    checkpoints: Rc<std::cell::Cell<usize>>,
+
+    /// Call stack depth of the virtual machines further out.
+    depth: usize,
+}
+
+impl Context {
+    /// The context of the virtual machine that is currently calling a
+    /// builtin, or an unlimited context if there is none.
+    pub(crate) fn current() -> Self {
+        CONTEXT.with(|c| c.borrow().clone()).unwrap_or_default()
+    }
+
+    /// Check the length in bytes of a string against
+    /// [`Limits::max_string_length`]. Builtins should call this before
+    /// building strings that may be large.
+    pub(crate) fn check_str_len(&self, len: usize) -> Res<()> {
+        if self.limits.max_string_length.is_some_and(|max| len > max) {
+            return Err(Error::new(Value::TooLong));
+        }
+        Ok(())
+    }
+
+    /// Charge `n` steps of work done by a builtin against
+    /// [`Limits::max_steps`], and check the deadline. Builtins should call
+    /// this in loops that don't call Gold functions, so that they can't run
+    /// beyond the limits.
+    pub(crate) fn charge(&self, n: usize) -> Res<()> {
+        if let Some(max_steps) = self.limits.max_steps {
+            let steps = self.steps.get().saturating_add(n);
+            self.steps.set(steps);
+            if steps > max_steps {
+                return Err(Error::new(Value::TooManySteps));
+            }
+        }
+        if let Some(deadline) = self.limits.deadline {
+            let before = self.checkpoints.get();
+            let after = before.saturating_add(n);
+            self.checkpoints.set(after);
+            if after / DEADLINE_INTERVAL > before / DEADLINE_INTERVAL && Instant::now() >= deadline
+            {
+                return Err(Error::new(Value::DeadlineExceeded));
+            }
+        }
+        Ok(())
+    }
+
+    /// Check the result of a builtin against [`Limits::max_string_length`].
+    /// This catches strings built by builtins that don't check the length
+    /// up front (e.g. `str` or `regex_replace`).
+    pub(crate) fn check_result(&self, obj: Object) -> Res<Object> {
+        if self.limits.max_string_length.is_some() {
+            self.check_str_len(obj.max_str_len())?;
+        }
+        Ok(obj)
+    }
+}
+
+thread_local! {
+    /// The context of the virtual machine that is currently calling a
+    /// builtin, if any.
+    static CONTEXT: RefCell<Option<Context>> = const { RefCell::new(None) };
+}
+
+/// Output for a result that is written while it's being built (see
+/// [`Instruction::FlushToSink`]).
+pub struct Sink<'a> {
+    out: &'a mut dyn Write,
+
+    /// The closing bracket of the collection being written, once it has been
+    /// opened.
+    close: Option<&'static [u8]>,
+
+    /// Number of elements written so far.
+    written: usize,
+
+    /// Keys written so far, if the collection is a map.
+    keys: HashSet<Key>,
+}
+
+impl<'a> Sink<'a> {
+    fn new(out: &'a mut dyn Write) -> Self {
+        Self {
+            out,
+            close: None,
+            written: 0,
+            keys: HashSet::new(),
+        }
+    }
+
+    /// Write the elements of a list or map to the output and remove them.
+    fn flush(&mut self, obj: &Object) -> Res<()> {
+        if self.close.is_none() {
+            let (open, close): (&[u8], &'static [u8]) = match obj.type_of() {
+                Type::Map => (b"{", b"}"),
+                _ => (b"[", b"]"),
+            };
+            self.write(open)?;
+            self.close = Some(close);
+        }
+        obj.drain_json(&mut self.out, &mut self.written, &mut self.keys)
+    }
+
+    /// Write the final result to the output. If the result is a collection
+    /// that has been partially written already, only the remaining elements
+    /// are written.
+    fn finish(mut self, result: Object) -> Res<()> {
+        match self.close {
+            Some(close) => {
+                self.flush(&result)?;
+                self.write(close)
+            }
+            None => result.write_json(&mut self.out),
+        }
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Res<()> {
+        self.out
+            .write_all(bytes)
+            .map_err(|_| Error::new(FileSystem::Write))
+    }
+}
+
 pub struct Vm<'a> {
     frames: Vec<Frame>,
     fp: usize,
     importer: &'a ImportConfig,
+    context: Context,
+    sink: Option<Sink<'a>>,
 }
 
 impl<'a> Vm<'a> {
@@ -185,9 +623,75 @@ impl<'a> Vm<'a> {
             frames: vec![],
             fp: 0,
             importer,
+            context: Context::default(),
+            sink: None,
+        }
+    }
+
+    /// Construct a virtual machine for evaluating a closure called from a
+    /// builtin. If a virtual machine further out is calling the builtin, this
+    /// inherits its limits, overflow behavior and resource usage.
+    pub fn nested(importer: &'a ImportConfig) -> Self {
+        let context = Context::current();
+        Self {
+            frames: vec![],
+            fp: 0,
+            importer,
+            context,
+            sink: None,
         }
     }
 
+    /// Impose resource limits on this virtual machine.
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.context.limits = limits;
+        self
+    }
+
+    /// Set the integer overflow behavior of this virtual machine.
+    pub fn with_overflow(mut self, overflow: Overflow) -> Self {
+        self.context.overflow = overflow;
+        self
+    }
+
+    /// Write the result to `out` while it's being built, as far as the
+    /// compiled code allows (see [`Instruction::FlushToSink`]). Call
+    /// [`Vm::finish`] with the result to complete the output.
+    pub fn with_sink(mut self, out: &'a mut dyn Write) -> Self {
+        self.sink = Some(Sink::new(out));
+        self
+    }
+
+    /// Write the rest of the result to the output sink.
+    pub fn finish(&mut self, result: Object) -> Res<()> {
+        match self.sink.take() {
+            Some(sink) => sink.finish(result),
+            None => Ok(()),
+        }
+    }
+
+    /// Apply the integer overflow behavior to the result of an arithmetic
+    /// operator.
+    fn overflow(&self, obj: Object) -> Object {
+        match self.context.overflow {
+            Overflow::Promote => obj,
+            Overflow::Wrap => obj.wrap(),
+        }
+    }
+
+    fn check_length(&self, obj: &Object) -> Res<()> {
+        match obj.get_str() {
+            Some(s) => self.check_str_len(s.len()),
+            None => Ok(()),
+        }
+    }
+
+    fn check_str_len(&self, len: usize) -> Res<()> {
+        self.context
+            .check_str_len(len)
+            .map_err(|e| e.with_locations(self.err()))
+    }
+
     pub fn eval(&mut self, function: CompiledFunction) -> Res<Object> {
         self.frames.push(Frame::new(function, GcCell::new(vec![])));
         self.fp = 0;
@@ -203,6 +707,17 @@ impl<'a> Vm<'a> {
         args: &List,
         kwargs: Option<&Map>,
     ) -> Res<Object> {
+        if self
+            .context
+            .limits
+            .max_depth
+            .is_some_and(|max_depth| self.context.depth >= max_depth)
+        {
+            return Err(Error::new(Value::TooDeep));
+        }
+        if self.deadline_passed() {
+            return Err(Error::new(Value::DeadlineExceeded));
+        }
         self.frames.push(Frame::new(function, enclosed));
         self.fp = 0;
         self.push(
@@ -249,9 +764,57 @@ impl<'a> Vm<'a> {
         err
     }
 
+    /// The context for virtual machines nested inside this one, which share
+    /// its resource usage and add to its call depth.
+    fn nested_context(&self) -> Context {
+        Context {
+            depth: self.context.depth + self.frames.len(),
+            ..self.context.clone()
+        }
+    }
+
+    /// Call a function outside the virtual machine (a builtin or a native
+    /// closure), making this machine's context available to any closures it
+    /// calls in turn.
+    fn call_builtin(&self, f: impl FnOnce() -> Res<Object>) -> Res<Object> {
+        let outer = CONTEXT.with(|c| c.replace(Some(self.nested_context())));
+        let result = f();
+        CONTEXT.with(|c| *c.borrow_mut() = outer);
+        result
+    }
+
+    /// Check the deadline, if any. This is called on loop iterations and
+    /// function calls, but only reads the clock every [`DEADLINE_INTERVAL`]
+    /// times.
+    fn checkpoint(&mut self) -> Res<()> {
+        if self.deadline_passed() {
+            return Err(self.err().with_reason(Value::DeadlineExceeded));
+        }
+        Ok(())
+    }
+
+    fn deadline_passed(&self) -> bool {
+        if let Some(deadline) = self.context.limits.deadline {
+            let checkpoints = self.context.checkpoints.get() + 1;
+            self.context.checkpoints.set(checkpoints);
+            checkpoints.is_multiple_of(DEADLINE_INTERVAL) && Instant::now() >= deadline
+        } else {
+            false
+        }
+    }
+
     fn eval_impl(&mut self) -> Res<Object> {
         loop {
             let instruction = self.cur_frame().next_instruction();
+
+            if let Some(max_steps) = self.context.limits.max_steps {
+                let steps = self.context.steps.get() + 1;
+                self.context.steps.set(steps);
+                if steps > max_steps {
+                    return Err(self.err().with_reason(Value::TooManySteps));
+                }
+            }
+
             match instruction {
                 Instruction::LoadConst(i) => {
                     let obj = self.cur_frame().function.constants[i].clone();
@@ -290,7 +853,7 @@ impl<'a> Vm<'a> {
                     let path = self.frames[self.fp].function.import_paths.get(i).unwrap();
                     let object = self
                         .importer
-                        .resolve(path.as_ref())
+                        .resolve(path.as_ref(), self.nested_context())
                         .map_err(|e| e.add_locations(self.err()))?;
                     self.push(object);
                 }
@@ -338,6 +901,7 @@ impl<'a> Vm<'a> {
                 }
 
                 Instruction::JumpBack(delta) => {
+                    self.checkpoint()?;
                     self.cur_frame().ip -= delta;
                 }
 
@@ -365,23 +929,39 @@ impl<'a> Vm<'a> {
                     self.push(b);
                 }
 
+                Instruction::Tuck => {
+                    let a = self.pop();
+                    let b = self.pop();
+                    self.push(a.clone());
+                    self.push(b);
+                    self.push(a);
+                }
+
                 Instruction::Call => {
                     let args = self.pop();
                     let kwargs = self.pop();
                     let func = self.pop();
 
-                    if let Some(f) = func.get_native_callable() {
+                    if let Some((f, e)) = func.get_closure() {
+                        if self.context.limits.max_depth.is_some_and(|max_depth| {
+                            self.context.depth + self.frames.len() >= max_depth
+                        }) {
+                            return Err(self.err().with_reason(Value::TooDeep));
+                        }
+                        self.checkpoint()?;
+                        self.frames.push(Frame::new(f.as_ref().clone(), e.clone()));
+                        self.fp += 1;
+                        self.push(kwargs);
+                        self.push(args);
+                    } else if let Some(f) = func.get_func() {
                         let x = args.get_list().ok_or_else(|| Internal::ArgsNotList.err())?;
                         let y = kwargs
                             .get_map()
                             .ok_or_else(|| Internal::KwargsNotMap.err())?;
-                        let result = f(&x, Some(&y)).map_err(|e| e.with_locations(self.err()))?;
+                        let result = self
+                            .call_builtin(|| f.call(&x, Some(&y)))
+                            .map_err(|e| e.with_locations(self.err()))?;
                         self.push(result);
-                    } else if let Some((f, e)) = func.get_closure() {
-                        self.frames.push(Frame::new(f.as_ref().clone(), e.clone()));
-                        self.fp += 1;
-                        self.push(kwargs);
-                        self.push(args);
                     } else {
                         return Err(self.err().with_reason(TypeMismatch::Call(func.type_of())));
                     }
@@ -426,6 +1006,21 @@ impl<'a> Vm<'a> {
                     }
                 }
 
+                Instruction::AssertArgCount(min, max) => {
+                    let received = self
+                        .peek()
+                        .get_list()
+                        .ok_or_else(|| Internal::ArgsNotList.err())?
+                        .len();
+                    if received < min || max.is_some_and(|max| received > max) {
+                        return Err(self.err().with_reason(Unpack::ArgCount {
+                            low: min,
+                            high: max.unwrap_or(usize::MAX),
+                            received,
+                        }));
+                    }
+                }
+
                 Instruction::AssertMap => {
                     let obj = self.peek();
                     match obj.get_map() {
@@ -451,8 +1046,16 @@ impl<'a> Vm<'a> {
 
                 Instruction::FormatWithSpec(i) => {
                     let obj = self.pop();
+                    let spec = self.cur_frame().function.fmt_specs[i];
+
+                    // The result is at least as many bytes long as the width,
+                    // so check that before building it
+                    if let Some(width) = spec.width {
+                        self.check_str_len(width)?;
+                    }
+
                     let result = Object::from(
-                        obj.format(&self.cur_frame().function.fmt_specs[i])
+                        obj.format(&spec)
                             .map_err(|e| e.with_locations(self.err()))?,
                     );
                     self.push(result);
@@ -470,19 +1073,27 @@ impl<'a> Vm<'a> {
                 Instruction::Add => {
                     let rhs = self.pop();
                     let lhs = self.pop();
-                    self.push(lhs.add(&rhs).map_err(|e| e.with_locations(self.err()))?);
+                    let res = lhs.add(&rhs).map_err(|e| e.with_locations(self.err()))?;
+                    self.check_length(&res)?;
+                    self.push(self.overflow(res));
                 }
 
                 Instruction::Subtract => {
                     let rhs = self.pop();
                     let lhs = self.pop();
-                    self.push(lhs.sub(&rhs).map_err(|e| e.with_locations(self.err()))?);
+                    let res = lhs.sub(&rhs).map_err(|e| e.with_locations(self.err()))?;
+                    self.push(self.overflow(res));
                 }
 
                 Instruction::Multiply => {
                     let rhs = self.pop();
                     let lhs = self.pop();
-                    self.push(lhs.mul(&rhs).map_err(|e| e.with_locations(self.err()))?);
+                    if let Some(len) = lhs.repeated_str_len(&rhs) {
+                        let len = len.map_err(|e| e.with_locations(self.err()))?;
+                        self.check_str_len(len)?;
+                    }
+                    let res = lhs.mul(&rhs).map_err(|e| e.with_locations(self.err()))?;
+                    self.push(self.overflow(res));
                 }
 
                 Instruction::IntegerDivide => {
@@ -588,12 +1199,46 @@ impl<'a> Vm<'a> {
                     ));
                 }
 
+                Instruction::In => {
+                    let rhs = self.pop();
+                    let lhs = self.pop();
+                    let result = rhs.contains(&lhs).map_err(|_| {
+                        Error::new(TypeMismatch::BinOp(
+                            lhs.type_of(),
+                            rhs.type_of(),
+                            BinOp::Eager(EagerOp::In),
+                        ))
+                        .with_locations(self.err())
+                    })?;
+                    self.push(Object::from(result));
+                }
+
+                Instruction::NotIn => {
+                    let rhs = self.pop();
+                    let lhs = self.pop();
+                    let result = rhs.contains(&lhs).map_err(|_| {
+                        Error::new(TypeMismatch::BinOp(
+                            lhs.type_of(),
+                            rhs.type_of(),
+                            BinOp::Eager(EagerOp::NotIn),
+                        ))
+                        .with_locations(self.err())
+                    })?;
+                    self.push(Object::from(!result));
+                }
+
                 Instruction::Index => {
                     let rhs = self.pop();
                     let lhs = self.pop();
                     self.push(lhs.index(&rhs).map_err(|e| e.with_locations(self.err()))?);
                 }
 
+                Instruction::Access => {
+                    let rhs = self.pop();
+                    let lhs = self.pop();
+                    self.push(lhs.access(&rhs).map_err(|e| e.with_locations(self.err()))?);
+                }
+
                 Instruction::NewList => {
                     self.push(Object::new_list());
                 }
@@ -633,6 +1278,14 @@ impl<'a> Vm<'a> {
                         .map_err(|e| e.with_locations(self.err()))?;
                 }
 
+                Instruction::FlushToSink => {
+                    if let Some(mut sink) = self.sink.take() {
+                        let result = sink.flush(self.peek());
+                        self.sink = Some(sink);
+                        result.map_err(|e| e.with_locations(self.err()))?;
+                    }
+                }
+
                 Instruction::DelKeyIfExists(key) => {
                     let mut l = self
                         .peek()
@@ -666,6 +1319,19 @@ impl<'a> Vm<'a> {
                     }
                 }
 
+                Instruction::NextIndexedOrJump(usize) => {
+                    let obj = self.peek().next_indexed()?;
+                    match obj {
+                        None => {
+                            self.cur_frame().ip += usize;
+                        }
+                        Some((i, x)) => {
+                            self.push(x);
+                            self.push(Object::from(i));
+                        }
+                    }
+                }
+
                 Instruction::IntIndexL(i) => {
                     let obj = {
                         let l = self
@@ -805,9 +1471,21 @@ impl<'a> Vm<'a> {
 
 #[cfg(test)]
 mod tests {
-    use crate::error::{Action, BindingType, Error, Reason, Span, TypeMismatch, Types, Unpack};
-    use crate::types::{BinOp, EagerOp, Key, Res, UnOp};
-    use crate::{eval_raw, Object, Type};
+    use std::path::PathBuf;
+    use std::rc::Rc;
+    use std::time::{Duration, Instant};
+    use std::{env, fs, process};
+
+    use crate::error::{
+        Action, BindingType, Error, FileSystem, Reason, Span, Syntax, TypeMismatch, Types, Unpack,
+        Value,
+    };
+    use crate::types::{BinOp, EagerOp, Key, NativeClosure, Res, UnOp};
+    use crate::{
+        eval_parsed, eval_parsed_with, eval_raw, eval_to_writer, eval_with, eval_with_bindings,
+        eval_with_builtins, eval_with_deadline, eval_with_limits, eval_with_overflow, parse,
+        Capabilities, Environment, EvalOptions, ImportConfig, Limits, Object, Overflow, Type,
+    };
 
     fn eval(input: &str) -> Res<Object> {
         eval_raw(input).map_err(Error::unrender)
@@ -851,6 +1529,16 @@ mod tests {
         assert_seq!(eval("1"), Object::from(1));
         assert_seq!(eval("-1"), Object::from(-1));
         assert_seq!(eval("+1"), Object::from(1));
+
+        // Structural equality distinguishes machine integers from bignums, so
+        // these also check that results are demoted when they fit
+        assert_seq!(eval("2^64 - 2^64 + 1"), Object::from(1));
+        assert_seq!(eval("9223372036854775807 + 1 - 1"), Object::from(i64::MAX));
+        assert_seq!(eval("-9223372036854775808 - 1 + 1"), Object::from(i64::MIN));
+        assert_seq!(eval("2^64 * 0"), Object::from(0));
+        assert_seq!(eval("2^64 // 2^60"), Object::from(16));
+        assert_seq!(eval("-9223372036854775808"), Object::from(i64::MIN));
+        assert_seq!(eval("2^64 - 1 == 18446744073709551615"), Object::from(true));
     }
 
     #[test]
@@ -862,6 +1550,79 @@ mod tests {
         assert_seq!(eval("5e+1"), Object::from(50.0));
     }
 
+    #[test]
+    fn numeric_equality() {
+        assert_seq!(eval("1 == 1.0"), Object::from(true));
+        assert_seq!(eval("[1, {a: 2}] == [1.0, {a: 2.0}]"), Object::from(true));
+
+        // Integers and floats compare exactly, even where the integer is not
+        // representable as a float
+        assert_seq!(eval("2^60 == float(2^60)"), Object::from(true));
+        assert_seq!(eval("2^60 + 1 == float(2^60 + 1)"), Object::from(false));
+        assert_seq!(eval("2^60 + 1 > float(2^60 + 1)"), Object::from(true));
+        assert_seq!(
+            eval("9007199254740993 == 9007199254740992.0"),
+            Object::from(false)
+        );
+        assert_seq!(
+            eval("-9007199254740993 < -9007199254740992.0"),
+            Object::from(true)
+        );
+        assert_seq!(eval("2^100 == float(2^100)"), Object::from(true));
+        assert_seq!(eval("2^100 + 1 == float(2^100)"), Object::from(false));
+        assert_seq!(eval("2^100 - 1 < float(2^100)"), Object::from(true));
+        assert_seq!(eval("2^1100 > 1e308"), Object::from(true));
+        assert_seq!(eval("2^1100 < float(\"inf\")"), Object::from(true));
+        assert_seq!(eval("-(2^1100) > float(\"-inf\")"), Object::from(true));
+
+        // Membership uses the same equality
+        assert_seq!(eval("[1, 2] has 2.0"), Object::from(true));
+        assert_seq!(eval("[1.0, 2.0] has 1"), Object::from(true));
+        assert_seq!(eval("[2^60 + 1] has float(2^60)"), Object::from(false));
+        assert_seq!(eval("range(5) has 3.0"), Object::from(true));
+        assert_seq!(eval("range(5) has 3.5"), Object::from(false));
+        assert_seq!(eval("range(5) has \"3\""), Object::from(false));
+    }
+
+    #[test]
+    fn float_equality() {
+        assert_seq!(
+            eval(concat!(
+                "let nan = float(\"nan\")\n",
+                "in [nan == nan, nan != nan, [nan] == [nan], {a: nan} == {a: nan}, [nan] has nan]"
+            )),
+            Object::from(vec![
+                Object::from(false),
+                Object::from(true),
+                Object::from(false),
+                Object::from(false),
+                Object::from(false)
+            ])
+        );
+        assert_seq!(
+            eval("float(\"nan\") == float(\"nan\")"),
+            Object::from(false)
+        );
+
+        assert_seq!(eval("-0.0 == 0.0"), Object::from(true));
+        assert_seq!(eval("-0.0 == 0"), Object::from(true));
+        assert_seq!(eval("[-0.0] == [0.0]"), Object::from(true));
+        assert_seq!(eval("1.0 == 1"), Object::from(true));
+        assert_seq!(eval("1 == 1.0"), Object::from(true));
+        assert_seq!(eval("1.5 == 1"), Object::from(false));
+        assert_seq!(eval("{a: [1]} == {a: [1.0]}"), Object::from(true));
+
+        // Consistency with hashing
+        assert_seq!(
+            eval("unique([0.0, -0.0, 0, 1, 1.0])"),
+            Object::from(vec![Object::from(0.0), Object::from(1)])
+        );
+        assert_seq!(
+            eval("len(unique([float(\"nan\"), float(\"nan\")]))"),
+            Object::from(2)
+        );
+    }
+
     #[test]
     fn strings() {
         assert_seq!(eval("\"\""), Object::new_str_interned(""));
@@ -940,6 +1701,11 @@ mod tests {
             eval("{$\"abcdefghijklmnopqrstuvwxyz\": 1}"),
             Object::from(vec![("abcdefghijklmnopqrstuvwxyz", Object::from(1)),])
         );
+
+        assert_seq!(
+            eval("let x = \"a\" in {$x: 1, a: 2}"),
+            Object::from(vec![("a", Object::from(2)),])
+        );
     }
 
     #[test]
@@ -964,41 +1730,112 @@ mod tests {
         );
 
         assert!(eval("let a = 1 let b = a in y").is_err());
-    }
 
-    #[test]
-    fn list_bindings() {
-        assert_seq!(eval("let [a] = [1] in a"), Object::from(1));
-        assert_seq!(eval("let [a, ...] = [1] in a"), Object::from(1));
-        assert_seq!(eval("let [a, ...] = [1, 2, 3] in a"), Object::from(1));
-        assert_seq!(eval("let [_, a, ...] = [1, 2, 3] in a"), Object::from(2));
-        assert_seq!(eval("let [_, _, a, ...] = [1, 2, 3] in a"), Object::from(3));
-        assert_seq!(eval("let [_, _, a] = [1, 2, 3] in a"), Object::from(3));
+        // Bindings are sequential, and may shadow earlier ones
+        assert_seq!(eval("let a = 1 let a = a + 1 in a"), Object::from(2));
+        assert_seq!(
+            eval("let a = 1 let f = fn () a let a = 2 in [f(), a]"),
+            Object::from(vec![Object::from(1), Object::from(2)])
+        );
 
+        // Final bindings behave like normal ones as long as they're not shadowed
         assert_seq!(
-            eval("let [...a] = [1, 2, 3] in a"),
-            (1..4).map(Object::from).collect()
+            eval("let final a = 1 let b = a + 1 in [a, b]"),
+            (1..3).map(Object::from).collect()
         );
         assert_seq!(
-            eval("let [...a, _] = [1, 2, 3] in a"),
+            eval("[let final a = 1 in a, let a = 2 in a]"),
             (1..3).map(Object::from).collect()
         );
         assert_seq!(
-            eval("let [...a, _, _] = [1, 2, 3] in a"),
-            Object::from(vec![Object::from(1)])
+            eval("let a = 1, f = fn () a, a = 2, g = fn () a, a = 3 in [f(), g(), a]"),
+            Object::from(vec![Object::from(1), Object::from(2), Object::from(3)])
         );
         assert_seq!(
-            eval("let [_, ...a, _] = [1, 2, 3] in a"),
-            Object::from(vec![Object::from(2)])
+            eval("let [a, b] = [1, 2] let {a, c} = {a: b, c: a} in [a, c]"),
+            Object::from(vec![Object::from(2), Object::from(1)])
         );
 
+        assert_seq!(eval("let a = 1, b = a + 1 in b"), Object::from(2));
         assert_seq!(
-            eval("let [_, ...a, _, _] = [1, 2, 3] in a"),
-            Object::new_list(),
+            eval("let a = 1, b = a let a = 2, c = [a, b] in c"),
+            Object::from(vec![Object::from(2), Object::from(1)])
+        );
+        assert_seq!(
+            eval("let [a, b] = [1, 2], {c} = {c: a + b} in c"),
+            Object::from(3)
+        );
+        assert_seq!(
+            eval("[let a = 1, b = 2 in a + b, 4]"),
+            Object::from(vec![Object::from(3), Object::from(4)])
         );
+    }
 
-        assert_seq!(eval("let [..., a] = [1] in a"), Object::from(1));
-        assert_seq!(eval("let [..., _, a] = [1, 2] in a"), Object::from(2));
+    #[test]
+    fn modules() {
+        assert_seq!(
+            eval("let a = 1 let b = a + 1"),
+            Object::from(vec![("a", Object::from(1)), ("b", Object::from(2))])
+        );
+        assert_seq!(
+            eval("let [a, _] = [1, 2] let {b} = {b: 3} let a = a + b"),
+            Object::from(vec![("a", Object::from(4)), ("b", Object::from(3))])
+        );
+        assert_eq!(
+            eval_errstr("let a = 1 b"),
+            Some("Error: expected 'in'\nlet a = 1 b\n         \nwhile parsing at 1:10".to_owned())
+        );
+
+        // Columns count characters, not bytes
+        assert_eq!(
+            eval_errstr("let a = \"😀😀\" b"),
+            Some(
+                "Error: expected 'in'\nlet a = \"😀😀\" b\n            \nwhile parsing at 1:13"
+                    .to_owned()
+            )
+        );
+        assert_eq!(
+            eval_errstr("[\"😀\", -true]"),
+            Some(
+                "Error: unsuitable type for '-': bool\n[\"😀\", -true]\n      ^\nwhile evaluating at 1:7"
+                    .to_owned()
+            )
+        );
+    }
+
+    #[test]
+    fn list_bindings() {
+        assert_seq!(eval("let [a] = [1] in a"), Object::from(1));
+        assert_seq!(eval("let [a, ...] = [1] in a"), Object::from(1));
+        assert_seq!(eval("let [a, ...] = [1, 2, 3] in a"), Object::from(1));
+        assert_seq!(eval("let [_, a, ...] = [1, 2, 3] in a"), Object::from(2));
+        assert_seq!(eval("let [_, _, a, ...] = [1, 2, 3] in a"), Object::from(3));
+        assert_seq!(eval("let [_, _, a] = [1, 2, 3] in a"), Object::from(3));
+
+        assert_seq!(
+            eval("let [...a] = [1, 2, 3] in a"),
+            (1..4).map(Object::from).collect()
+        );
+        assert_seq!(
+            eval("let [...a, _] = [1, 2, 3] in a"),
+            (1..3).map(Object::from).collect()
+        );
+        assert_seq!(
+            eval("let [...a, _, _] = [1, 2, 3] in a"),
+            Object::from(vec![Object::from(1)])
+        );
+        assert_seq!(
+            eval("let [_, ...a, _] = [1, 2, 3] in a"),
+            Object::from(vec![Object::from(2)])
+        );
+
+        assert_seq!(
+            eval("let [_, ...a, _, _] = [1, 2, 3] in a"),
+            Object::new_list(),
+        );
+
+        assert_seq!(eval("let [..., a] = [1] in a"), Object::from(1));
+        assert_seq!(eval("let [..., _, a] = [1, 2] in a"), Object::from(2));
         assert_seq!(eval("let [..., a=1] = [2] in a"), Object::from(2));
         assert_seq!(eval("let [..., a=1] = [] in a"), Object::from(1));
         assert_seq!(eval("let [...a, _=1] = [2] in a"), Object::new_list());
@@ -1055,10 +1892,121 @@ mod tests {
 
         assert!(eval("let {a} = {} in a").is_err());
         assert!(eval("let {a} = {b: 1} in a").is_err());
+
+        assert_seq!(
+            eval("let {a as [b, {c}]} = {a: [1, {c: 2}]} in [b, c]"),
+            Object::from(vec![Object::from(1), Object::from(2)])
+        );
+        assert_seq!(
+            eval("let {a as {b as [c, ...d], ...e}} = {a: {b: [1, 2], f: 3}} in [c, d, e]"),
+            Object::from(vec![
+                Object::from(1),
+                Object::from(vec![Object::from(2)]),
+                Object::from(vec![("f", Object::from(3))]),
+            ])
+        );
+
+        // Explicitly bound keys are excluded from the slurp, also when renamed
+        // or defaulted
+        assert_seq!(
+            eval("(fn ({a, ...rest}) rest)({a: 1, b: 2, c: 3})"),
+            Object::from(vec![("b", Object::from(2)), ("c", Object::from(3))])
+        );
+        assert_seq!(
+            eval("(fn ({a as x, z = 4, ...rest}) [x, z, rest])({a: 1, b: 2, z: 3})"),
+            Object::from(vec![
+                Object::from(1),
+                Object::from(3),
+                Object::from(vec![("b", Object::from(2))]),
+            ])
+        );
+        assert_seq!(
+            eval("(fn ({z = 4, ...rest}) [z, rest])({b: 2})"),
+            Object::from(vec![
+                Object::from(4),
+                Object::from(vec![("b", Object::from(2))]),
+            ])
+        );
+    }
+
+    #[test]
+    fn variadic_functions() {
+        let list = |x: &[i64]| x.iter().copied().map(Object::from).collect::<Object>();
+
+        assert_seq!(eval("(fn (a, ...rest) rest)(1, 2, 3)"), list(&[2, 3]));
+        assert_seq!(eval("(fn (a, ...rest) rest)(1)"), list(&[]));
+        assert_seq!(eval("(fn (...rest) rest)()"), list(&[]));
+        assert_seq!(
+            eval("(fn (a, b = 5, ...rest) [a, b, rest])(1)"),
+            Object::from(vec![Object::from(1), Object::from(5), list(&[])])
+        );
+        assert_seq!(
+            eval("(fn (a, b = 5, ...rest) [a, b, rest])(1, 2, 3, 4)"),
+            Object::from(vec![Object::from(1), Object::from(2), list(&[3, 4])])
+        );
+        assert_seq!(
+            eval("(fn (a, ...rest, z) [a, rest, z])(1, 2, 3, 4)"),
+            Object::from(vec![Object::from(1), list(&[2, 3]), Object::from(4)])
+        );
+        assert_seq!(eval("(fn (...rest) rest)(...[1, 2], 3)"), list(&[1, 2, 3]));
+
+        assert_seq!(
+            eval("(fn (a; b = 1, ...kw) [a, b, kw])(1, c: 3, d: 4)"),
+            Object::from(vec![
+                Object::from(1),
+                Object::from(1),
+                Object::from(vec![("c", Object::from(3)), ("d", Object::from(4))]),
+            ])
+        );
+        assert_seq!(
+            eval("(fn (a; b = 1, ...kw) [a, b, kw])(1, b: 2, c: 3)"),
+            Object::from(vec![
+                Object::from(1),
+                Object::from(2),
+                Object::from(vec![("c", Object::from(3))]),
+            ])
+        );
+        assert_seq!(eval("(fn (; ...kw) kw)()"), Object::new_map());
+        assert_seq!(
+            eval("(fn {x, ...kw} [x, kw])(x: 1, y: 2)"),
+            Object::from(vec![
+                Object::from(1),
+                Object::from(vec![("y", Object::from(2))])
+            ])
+        );
     }
 
     #[test]
     fn function_bindings() {
+        assert_seq!(eval("(fn (a, b = 1) b)(2)"), Object::from(1));
+        assert_seq!(eval("(fn (a, b = 1) b)(2, 3)"), Object::from(3));
+        assert_seq!(
+            eval("(fn (a = 1, ...b) [a, b])()"),
+            Object::from(vec![Object::from(1), Object::new_list()])
+        );
+
+        assert_seq!(
+            eval("let f = fn ([[a, b], c]) [a, b, c] in f([[1, 2], 3])"),
+            Object::from(vec![Object::from(1), Object::from(2), Object::from(3)])
+        );
+
+        assert_seq!(
+            eval(
+                "let f = fn ([[a, ...b], {x, ...y}]) [a, b, x, y] in f([[1, 2, 3], {x: 4, z: 5}])"
+            ),
+            Object::from(vec![
+                Object::from(1),
+                Object::from(vec![Object::from(2), Object::from(3)]),
+                Object::from(4),
+                Object::from(vec![("z", Object::from(5))]),
+            ])
+        );
+
+        assert_seq!(
+            eval("let f = fn ([a, [b = 2, ...c]] = [1, []]) [a, b, c] in f()"),
+            Object::from(vec![Object::from(1), Object::from(2), Object::new_list()])
+        );
+
         assert_seq!(
             eval(concat!(
                 "let a = fn (x, [y, z]) x + y + z\n",
@@ -1238,11 +2186,43 @@ mod tests {
         assert_seq!(eval("2 * 4.0"), Object::from(8.0));
         assert_seq!(eval("2 * 4 + 1"), Object::from(9));
         assert_seq!(eval("2 * (4 + 1)"), Object::from(10));
+
+        assert_seq!(eval("\"ab\" * 3"), Object::from("ababab"));
+        assert_seq!(eval("2 * \"ab\""), Object::from("abab"));
+        assert_seq!(eval("\"ab\" * 0"), Object::from(""));
+        assert_seq!(eval("\"ab\" * -2"), Object::from(""));
+        assert_seq!(
+            eval("[0] * 3"),
+            Object::from(vec![Object::from(0), Object::from(0), Object::from(0)])
+        );
+        assert_seq!(
+            eval("2 * [1, 2]"),
+            Object::from(vec![
+                Object::from(1),
+                Object::from(2),
+                Object::from(1),
+                Object::from(2)
+            ])
+        );
+        assert_seq!(eval("[1] * -1"), Object::new_list());
+        assert_seq!(eval("\"\" * 9223372036854775807"), Object::from(""));
+        assert_seq!(eval("[] * 9223372036854775807"), Object::new_list());
         assert_seq!(eval("3 / 2"), Object::from(1.5));
         assert_seq!(eval("3.0 / 2"), Object::from(1.5));
         assert_seq!(eval("3 / 2.0"), Object::from(1.5));
         assert_seq!(eval("3.0 / 2.0"), Object::from(1.5));
         assert_seq!(eval("3 // 2"), Object::from(1));
+        assert_seq!(eval("4 / 2"), Object::from(2.0));
+        assert_seq!(eval("-7 // 2"), Object::from(-4));
+        assert_seq!(eval("7 // -2"), Object::from(-4));
+        assert_seq!(eval("-7 // -2"), Object::from(3));
+        assert_seq!(eval("-6 // 2"), Object::from(-3));
+        assert_seq!(eval("7.5 // 2"), Object::from(3.0));
+        assert_seq!(eval("-7.5 // 2"), Object::from(-4.0));
+        assert_seq!(
+            eval("-(2 ^ 100 + 1) // 2"),
+            Object::new_int_from_str("-633825300114114700748351602689").unwrap()
+        );
         assert_seq!(eval("1 + 2.0"), Object::from(3.0));
         assert_seq!(eval("1.0 + 2"), Object::from(3.0));
         assert_seq!(eval("1.0 + 2.0"), Object::from(3.0));
@@ -1250,6 +2230,11 @@ mod tests {
         assert_seq!(eval("1.0 - 2"), Object::from(-1.0));
         assert_seq!(eval("1 - 2.0"), Object::from(-1.0));
         assert_seq!(eval("1 - 2 + 3"), Object::from(2));
+        assert_seq!(eval("1 - 2 - 3"), Object::from(-4));
+        assert_seq!(eval("1 - (2 - 3)"), Object::from(2));
+        assert_seq!(eval("16 / 2 / 2"), Object::from(4.0));
+        assert_seq!(eval("16 / (2 / 2)"), Object::from(16.0));
+        assert_seq!(eval("16 // 4 // 2"), Object::from(2));
         assert_seq!(eval("2 // 2 * 2"), Object::from(2));
         assert_seq!(eval("2 ^ 2"), Object::from(4));
         assert_seq!(eval("-2 ^ 2"), Object::from(-4));
@@ -1261,6 +2246,23 @@ mod tests {
         assert_seq!(eval("(-2 ^ 3) ^ 3"), Object::from(-512));
         assert_seq!(eval("-(2 ^ 3) ^ 3"), Object::from(-512));
         assert_seq!(eval("2 ^ -1"), Object::from(0.5));
+        assert_seq!(eval("-2 ^ -2"), Object::from(-0.25));
+        assert_seq!(eval("2 ^ 0"), Object::from(1));
+        assert_seq!(eval("2.0 ^ 3"), Object::from(8.0));
+        assert_seq!(eval("4 ^ 0.5"), Object::from(2.0));
+        assert_seq!(
+            eval("2 ^ 64"),
+            Object::new_int_from_str("18446744073709551616").unwrap()
+        );
+        assert_seq!(
+            eval("2 ^ 100"),
+            Object::new_int_from_str("1267650600228229401496703205376").unwrap()
+        );
+        assert_seq!(eval("2 ^ 100 - 2 ^ 100 + 1"), Object::from(1));
+        assert_seq!(
+            eval("2 ^ 100 == 1267650600228229401496703205376"),
+            Object::from(true)
+        );
 
         assert_seq!(
             eval("(9999999999999999999999999 + 1) - 9999999999999999999999999"),
@@ -1283,6 +2285,7 @@ mod tests {
         assert_seq!(eval("1.0 < 2"), Object::from(true));
         assert_seq!(eval("1.0 < 2.0"), Object::from(true));
         assert_seq!(eval("\"a\" < \"b\""), Object::from(true));
+        assert_seq!(eval("\"Z\" < \"a\""), Object::from(true));
 
         assert_seq!(eval("1 > 2"), Object::from(false));
         assert_seq!(eval("1 > 2.0"), Object::from(false));
@@ -1302,6 +2305,29 @@ mod tests {
         assert_seq!(eval("1.0 >= 2.0"), Object::from(false));
         assert_seq!(eval("\"a\" >= \"b\""), Object::from(false));
 
+        assert_seq!(eval("1 < 2 < 3"), Object::from(true));
+        assert_seq!(eval("3 < 2 < 1"), Object::from(false));
+        assert_seq!(eval("1 < 3 < 2"), Object::from(false));
+        assert_seq!(eval("1 < 2 <= 2 < 3"), Object::from(true));
+        assert_seq!(eval("1 < 2 <= 2 < 2"), Object::from(false));
+        assert_seq!(eval("1 < 2 == 2"), Object::from(true));
+        assert_seq!(eval("1 < 2 == true"), Object::from(false));
+        assert_seq!(eval("(1 < 2) == true"), Object::from(true));
+        assert_seq!(eval("3 < 2 < {}.x"), Object::from(false));
+        assert_seq!(
+            eval("let x = 2 in [1 < x < 3, 3 > x > 1]"),
+            Object::from(vec![true, true])
+        );
+
+        assert_seq!(eval("[1, 2] < [1, 3]"), Object::from(true));
+        assert_seq!(eval("[1] < [1, 0]"), Object::from(true));
+        assert_seq!(eval("[] < []"), Object::from(false));
+        assert_seq!(eval("[] <= []"), Object::from(true));
+        assert_seq!(eval("[2] > [1, 5]"), Object::from(true));
+        assert_seq!(eval("[1, 2.0] >= [1.0, 2]"), Object::from(true));
+        assert_seq!(eval("[null, 1] < [null, 2]"), Object::from(true));
+        assert_seq!(eval("[[1, 2], \"a\"] < [[1, 3], 1]"), Object::from(true));
+
         assert_seq!(eval("1 == 2"), Object::from(false));
         assert_seq!(eval("2 == 2"), Object::from(true));
         assert_seq!(eval("2.0 == 2.0"), Object::from(true));
@@ -1324,7 +2350,20 @@ mod tests {
         assert_seq!(eval("{a: 1.0} == {a: 1}"), Object::from(true));
         assert_seq!(eval("{a: 2} == {a: 1}"), Object::from(false));
         assert_seq!(eval("{a: 1} == {a: 1, b: 1}"), Object::from(false));
-        assert_seq!(eval("{a: 1} == {a: 1, a: 1}"), Object::from(true));
+        assert_seq!(
+            eval("let k = \"a\" in {a: 1} == {a: 1, $k: 1}"),
+            Object::from(true)
+        );
+
+        // Insertion order doesn't matter
+        assert_seq!(eval("{a: 1, b: 2} == {b: 2, a: 1}"), Object::from(true));
+        assert_seq!(eval("{a: 1, b: 2} == {b: 3, a: 1}"), Object::from(false));
+        assert_seq!(eval("{a: 1, b: 2} != {b: 2, a: 1}"), Object::from(false));
+        assert_seq!(eval("[{a: 1, b: 2}] has {b: 2, a: 1}"), Object::from(true));
+        assert_seq!(
+            eval("count([{a: 1, b: 2}, {b: 2, a: 1}], {a: 1, b: 2})"),
+            Object::from(2)
+        );
 
         assert_seq!(eval("[] == {}"), Object::from(false));
 
@@ -1350,7 +2389,10 @@ mod tests {
         assert_seq!(eval("{a: 1.0} != {a: 1}"), Object::from(false));
         assert_seq!(eval("{a: 2} != {a: 1}"), Object::from(true));
         assert_seq!(eval("{a: 1} != {a: 1, b: 1}"), Object::from(true));
-        assert_seq!(eval("{a: 1} != {a: 1, a: 1}"), Object::from(false));
+        assert_seq!(
+            eval("let k = \"a\" in {a: 1} != {a: 1, $k: 1}"),
+            Object::from(false)
+        );
 
         assert_seq!(eval("[] != {}"), Object::from(true));
     }
@@ -1363,6 +2405,24 @@ mod tests {
         assert_seq!(eval("\"bobloblaw\" has \"blob\""), Object::from(true));
         assert_seq!(eval("\"bobloblaw\" has \"lobl\""), Object::from(true));
         assert_seq!(eval("\"bobloblaw\" has \"shrimp\""), Object::from(false));
+
+        assert_seq!(eval("3 not in [1, 2]"), Object::from(true));
+        assert_seq!(eval("2 not in [1, 2]"), Object::from(false));
+        assert_seq!(eval("\"blob\" not in \"bobloblaw\""), Object::from(false));
+        assert_seq!(eval("not 1 not in [true]"), Object::from(false));
+        assert_seq!(eval("(not 1) not in [true]"), Object::from(true));
+        assert_seq!(eval("let x = 1 not in [2] in x"), Object::from(true));
+
+        assert_seq!(eval("2 in [1, 2]"), Object::from(true));
+        assert_seq!(eval("3 in [1, 2]"), Object::from(false));
+        assert_seq!(eval("\"blob\" in \"bobloblaw\""), Object::from(true));
+        assert_seq!(eval("not 3 in [1, 2]"), Object::from(true));
+        assert_seq!(eval("let x = 1 in x in [1]"), Object::from(true));
+        assert_seq!(eval("let x = (1 in [2]) in x"), Object::from(false));
+        assert_seq!(
+            eval("let x = [1 in [1]] in x"),
+            Object::from(vec![Object::from(true)])
+        );
     }
 
     #[test]
@@ -1373,6 +2433,25 @@ mod tests {
         assert_seq!(eval("false or 1"), Object::from(1));
         assert_seq!(eval("null or 1"), Object::from(1));
         assert_seq!(eval("1 or 1"), Object::from(1));
+
+        // The operands are returned as is, not coerced to booleans
+        assert_seq!(eval("0 or 5"), Object::from(5));
+        assert_seq!(eval("0.0 or \"x\""), Object::from("x"));
+        assert_seq!(eval("null or \"default\""), Object::from("default"));
+        assert_seq!(eval("2 and 3"), Object::from(3));
+        assert_seq!(eval("0 and 3"), Object::from(0));
+        assert_seq!(eval("null and 3"), Object::null());
+
+        // Empty strings and collections are truthy
+        assert_seq!(eval("\"\" or \"x\""), Object::from(""));
+        assert_seq!(eval("[] or 1"), Object::new_list());
+        assert_seq!(eval("{} and 1"), Object::from(1));
+
+        // The right operand is not evaluated when short-circuiting
+        assert_seq!(eval("1 or 1 + null"), Object::from(1));
+        assert_seq!(eval("0 and 1 + null"), Object::from(0));
+        assert_seq!(eval("true or [][0]"), Object::from(true));
+        assert!(eval("0 or 1 + null").is_err());
     }
 
     #[test]
@@ -1439,7 +2518,7 @@ mod tests {
                 "let a = 2\n",
                 "in b()"
             )),
-            Object::from(2)
+            Object::from(1)
         );
 
         assert_seq!(
@@ -1454,7 +2533,7 @@ mod tests {
                 "let a = 2\n",
                 "in b()"
             )),
-            Object::from(2)
+            Object::from(1)
         );
 
         assert_seq!(
@@ -1566,6 +2645,61 @@ mod tests {
             eval("{for [x,y] in [[\"a\", 1], [\"b\", 2]]: $x: y}"),
             Object::from(vec![("a", Object::from(1)), ("b", Object::from(2))])
         );
+
+        assert_seq!(
+            eval("{for i, x in [\"a\", \"b\"]: \"${i}\": x}"),
+            Object::from(vec![("0", Object::from("a")), ("1", Object::from("b"))])
+        );
+        assert_seq!(
+            eval("{for i, [k, v] in [[\"a\", 1], [\"b\", 2]]: $k: i + v}"),
+            Object::from(vec![("a", Object::from(1)), ("b", Object::from(3))])
+        );
+        assert_seq!(
+            eval("{for i, x in [1, 2, 3]: when i > 0: \"k${i}\": x}"),
+            Object::from(vec![("k1", Object::from(2)), ("k2", Object::from(3))])
+        );
+
+        assert_seq!(
+            eval("[for i, x in [\"a\", \"b\"]: [i, x]]"),
+            Object::from(vec![
+                Object::from(vec![Object::from(0), Object::from("a")]),
+                Object::from(vec![Object::from(1), Object::from("b")]),
+            ])
+        );
+        assert_seq!(
+            eval("[for i, [x, y] in [[1, 2], [3, 4]]: i + x + y]"),
+            Object::from(vec![Object::from(3), Object::from(8)])
+        );
+        assert_seq!(
+            eval("[for i, x in [5, 6]: for j, y in [7, 8]: [i, j]]"),
+            Object::from(vec![
+                Object::from(vec![Object::from(0), Object::from(0)]),
+                Object::from(vec![Object::from(0), Object::from(1)]),
+                Object::from(vec![Object::from(1), Object::from(0)]),
+                Object::from(vec![Object::from(1), Object::from(1)]),
+            ])
+        );
+
+        assert_seq!(
+            eval("[for c in \"abc\": ord(c)]"),
+            Object::from(vec![Object::from(97), Object::from(98), Object::from(99)])
+        );
+        assert_seq!(
+            eval("[for i, c in \"ø€\": \"${i}${c}\"]"),
+            Object::from(vec![
+                Object::new_str_natural("0ø"),
+                Object::new_str_natural("1€"),
+            ])
+        );
+        assert_seq!(eval("[for c in \"\": c]"), Object::new_list());
+        assert_seq!(
+            eval("[for kv in {a: 1, b: 2}: kv[0]]"),
+            Object::from(vec![Object::from("a"), Object::from("b")])
+        );
+        assert_seq!(
+            eval("{for [k, v] in {a: 1, b: 2}: $k: v + 1}"),
+            Object::from(vec![("a", Object::from(2)), ("b", Object::from(3))])
+        );
     }
 
     #[test]
@@ -1595,8 +2729,66 @@ mod tests {
         );
     }
 
+    #[test]
+    fn map_order() {
+        let keys = |names: &[&str]| {
+            Object::from(names.iter().map(|x| Object::from(*x)).collect::<Vec<_>>())
+        };
+
+        assert_seq!(
+            eval(concat!(
+                "let base = {z: 1, y: 2, x: 3, v: 4}\n",
+                "let {y, ...rest} = base\n",
+                "let m = {\n",
+                "    w: 0,\n",
+                "    ...base,\n",
+                "    for [k, v] in rest: $\"${k}2\": v,\n",
+                "    z: 10,\n",
+                "    ...{b: 1, a: 2},\n",
+                "}\n",
+                "in [keys(rest), keys(m), keys(deep_merge(m, {a: 3, c: 4}))]",
+            )),
+            Object::from(vec![
+                keys(&["z", "x", "v"]),
+                keys(&["w", "z", "y", "x", "v", "z2", "x2", "v2", "b", "a"]),
+                keys(&["w", "z", "y", "x", "v", "z2", "x2", "v2", "b", "a", "c"]),
+            ])
+        );
+
+        assert_seq!(
+            eval("values({c: 1, a: 2, b: 3, ...{a: 4}})"),
+            Object::from(vec![Object::from(1), Object::from(4), Object::from(3)])
+        );
+        assert_seq!(
+            eval("items({c: 1, a: 2})"),
+            Object::from(vec![
+                Object::from(vec![Object::from("c"), Object::from(1)]),
+                Object::from(vec![Object::from("a"), Object::from(2)]),
+            ])
+        );
+    }
+
     #[test]
     fn builtins() {
+        assert_seq!(
+            eval("map(fn (x) x + 1)([1, 2])"),
+            Object::from(vec![Object::from(2), Object::from(3)])
+        );
+        assert_seq!(
+            eval("let f = filter(fn (x) x > 1) in [f([1, 2]), f([3, 0])]"),
+            Object::from(vec![
+                Object::from(vec![Object::from(2)]),
+                Object::from(vec![Object::from(3)]),
+            ])
+        );
+        assert_seq!(
+            eval("map(map(fn (x) 2 * x), [[1], [2, 3]])"),
+            Object::from(vec![
+                Object::from(vec![Object::from(2)]),
+                Object::from(vec![Object::from(4), Object::from(6)]),
+            ])
+        );
+
         assert_seq!(eval("len([1, 2])"), Object::from(2));
         assert_seq!(eval("len([])"), Object::from(0));
 
@@ -1607,268 +2799,2007 @@ mod tests {
         assert_seq!(eval("len(\"abc\")"), Object::from(3));
         assert_seq!(eval("len(\"å\")"), Object::from(1));
 
+        assert_seq!(eval("bytelen(\"\")"), Object::from(0));
+        assert_seq!(eval("bytelen(\"abc\")"), Object::from(3));
+        assert_seq!(eval("bytelen(\"å\")"), Object::from(2));
+        assert_seq!(
+            eval("let s = \"håndklær 😀\" in [s.len(), s.bytelen()]"),
+            Object::from(vec![Object::from(10), Object::from(15)])
+        );
+
         assert_seq!(eval("range(3)"), (0..3).map(Object::from).collect());
         assert_seq!(eval("range(1, 3)"), (1..3).map(Object::from).collect());
+        assert_seq!(
+            eval("range(0, 10, 2)"),
+            (0..10).step_by(2).map(Object::from).collect()
+        );
+        assert_seq!(
+            eval("range(10, 0, -2)"),
+            (1..=5).rev().map(|x| Object::from(2 * x)).collect()
+        );
+        assert_seq!(eval("range(0, 0, 1)"), Object::new_list());
+        assert_seq!(eval("range(0, 5, -1)"), Object::new_list());
+        assert_seq!(eval("range(3) == [0, 1, 2]"), Object::from(true));
+        assert_seq!(eval("range(3) + [3]"), (0..4).map(Object::from).collect());
+        assert_seq!(
+            eval("len(range(1000000000000))"),
+            Object::from(1000000000000_i64)
+        );
+        assert_seq!(
+            eval("range(1000000000000)[123456789]"),
+            Object::from(123456789)
+        );
+        assert_seq!(
+            eval("range(1, 1000000000000, 2) has 123456789"),
+            Object::from(true)
+        );
+        assert_seq!(
+            eval("let [a, b, ...] = range(5) in [a, b]"),
+            Object::from(vec![Object::from(0), Object::from(1)])
+        );
+        assert_seq!(
+            eval("[for x in range(10): when x < 3: x]"),
+            (0..3).map(Object::from).collect()
+        );
 
-        assert_seq!(eval("int(1)"), Object::from(1));
-        assert_seq!(eval("int(true)"), Object::from(1));
-        assert_seq!(eval("int(false)"), Object::from(0));
-        assert_seq!(eval("int(1.2)"), Object::from(1));
-        assert_seq!(eval("int(-1.2)"), Object::from(-1));
-        assert_seq!(eval("int(\"-3\")"), Object::from(-3));
-
-        assert_seq!(eval("bool(1)"), Object::from(true));
-        assert_seq!(eval("bool(0)"), Object::from(false));
-        assert_seq!(eval("bool(1.5)"), Object::from(true));
-        assert_seq!(eval("bool(0.0)"), Object::from(false));
-        assert_seq!(eval("bool(true)"), Object::from(true));
-        assert_seq!(eval("bool(false)"), Object::from(false));
-        assert_seq!(eval("bool(null)"), Object::from(false));
-        assert_seq!(eval("bool([])"), Object::from(true));
-        assert_seq!(eval("bool({})"), Object::from(true));
+        assert_seq!(
+            eval("repeat(\"x\", 3)"),
+            Object::from(vec![Object::from("x"); 3])
+        );
+        assert_seq!(eval("repeat(1, 0)"), Object::new_list());
 
-        assert_seq!(eval("str(1)"), Object::from("1"));
-        assert_seq!(eval("str(1.2)"), Object::from("1.2"));
-        assert_seq!(eval("str(\"delta\")"), Object::from("delta"));
-        assert_seq!(eval("str(true)"), Object::from("true"));
-        assert_seq!(eval("str(false)"), Object::from("false"));
-        assert_seq!(eval("str(null)"), Object::from("null"));
+        assert_seq!(eval("unique([])"), Object::new_list());
+        assert_seq!(
+            eval("unique([1, 2, 1.0, 2.5, 2])"),
+            Object::from(vec![Object::from(1), Object::from(2), Object::from(2.5)])
+        );
+        assert_seq!(
+            eval("unique([[1], {a: 1, b: 2}, [1.0], {b: 2, a: 1}, \"x\"])"),
+            Object::from(vec![
+                Object::from(vec![Object::from(1)]),
+                Object::from(vec![("a", Object::from(1)), ("b", Object::from(2))]),
+                Object::from("x"),
+            ])
+        );
 
-        assert_seq!(eval("float(1)"), Object::from(1.0));
-        assert_seq!(eval("float(1.0)"), Object::from(1.0));
+        assert_seq!(eval("dict([])"), Object::new_map());
+        assert_seq!(
+            eval("dict([[\"a\", 1], [\"b\", 2], [\"a\", 3]])"),
+            Object::from(vec![("a", Object::from(3)), ("b", Object::from(2))])
+        );
+        assert_seq!(
+            eval("dict([\"a\", \"b\"], [1, 2])"),
+            Object::from(vec![("a", Object::from(1)), ("b", Object::from(2))])
+        );
+        assert_seq!(
+            eval("let m = {a: 1, b: [2], c: {d: 3}} in dict(items(m)) == m"),
+            Object::from(true)
+        );
+
+        // Integer keys are converted to strings
+        assert_seq!(
+            eval("dict([[123, \"a\"], [-4, \"b\"]])"),
+            Object::from(vec![("123", Object::from("a")), ("-4", Object::from("b"))])
+        );
+        assert_seq!(
+            eval("dict([1, \"1\"], [2, 3])"),
+            Object::from(vec![("1", Object::from(3))])
+        );
+        assert_seq!(
+            eval("keys(dict([[100000000000000000000, null]]))"),
+            Object::from(vec![Object::new_str_interned("100000000000000000000")])
+        );
+        assert_seq!(
+            eval("{for i in [1, 2]: $i: i * i}"),
+            Object::from(vec![("1", Object::from(1)), ("2", Object::from(4))])
+        );
+
+        assert_seq!(
+            eval("path({a: {b: [10, 20]}}, \"a.b.1\")"),
+            Object::from(20)
+        );
+        assert_seq!(eval("path([{\"0\": 1}], \"0.0\")"), Object::from(1));
+        assert_seq!(eval("path(range(5), \"3\")"), Object::from(3));
+        assert_seq!(eval("path(1, \"\")"), Object::from(1));
+        assert_seq!(eval("path({a: 1}, \"b\", null)"), Object::null());
+        assert_seq!(eval("path({a: [1]}, \"a.1\", 0)"), Object::from(0));
+        assert_seq!(eval("path({a: 1}, \"a.b\", 2)"), Object::from(2));
+        assert_seq!(eval("path({a: 1}, \"a\", 2)"), Object::from(1));
+
+        assert_seq!(eval("lower(\"ABC\")"), Object::from("abc"));
+        assert_seq!(eval("upper(\"åbc\")"), Object::from("ÅBC"));
+        assert_seq!(
+            eval("split(\"a,b,,c\", \",\")"),
+            Object::from(vec![
+                Object::from("a"),
+                Object::from("b"),
+                Object::from(""),
+                Object::from("c"),
+            ])
+        );
+        assert_seq!(
+            eval("split(\" a  b \")"),
+            Object::from(vec![Object::from("a"), Object::from("b")])
+        );
+
+        assert_seq!(
+            eval("regex_match(\"key=value\", \"([a-z]+)=([a-z]+)(x)?\")"),
+            Object::from(vec![
+                Object::from("key=value"),
+                Object::from("key"),
+                Object::from("value"),
+                Object::null(),
+            ])
+        );
+        assert_seq!(eval("\"a-b\".regex_match(\"[0-9]\")"), Object::null());
+        assert_seq!(
+            eval(
+                "regex_replace(\"2024-10-16\", \"([0-9]+)-([0-9]+)-([0-9]+)\", \"\\$3/\\$2/\\$1\")"
+            ),
+            Object::from("16/10/2024")
+        );
+        assert_seq!(
+            eval("\"a1b22\".regex_replace(\"[0-9]+\", \"#\")"),
+            Object::from("a#b#")
+        );
+
+        let strs = |x: &[&str]| x.iter().copied().map(Object::from).collect::<Object>();
+        assert_seq!(
+            eval("regex_split(\"a1b2c\", \"[0-9]\")"),
+            strs(&["a", "b", "c"])
+        );
+        assert_seq!(
+            eval("\"a  b c\".regex_split(\" +\")"),
+            strs(&["a", "b", "c"])
+        );
+        assert_seq!(
+            eval("regex_split(\"abc\", \"\")"),
+            strs(&["", "a", "b", "c", ""])
+        );
+        assert_seq!(
+            eval("regex_split(\"a12b\", \"[0-9]*\")"),
+            strs(&["", "a", "b", ""])
+        );
+
+        assert_seq!(
+            eval("interpolate(\"\\${greeting}, \\${name}! (\\${n})\", {greeting: \"Hello\", name: \"world\", n: 1.5})"),
+            Object::new_str_natural("Hello, world! (1.5)")
+        );
+        assert_seq!(
+            eval("\"[\\${x}] \\${x}\".interpolate({x: [1, \"a\"]})"),
+            Object::new_str_natural("[[1, \"a\"]] [1, \"a\"]")
+        );
+        assert_seq!(
+            eval("interpolate(\"\\${a} \\${b} \\$c \\${d\", {a: 1}, \"?\")"),
+            Object::new_str_natural("1 ? $c ${d")
+        );
+        assert_seq!(eval("interpolate(\"\", {})"), Object::new_str_natural(""));
+
+        assert_seq!(eval("format_number(1234567)"), Object::from("1234567"));
+        assert_seq!(
+            eval("format_number(1234567, grouping: true)"),
+            Object::from("1,234,567")
+        );
+        assert_seq!(
+            eval("format_number(-123456, grouping: true, separator: \" \")"),
+            Object::from("-123 456")
+        );
+        assert_seq!(
+            eval("format_number(999, grouping: true)"),
+            Object::from("999")
+        );
+        assert_seq!(
+            eval("format_number(1234.5, decimal: \",\")"),
+            Object::from("1234,5")
+        );
+        assert_seq!(
+            eval("format_number(-1234567.25, grouping: true, separator: \".\", decimal: \",\")"),
+            Object::from("-1.234.567,25")
+        );
+        assert_seq!(
+            eval("format_number(float(\"inf\"), grouping: true)"),
+            Object::from("inf")
+        );
+
+        assert_seq!(
+            eval("[for [k, v] in iter_items({a: 1, b: 2}): [v, k]]"),
+            Object::from(vec![
+                Object::from(vec![Object::from(1), Object::from("a")]),
+                Object::from(vec![Object::from(2), Object::from("b")]),
+            ])
+        );
+        assert_seq!(
+            eval("{for i, k in {a: 1, b: 2}.iter_keys(): $k: i}"),
+            Object::from(vec![("a", Object::from(0)), ("b", Object::from(1))])
+        );
+        assert_seq!(
+            eval("[for v in iter_values({a: 1, b: 2}): v * 10]"),
+            Object::from(vec![Object::from(10), Object::from(20)])
+        );
+        assert_seq!(
+            eval("let m = {a: 1, b: [2]} in [for x in iter_items(m): x] == items(m)"),
+            Object::from(true)
+        );
+        assert_seq!(eval("[for k in iter_keys({}): k]"), Object::new_list());
+        assert_seq!(eval("len(iter_keys({a: 1, b: 2}))"), Object::from(2));
+        assert_seq!(eval("str(iter_keys({a: 1}))"), Object::from("[\"a\"]"));
+        assert_seq!(eval("iter_keys({}) == iter_keys({})"), Object::from(true));
+        assert_seq!(
+            eval("iter_items({a: 1}) == items({a: 1})"),
+            Object::from(true)
+        );
+        assert_seq!(eval("iter_values({a: 1, b: 2})[1]"), Object::from(2));
+        assert_seq!(
+            eval("iter_keys({a: 1, b: 2}) has \"b\""),
+            Object::from(true)
+        );
+        assert_seq!(
+            eval("sort(iter_keys({b: 1, a: 2}))"),
+            Object::from(vec![Object::from("a"), Object::from("b")])
+        );
+        assert_seq!(
+            eval("iter_keys({a: 1}) + [\"b\"]"),
+            Object::from(vec![Object::from("a"), Object::from("b")])
+        );
+
+        assert_seq!(eval("isempty(\"\")"), Object::from(true));
+        assert_seq!(eval("isempty(\"a\")"), Object::from(false));
+        assert_seq!(eval("isempty([])"), Object::from(true));
+        assert_seq!(eval("isempty([null])"), Object::from(false));
+        assert_seq!(eval("isempty({})"), Object::from(true));
+        assert_seq!(eval("isempty({a: 1})"), Object::from(false));
+        assert_seq!(eval("isempty(range(0))"), Object::from(true));
+        assert_seq!(eval("isempty(range(1000000000000))"), Object::from(false));
+
+        assert_seq!(eval("deep_len({a: [1, 2], b: 3})"), Object::from(3));
+        assert_seq!(eval("deep_len([[], {}, [[null]]])"), Object::from(1));
+        assert_seq!(eval("deep_len([range(5), {x: len}])"), Object::from(6));
+        assert_seq!(eval("deep_len(\"abc\")"), Object::from(1));
+
+        assert_seq!(eval("arity(fn (x, y) x)"), Object::from(vec![2, 2]));
+        assert_seq!(eval("arity(fn (x, y = 1) x)"), Object::from(vec![1, 2]));
+        assert_seq!(
+            eval("arity(fn (x, ...rest) x)"),
+            Object::from(vec![Object::from(1), Object::null()])
+        );
+        assert_seq!(eval("arity(fn {x} x)"), Object::from(vec![0, 0]));
+        assert_seq!(eval("arity(range)"), Object::from(vec![1, 3]));
+        assert_seq!(eval("arity(map)"), Object::from(vec![1, 2]));
+        assert_seq!(
+            eval("arity(max)"),
+            Object::from(vec![Object::from(1), Object::null()])
+        );
+
+        assert_seq!(
+            eval("zip_longest([1, 2, 3], [\"a\"], fill: 0)"),
+            Object::from(vec![
+                Object::from(vec![Object::from(1), Object::from("a")]),
+                Object::from(vec![2, 0]),
+                Object::from(vec![3, 0]),
+            ])
+        );
+        assert_seq!(
+            eval("zip_longest([1], range(2))"),
+            Object::from(vec![
+                Object::from(vec![1, 0]),
+                Object::from(vec![Object::null(), Object::from(1)]),
+            ])
+        );
+        assert_seq!(eval("zip_longest()"), Object::new_list());
+
+        let schema = concat!(
+            "let schema = {\n",
+            "  name: {type: \"str\"},\n",
+            "  port: {type: \"int\", min: 1, max: 65535},\n",
+            "  ratio: {type: \"number\", optional: true},\n",
+            "  tags: {type: \"list\", items: {type: \"str\"}},\n",
+            "  db: {host: {type: \"str\"}},\n",
+            "}\n",
+        );
+        assert_seq!(
+            eval(&format!(
+                "{}in check({{name: \"x\", port: 80, tags: [], db: {{host: \"h\", user: 1}}}}, schema).port",
+                schema
+            )),
+            Object::from(80)
+        );
+        assert_seq!(
+            eval("check(1.5, {type: \"number\", max: 2})"),
+            Object::from(1.5)
+        );
+        assert_seq!(
+            eval("check([null], {type: \"any\"})"),
+            Object::from(vec![Object::null()])
+        );
+
+        assert_seq!(
+            eval("chunk([1, 2, 3, 4, 5], 2)"),
+            Object::from(vec![
+                Object::from(vec![1, 2]),
+                Object::from(vec![3, 4]),
+                Object::from(vec![5]),
+            ])
+        );
+        assert_seq!(eval("chunk([], 2)"), Object::new_list());
+        assert_seq!(
+            eval("windows([1, 2, 3], 2)"),
+            Object::from(vec![Object::from(vec![1, 2]), Object::from(vec![2, 3])])
+        );
+        assert_seq!(eval("windows([1, 2, 3], 4)"), Object::new_list());
+        assert_seq!(
+            eval("windows(range(3), 3)"),
+            Object::from(vec![Object::from(vec![0, 1, 2])])
+        );
+
+        assert_seq!(eval("coalesce(null, null, 3)"), Object::from(3));
+        assert_seq!(eval("coalesce(null, false, 3)"), Object::from(false));
+        assert_seq!(eval("coalesce(null)"), Object::null());
+        assert_seq!(eval("coalesce()"), Object::null());
+        assert_seq!(eval("coalesce(...[null, 1])"), Object::from(1));
+
+        assert_seq!(eval("sum([1, 2, 3])"), Object::from(6));
+        assert_seq!(eval("sum([1, 2.5])"), Object::from(3.5));
+        assert_seq!(eval("sum([])"), Object::from(0));
+        assert_seq!(eval("sum(range(100001))"), Object::from(5000050000i64));
+        assert_seq!(eval("sum(range(0))"), Object::from(0));
+        assert_seq!(
+            eval("sum([9223372036854775807, 1])"),
+            Object::new_int_from_str("9223372036854775808").unwrap()
+        );
+        assert_seq!(eval("product([2, 3, 4])"), Object::from(24));
+        assert_seq!(eval("product([])"), Object::from(1));
+        assert_seq!(
+            eval("product(range(1, 21))"),
+            Object::new_int_from_str("2432902008176640000").unwrap()
+        );
+        assert_seq!(
+            eval("product(range(1, 26))"),
+            Object::new_int_from_str("15511210043330985984000000").unwrap()
+        );
+
+        assert_seq!(eval("first([1, 2, 3])"), Object::from(1));
+        assert_seq!(eval("first([], null)"), Object::null());
+        assert_seq!(eval("first([1], 2)"), Object::from(1));
+        assert_seq!(eval("first(\"åb\")"), Object::from("å"));
+        assert_seq!(eval("first(\"\", \"x\")"), Object::from("x"));
+        assert_seq!(eval("last(range(5))"), Object::from(4));
+        assert_seq!(eval("first(range(1000000000000))"), Object::from(0));
+        assert_seq!(eval("first(range(0), null)"), Object::null());
+        assert_seq!(
+            eval("last(range(1000000000000))"),
+            Object::from(999_999_999_999i64)
+        );
+        assert_seq!(eval("last(range(0), 1)"), Object::from(1));
+        assert_seq!(eval("nth(range(0, 1000000000000, 2), 3)"), Object::from(6));
+        assert_seq!(eval("nth(range(5), 5, null)"), Object::null());
+        assert!(eval("nth(range(5), 5)").is_err());
+        assert!(eval("nth(range(1000000000000), \"a\")").is_err());
+        assert_seq!(eval("last([], 0)"), Object::from(0));
+        assert_seq!(eval("last(\"abc\")"), Object::from("c"));
+        assert_seq!(eval("nth([1, 2, 3], 1)"), Object::from(2));
+        assert_seq!(eval("nth([1, 2, 3], 3, null)"), Object::null());
+        assert_seq!(eval("nth([1, 2, 3], -1, 0)"), Object::from(0));
+
+        assert_seq!(eval("count([1, 1, 2], 1)"), Object::from(2));
+        assert_seq!(eval("count([1, 1.0, [1]], 1)"), Object::from(2));
+        assert_seq!(eval("count([], null)"), Object::from(0));
+        assert_seq!(eval("count(\"banana\", \"a\")"), Object::from(3));
+        assert_seq!(eval("count(\"aaaa\", \"aa\")"), Object::from(2));
+        assert_seq!(eval("count(fn (x) x > 1, [1, 2, 3])"), Object::from(2));
+        assert_seq!(
+            eval("group_by(fn (x) x - x // 2 * 2, [1, 2, 3, 4])"),
+            Object::from(vec![
+                ("1", Object::from(vec![Object::from(1), Object::from(3)])),
+                ("0", Object::from(vec![Object::from(2), Object::from(4)])),
+            ])
+        );
+        assert_seq!(
+            eval("group_by(fn (x) x.k)([{k: \"a\", n: 1}, {k: null, n: 2}, {k: \"a\", n: 3}])"),
+            Object::from(vec![
+                (
+                    "a",
+                    Object::from(vec![
+                        Object::from(vec![("k", Object::from("a")), ("n", Object::from(1))]),
+                        Object::from(vec![("k", Object::from("a")), ("n", Object::from(3))]),
+                    ])
+                ),
+                (
+                    "null",
+                    Object::from(vec![Object::from(vec![
+                        ("k", Object::null()),
+                        ("n", Object::from(2))
+                    ])])
+                ),
+            ])
+        );
+        assert_seq!(eval("group_by(fn (x) x, [])"), Object::new_map());
+        assert_seq!(
+            eval("group_by(fn (x) x > 2, [1, 2, 3, 4])"),
+            Object::from(vec![
+                ("false", Object::from(vec![1, 2])),
+                ("true", Object::from(vec![3, 4])),
+            ])
+        );
+        assert_seq!(
+            eval("map(fn ([k, v]) [k, v * 10], {a: 1, b: 2})"),
+            Object::from(vec![("a", Object::from(10)), ("b", Object::from(20))])
+        );
+        assert_seq!(
+            eval("map(fn ([k, v]) [\"${k}${k}\", v])({a: 1, b: 2})"),
+            Object::from(vec![("aa", Object::from(1)), ("bb", Object::from(2))])
+        );
+        assert_seq!(
+            eval("map(fn (i, x) [i, x], [\"a\", \"b\"], index: true)"),
+            Object::from(vec![
+                Object::from(vec![Object::from(0), Object::from("a")]),
+                Object::from(vec![Object::from(1), Object::from("b")]),
+            ])
+        );
+        assert_seq!(
+            eval("filter(fn ([k, v]) v // 2 * 2 == v, {a: 1, b: 2, c: 3, d: 4})"),
+            Object::from(vec![("b", Object::from(2)), ("d", Object::from(4))])
+        );
+        assert_seq!(
+            eval("filter(fn (i, x) i // 2 * 2 != i, [\"a\", \"b\", \"c\", \"d\"], index: true)"),
+            Object::from(vec![Object::from("b"), Object::from("d")])
+        );
+        assert_seq!(
+            eval("filter(fn (i, [k, v]) i > 0 and v < 3, {a: 1, b: 2, c: 3}, index: true)"),
+            Object::from(vec![("b", Object::from(2))])
+        );
+        assert_seq!(
+            eval("filter(fn ([k, v]) v > 1, {a: 1, b: 2}, index: false)"),
+            Object::from(vec![("b", Object::from(2))])
+        );
+        assert_seq!(
+            eval("filter(fn (x) x > 1, [1, 2, 3], index: false)"),
+            Object::from(vec![Object::from(2), Object::from(3)])
+        );
+        assert_seq!(
+            eval("filter_keys(fn (k) k != \"b\", {a: 1, b: 2, c: 3})"),
+            Object::from(vec![("a", Object::from(1)), ("c", Object::from(3))])
+        );
+        assert_seq!(
+            eval("filter_values(fn (v) v > 1)({a: 1, b: 2, c: 3})"),
+            Object::from(vec![("b", Object::from(2)), ("c", Object::from(3))])
+        );
+
+        assert_seq!(
+            eval("deep_merge({a: {b: 1, c: 2}}, {a: {c: 3}})"),
+            Object::from(vec![(
+                "a",
+                Object::from(vec![("b", Object::from(1)), ("c", Object::from(3))])
+            )])
+        );
+        assert_seq!(
+            eval("deep_merge({a: [1, 2], b: {c: 1}, d: 1}, {a: [3], b: 2}, {e: 5})"),
+            Object::from(vec![
+                ("a", Object::from(vec![Object::from(3)])),
+                ("b", Object::from(2)),
+                ("d", Object::from(1)),
+                ("e", Object::from(5)),
+            ])
+        );
+        assert_seq!(
+            eval("let x = {a: {b: 1}} let y = deep_merge(x, {a: {c: 2}}) in x"),
+            Object::from(vec![("a", Object::from(vec![("b", Object::from(1))]))])
+        );
+
+        assert_seq!(eval("max(3, 7, 2)"), Object::from(7));
+        assert_seq!(eval("max([3, 7, 2])"), Object::from(7));
+        assert_seq!(eval("max(3, 7, 2) == max([3, 7, 2])"), Object::from(true));
+        assert_seq!(eval("min(3, 7, 2)"), Object::from(2));
+        assert_seq!(eval("min([3, 7, 2])"), Object::from(2));
+        assert_seq!(eval("min([2.5])"), Object::from(2.5));
+        assert_seq!(eval("max(1, 2.5)"), Object::from(2.5));
+        assert_seq!(eval("max(\"b\", \"c\", \"a\")"), Object::from("c"));
+        assert_seq!(eval("max([1, 2], [1, 3])"), Object::from(vec![1, 3]));
+        assert_seq!(eval("min(1, 1.0)"), Object::from(1));
+        assert_seq!(eval("max(-3, 2, key: fn (x) x * x)"), Object::from(-3));
+        assert_seq!(eval("min([-3, 2], key: fn (x) x * x)"), Object::from(2));
+        assert_seq!(
+            eval("max([{n: 1}, {n: 4}, {n: 2}], key: fn (x) x.n)"),
+            Object::from(vec![("n", Object::from(4))])
+        );
+
+        assert_seq!(
+            eval("sort([3, 1, 2.5])"),
+            Object::from(vec![Object::from(1), Object::from(2.5), Object::from(3),])
+        );
+        assert_seq!(
+            eval("sort([3, 1, 2], reverse: true)"),
+            Object::from(vec![3, 2, 1])
+        );
+        assert_seq!(
+            eval("sort([\"b\", \"a\"])"),
+            Object::from(vec![Object::from("a"), Object::from("b"),])
+        );
+        assert_seq!(eval("sort([])"), Object::new_list());
+        assert_seq!(
+            eval("let x = [{k: 2, n: 1}, {k: 1, n: 2}, {k: 2, n: 3}, {k: 1, n: 4}] in [for y in sort(x, key: fn (y) y.k): y.n]"),
+            Object::from(vec![2, 4, 1, 3])
+        );
+        assert_seq!(
+            eval("let x = [{k: 2, n: 1}, {k: 1, n: 2}, {k: 2, n: 3}, {k: 1, n: 4}] in [for y in sort(x, key: fn (y) y.k, reverse: true): y.n]"),
+            Object::from(vec![1, 3, 2, 4])
+        );
+        assert_seq!(
+            eval("sort([\"banana\", \"Apple\", \"cherry\", \"apple\"])"),
+            Object::from(vec![
+                Object::from("Apple"),
+                Object::from("apple"),
+                Object::from("banana"),
+                Object::from("cherry"),
+            ])
+        );
+        assert_seq!(
+            eval("sort([\"banana\", \"Cherry\", \"apple\"])"),
+            Object::from(vec![
+                Object::from("Cherry"),
+                Object::from("apple"),
+                Object::from("banana"),
+            ])
+        );
+        assert_seq!(
+            eval("sort([\"banana\", \"Cherry\", \"apple\"], casefold: true)"),
+            Object::from(vec![
+                Object::from("apple"),
+                Object::from("banana"),
+                Object::from("Cherry"),
+            ])
+        );
+        assert_seq!(
+            eval("sort([\"b\", \"B\", \"a\"], casefold: true)"),
+            Object::from(vec![
+                Object::from("a"),
+                Object::from("b"),
+                Object::from("B")
+            ])
+        );
+        assert_seq!(
+            eval("sort([\"b\", \"B\", \"a\"], casefold: true, reverse: true)"),
+            Object::from(vec![
+                Object::from("b"),
+                Object::from("B"),
+                Object::from("a")
+            ])
+        );
+        assert_seq!(
+            eval("[for x in sort([{n: \"b\"}, {n: \"A\"}], key: fn (x) x.n, casefold: true): x.n]"),
+            Object::from(vec![Object::from("A"), Object::from("b")])
+        );
+        assert_seq!(
+            eval("sort([2, 1.5, 3], casefold: true)"),
+            Object::from(vec![Object::from(1.5), Object::from(2), Object::from(3)])
+        );
+
+        assert_seq!(
+            eval("wrapping_add(9223372036854775807, 1)"),
+            Object::from(i64::MIN)
+        );
+        assert_seq!(eval("wrapping_add(-1, 1)"), Object::from(0));
+        assert_seq!(eval("checked_add(9223372036854775807, 1)"), Object::null());
+        assert_seq!(
+            eval("checked_add(9223372036854775807, -1)"),
+            Object::from(i64::MAX - 1)
+        );
+
+        assert_seq!(eval("getattr({foo: 1}, \"f\" + \"oo\")"), Object::from(1));
+        assert_seq!(eval("getattr({foo: 1}, \"bar\", null)"), Object::null());
+        assert_seq!(eval("getattr({foo: 1}, \"foo\", 2)"), Object::from(1));
+        assert_seq!(eval("hasattr({foo: 1}, \"foo\")"), Object::from(true));
+        assert_seq!(eval("hasattr({foo: 1}, \"bar\")"), Object::from(false));
+
+        assert_seq!(eval("rjust(\"7\", 3, \"0\")"), Object::from("007"));
+        assert_seq!(eval("rjust(\"7\", 3)"), Object::from("  7"));
+        assert_seq!(eval("ljust(\"7\", 3, \"å\")"), Object::from("7åå"));
+        assert_seq!(eval("center(\"ab\", 5, \"*\")"), Object::from("*ab**"));
+        assert_seq!(eval("ljust(\"abcd\", 3)"), Object::from("abcd"));
+        assert_seq!(eval("center(\"abcd\", -1)"), Object::from("abcd"));
+
+        assert_seq!(eval("int(1)"), Object::from(1));
+        assert_seq!(eval("int(true)"), Object::from(1));
+        assert_seq!(eval("int(false)"), Object::from(0));
+        assert_seq!(eval("int(1.2)"), Object::from(1));
+        assert_seq!(eval("int(-1.2)"), Object::from(-1));
+        assert_seq!(eval("int(\"-3\")"), Object::from(-3));
+
+        assert_seq!(eval("bool(1)"), Object::from(true));
+        assert_seq!(eval("bool(0)"), Object::from(false));
+        assert_seq!(eval("bool(1.5)"), Object::from(true));
+        assert_seq!(eval("bool(0.0)"), Object::from(false));
+        assert_seq!(eval("bool(true)"), Object::from(true));
+        assert_seq!(eval("bool(false)"), Object::from(false));
+        assert_seq!(eval("bool(null)"), Object::from(false));
+        assert_seq!(eval("bool([])"), Object::from(true));
+        assert_seq!(eval("bool({})"), Object::from(true));
+
+        assert_seq!(eval("str(1)"), Object::from("1"));
+        assert_seq!(eval("str(1.2)"), Object::from("1.2"));
+        assert_seq!(eval("str(\"delta\")"), Object::from("delta"));
+        assert_seq!(eval("str(true)"), Object::from("true"));
+        assert_seq!(eval("str(false)"), Object::from("false"));
+        assert_seq!(eval("str(null)"), Object::from("null"));
+        assert_seq!(eval("str([\"delta\"])"), Object::from("[\"delta\"]"));
+        assert_seq!(eval("str({a: \"delta\"})"), Object::from("{a: \"delta\"}"));
+        assert_seq!(eval("str([1,2])"), Object::from("[1, 2]"));
+        assert_seq!(eval("str({a:1})"), Object::from("{a: 1}"));
+        assert_seq!(eval("str({\"a b\": 1})"), Object::from("{\"a b\": 1}"));
+        assert_seq!(
+            eval("str({\"for\": 1, \"when\": 2, \"if\": 3})"),
+            Object::from("{\"for\": 1, \"when\": 2, if: 3}")
+        );
+        assert_seq!(eval("str(len)"), Object::from("<function>"));
+        assert_seq!(eval("str([len])"), Object::from("[<function>]"));
+        assert_seq!(eval("str(fn (x) x)"), Object::from("<function>"));
+        assert_seq!(eval("str(\"delta\", pretty: true)"), Object::from("delta"));
+        assert_seq!(eval("str([1, 2], pretty: false)"), Object::from("[1, 2]"));
+        assert_seq!(
+            eval("str({a: [1]}, pretty: true)"),
+            Object::from("{\n    a: [\n        1,\n    ],\n}")
+        );
+
+        assert_seq!(eval("float(1)"), Object::from(1.0));
+        assert_seq!(eval("float(1.0)"), Object::from(1.0));
         assert_seq!(eval("float(true)"), Object::from(1.0));
         assert_seq!(eval("float(false)"), Object::from(0.0));
         assert_seq!(eval("float(\"1.2\")"), Object::from(1.2));
-    }
 
-    macro_rules! loc {
-        ($loc:expr, $act:ident) => {
-            (Span::from($loc), Action::$act)
-        };
+        assert_seq!(eval("number(\"42\")"), Object::from(42));
+        assert_seq!(eval("number(\" -42\t\")"), Object::from(-42));
+        assert_seq!(eval("number(\"4.2\")"), Object::from(4.2));
+        assert_seq!(eval("number(\"1e9\")"), Object::from(1e9));
+        assert_seq!(eval("isint(number(\"1e9\"))"), Object::from(false));
+        assert_seq!(
+            eval("number(\"123456789012345678901234567890\")"),
+            Object::new_int_from_str("123456789012345678901234567890").unwrap()
+        );
     }
 
-    macro_rules! err {
+    #[test]
+    fn limits() {
+        let eval_limited = |input: &str, max_steps, max_depth| {
+            let limits = Limits {
+                max_steps,
+                max_depth,
+                ..Default::default()
+            };
+            eval_with_limits(input, &ImportConfig::default(), limits).map_err(Error::unrender)
+        };
+
+        let recursive = "let f = fn (x) if x == 0 then 0 else 1 + f(x - 1) in f(50)";
+        assert_seq!(eval_limited(recursive, None, None), Object::from(50));
+        assert_seq!(eval_limited(recursive, None, Some(100)), Object::from(50));
+        assert!(eval_limited(recursive, None, Some(20)).is_err_and(|e| e
+            .render(None)
+            .rendered()
+            .unwrap()
+            .contains("recursion limit exceeded")));
+
+        let looping = "[for x in range(100): x]";
+        assert_seq!(
+            eval_limited(looping, Some(10000), None),
+            (0..100).map(Object::from).collect()
+        );
+        assert!(eval_limited(looping, Some(100), None).is_err_and(|e| e
+            .render(None)
+            .rendered()
+            .unwrap()
+            .contains("step limit exceeded")));
+
+        let callback = "len(map(fn (x) x, range(20000)))";
+        assert_seq!(eval_limited(callback, None, None), Object::from(20000));
+        assert!(eval_limited(callback, Some(1000), None).is_err_and(|e| e
+            .render(None)
+            .rendered()
+            .unwrap()
+            .contains("step limit exceeded")));
+        assert!(
+            eval_limited("filter(fn (x) x > 0, range(20000))", Some(1000), None).is_err_and(|e| e
+                .render(None)
+                .rendered()
+                .unwrap()
+                .contains("step limit exceeded"))
+        );
+        assert!(eval_limited(
+            "let f = fn (x) if x == 0 then 0 else 1 + sum(map(f, [x - 1])) in f(50)",
+            None,
+            Some(20)
+        )
+        .is_err_and(|e| e
+            .render(None)
+            .rendered()
+            .unwrap()
+            .contains("recursion limit exceeded")));
+
+        // Loops in builtins count towards the step limit
+        assert_seq!(
+            eval_limited("sum(range(1000))", Some(10000), None),
+            Object::from(499500)
+        );
+        for input in [
+            "sum(range(10000000000))",
+            "product(range(1, 10000000000))",
+            "repeat(1, 1000000000000)",
+            "windows(range(100000), 50000)",
+            "sort(range(100000000))",
+        ] {
+            assert!(
+                eval_limited(input, Some(1000), None).is_err_and(|e| e
+                    .render(None)
+                    .rendered()
+                    .unwrap()
+                    .contains("step limit exceeded")),
+                "{}",
+                input
+            );
+        }
+
+        let eval_short = |input: &str| {
+            let limits = Limits {
+                max_string_length: Some(20),
+                ..Default::default()
+            };
+            eval_with_limits(input, &ImportConfig::default(), limits).map_err(Error::unrender)
+        };
+
+        assert_seq!(
+            eval_short("\"a${1}b${2}c\""),
+            Object::new_str_natural("a1b2c")
+        );
+        assert!(
+            eval_short("let x = \"abcdefghij\" in \"${x}${x}${x}\"").is_err_and(|e| e
+                .render(None)
+                .rendered()
+                .unwrap()
+                .contains("value too long"))
+        );
+        assert!(
+            eval_short("let f = fn (s) \"${s}${s}\" in f(f(f(\"abc\")))").is_err_and(|e| e
+                .render(None)
+                .rendered()
+                .unwrap()
+                .contains("value too long"))
+        );
+        assert!(eval_short("\"abcdefghij\" + \"abcdefghijk\"").is_err());
+        assert_seq!(eval_short("\"ab\" * 10"), Object::from("ab".repeat(10)));
+        assert!(eval_short("\"x\" * 100000000").is_err_and(|e| e
+            .render(None)
+            .rendered()
+            .unwrap()
+            .contains("value too long")));
+        assert_seq!(
+            eval_short("rjust(\"a\", 20)"),
+            Object::from(" ".repeat(19) + "a")
+        );
+        assert!(eval_short("ljust(\"a\", 9999999999)").is_err_and(|e| e
+            .render(None)
+            .rendered()
+            .unwrap()
+            .contains("value too long")));
+        assert!(
+            eval_short("map(fn (x) center(x, 21, \"å\"), [\"a\"])").is_err_and(|e| e
+                .render(None)
+                .rendered()
+                .unwrap()
+                .contains("value too long"))
+        );
+
+        assert_seq!(eval_short("\"${1:>5}\""), Object::new_str_natural("    1"));
+        for input in [
+            "\"${1:>9999999999}\"",
+            "str(repeat(\"a\", 1000))",
+            "regex_replace(\"abc\", \"b\", \"bbbbbbbbbbbbbbbbbbbbbbbbb\")",
+            "format_number(123456789, grouping: true, separator: \"----------\")",
+            "map(str, [range(20)])",
+        ] {
+            assert!(
+                eval_short(input).is_err_and(|e| e
+                    .render(None)
+                    .rendered()
+                    .unwrap()
+                    .contains("value too long")),
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn bytelen_scaling() {
+        // With a megabyte-sized string, ten thousand calls would take a long
+        // time if the byte length were not available in constant time.
+        let s = "å".repeat(500_000);
+        let result = eval_with_bindings(
+            "sum([for _ in range(10000): s.bytelen()])",
+            &ImportConfig::default(),
+            [(Key::new("s"), Object::from(s.as_str()))],
+        );
+        assert_seq!(result, Object::from(10_000_000_000i64));
+    }
+
+    #[test]
+    fn deadline() {
+        let eval_until = |input: &str, timeout: Duration| {
+            eval_with_deadline(input, &ImportConfig::default(), Instant::now() + timeout)
+                .map_err(Error::unrender)
+        };
+
+        assert_seq!(
+            eval_until("[for x in range(100): x]", Duration::from_secs(60)),
+            (0..100).map(Object::from).collect()
+        );
+
+        let start = Instant::now();
+        assert!(eval_until(
+            "[for x in range(1000000000): when x < 0: x]",
+            Duration::from_millis(50)
+        )
+        .is_err_and(|e| e
+            .render(None)
+            .rendered()
+            .unwrap()
+            .contains("deadline exceeded")));
+        assert!(start.elapsed() < Duration::from_secs(10));
+
+        assert!(eval_until(
+            "let f = fn (x) x in [for x in range(1000000000): when f(x) < 0: x]",
+            Duration::from_millis(50)
+        )
+        .is_err_and(|e| e
+            .render(None)
+            .rendered()
+            .unwrap()
+            .contains("deadline exceeded")));
+
+        let start = Instant::now();
+        assert!(
+            eval_until("len(map(fn (x) x, range(20000)))", Duration::from_millis(1)).is_err_and(
+                |e| e
+                    .render(None)
+                    .rendered()
+                    .unwrap()
+                    .contains("deadline exceeded")
+            )
+        );
+        assert!(start.elapsed() < Duration::from_secs(10));
+
+        let start = Instant::now();
+        assert!(
+            eval_until("sum(range(10000000000))", Duration::from_millis(1)).is_err_and(|e| e
+                .render(None)
+                .rendered()
+                .unwrap()
+                .contains("deadline exceeded"))
+        );
+        assert!(start.elapsed() < Duration::from_secs(10));
+    }
+
+    #[test]
+    fn overflow() {
+        let eval_wrapping = |input: &str| {
+            eval_with_overflow(input, &ImportConfig::default(), Overflow::Wrap)
+                .map_err(Error::unrender)
+        };
+
+        let big = Object::new_int_from_str("9223372036854775808").unwrap();
+        assert_seq!(eval("9223372036854775807 + 1"), big.clone());
+        assert_seq!(
+            eval_wrapping("9223372036854775807 + 1"),
+            Object::from(i64::MIN)
+        );
+        assert_seq!(
+            eval_wrapping("-9223372036854775807 - 2"),
+            Object::from(i64::MAX)
+        );
+        assert_seq!(eval_wrapping("4294967296 * 4294967296"), Object::from(0));
+        assert_seq!(eval_wrapping("18446744073709551617 + 0"), Object::from(1));
+        assert_seq!(eval_wrapping("1 + 2"), Object::from(3));
+        assert_seq!(eval_wrapping("1 + 2.5"), Object::from(3.5));
+        assert_seq!(
+            eval_wrapping("map(fn (y) y + 1, [9223372036854775807])"),
+            Object::from(vec![Object::from(i64::MIN)])
+        );
+        assert_seq!(
+            eval("map(fn (y) y + 1, [9223372036854775807])"),
+            Object::from(vec![big.clone()])
+        );
+        assert_seq!(eval_wrapping("-(-9223372036854775807 - 1)"), big);
+    }
+
+    #[test]
+    fn bindings() {
+        let eval_bound = |input: &str, bindings: Vec<(&str, Object)>| {
+            let bindings = bindings.into_iter().map(|(k, v)| (k.key(), v));
+            eval_with_bindings(input, &ImportConfig::default(), bindings).map_err(Error::unrender)
+        };
+
+        assert_seq!(
+            eval_bound(
+                "x + y",
+                vec![("x", Object::from(1)), ("y", Object::from(2))]
+            ),
+            Object::from(3)
+        );
+        assert_seq!(
+            eval_bound(
+                "[for z in x: z + 1]",
+                vec![("x", vec![Object::from(1), Object::from(2)].into())]
+            ),
+            vec![Object::from(2), Object::from(3)].into()
+        );
+        assert_seq!(
+            eval_bound("let x = 2 in x", vec![("x", Object::from(1))]),
+            Object::from(2)
+        );
+        assert_seq!(eval_bound("1", vec![]), Object::from(1));
+        assert!(eval_bound("x", vec![]).is_err());
+    }
+
+    #[test]
+    fn custom_builtins() {
+        let double: Rc<NativeClosure> = Rc::new(|args, _| args[0].mul(&Object::from(2)));
+        let eval_with_double = |input: &str| {
+            let builtins = vec![
+                ("double".key(), Object::new_func(double.clone())),
+                ("len".key(), Object::from(-1)),
+                ("true".key(), Object::from(false)),
+            ];
+            eval_with_builtins(input, &ImportConfig::default(), builtins).map_err(Error::unrender)
+        };
+
+        assert_seq!(eval_with_double("double(21)"), Object::from(42));
+        assert_seq!(
+            eval_with_double("let f = fn (x) double(x) + 1 in f(2)"),
+            Object::from(5)
+        );
+        assert_seq!(
+            eval_with_double("let double = fn (x) x in double(2)"),
+            Object::from(2)
+        );
+        assert_seq!(eval_with_double("len"), Object::from(-1));
+        assert_seq!(eval_with_double("true"), Object::from(true));
+        assert!(eval("double(21)").is_err());
+    }
+
+    #[test]
+    fn parsed() {
+        let importer = ImportConfig::default();
+        let file = parse("let x = [1, 2] in x + [3]").unwrap();
+        let expected: Object = vec![Object::from(1), Object::from(2), Object::from(3)].into();
+        assert_seq!(eval_parsed(&file, &importer), expected.clone());
+        assert_seq!(eval_parsed(&file, &importer), expected);
+
+        let file = parse("x").unwrap();
+        assert!(eval_parsed(&file, &importer).is_err());
+    }
+
+    #[test]
+    fn options() {
+        let importer = ImportConfig::default();
+        let options = EvalOptions {
+            limits: Limits {
+                max_steps: Some(100),
+                ..Default::default()
+            },
+            overflow: Overflow::Wrap,
+            bindings: vec![(Key::new("x"), Object::from(i64::MAX))],
+            builtins: vec![(Key::new("one"), Object::from(1))],
+        };
+
+        assert_seq!(
+            eval_with("x + one", &importer, &options).map_err(Error::unrender),
+            Object::from(i64::MIN)
+        );
+
+        let file = parse("[for y in range(1000): y]").unwrap();
+        assert!(eval_parsed(&file, &importer).is_ok());
+        assert!(
+            eval_parsed_with(&file, &importer, &options).is_err_and(|e| e
+                .render(None)
+                .rendered()
+                .unwrap()
+                .contains("step limit exceeded"))
+        );
+    }
+
+    #[test]
+    fn writer() {
+        let importer = ImportConfig::default();
+        let buffered = |input: &str| {
+            json::JsonValue::try_from(eval(input).unwrap())
+                .unwrap()
+                .dump()
+        };
+        let streamed = |input: &str| {
+            let mut out = Vec::new();
+            eval_to_writer(input, &importer, &mut out).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+
+        for input in [
+            "range(10000)",
+            "[for x in range(1000): {x: x, y: [x * 1.5, \"a\\\"b\\\\c\", null, true]}]",
+            "{a: range(3), \"b c\": {}, d: [], e: -2.5e100}",
+            "\"string\"",
+            "[]",
+            "{}",
+            "[when false: 1]",
+            "[for x in range(1000): {a: x, b: [x]}]",
+            "let y = 2 in [1, ...[y, 3], when true: 4, for x in range(3): [x, x + y]]",
+            "{...{a: 1}, b: {c: 2}, when false: d: 3, for x in range(3): \"k${x}\": x}",
+            "let m = {for x in range(3): \"k${x}\": x} in {...m, z: m}",
+        ] {
+            assert_eq!(streamed(input), buffered(input));
+        }
+
+        assert_eq!(streamed("range(3)"), "[0,1,2]");
+
+        let mut out = Vec::new();
+        assert!(eval_to_writer("[1, fn () 1]", &importer, &mut out).is_err());
+        assert_eq!(out, b"[1,");
+
+        let mut out = Vec::new();
+        assert!(eval_to_writer("[for x in range(3): x, 1 + \"a\"]", &importer, &mut out).is_err());
+        assert_eq!(out, b"[0,1,2");
+
+        let mut out = Vec::new();
+        assert!(
+            eval_to_writer("{a: 1, ...{a: 2}}", &importer, &mut out).is_err_and(|e| e
+                .render(None)
+                .rendered()
+                .unwrap()
+                .contains("duplicate key 'a'"))
+        );
+        assert_eq!(out, b"{\"a\":1");
+    }
+
+    macro_rules! loc {
+        ($loc:expr, $act:ident) => {
+            (Span::from($loc), Action::$act)
+        };
+    }
+
+    macro_rules! err {
         ($reason:expr, $($locs:expr),*) => {
             Err(Error::new($reason).with_locations_vec(vec![$($locs),*]))
         }
     }
 
+    #[test]
+    fn methods() {
+        assert_eq!(eval("\"ABC\".lower()"), eval("lower(\"ABC\")"));
+        assert_eq!(eval("[3, 1, 2].sort()"), eval("sort([3, 1, 2])"));
+        assert_seq!(
+            eval("[3, 1, 2].sort(reverse: true)"),
+            Object::from(vec![3, 2, 1])
+        );
+        assert_seq!(
+            eval("\"a,b\".split(\",\")"),
+            Object::from(vec![Object::from("a"), Object::from("b")])
+        );
+        assert_seq!(eval("range(4).sum()"), Object::from(6));
+        assert_eq!(eval("{a: 1, b: 2}.keys()"), eval("keys({a: 1, b: 2})"));
+        assert_seq!(eval("let f = \"abc\".upper in f()"), Object::from("ABC"));
+
+        // Map fields take precedence
+        assert_seq!(eval("{keys: 1}.keys"), Object::from(1));
+
+        // Methods are only available with dot syntax
+        assert_eq!(
+            eval("{a: 1}[\"values\"]"),
+            err!(
+                Reason::NoField(Key::new("values"), Type::Map),
+                loc!(6..16, Evaluate)
+            )
+        );
+        assert_eq!(
+            eval("\"abc\"[\"upper\"]"),
+            err!(
+                TypeMismatch::Index(Type::String, Type::String),
+                loc!(5..14, Evaluate)
+            )
+        );
+        assert_seq!(
+            eval("path({a: {}}, \"a.keys\", \"missing\")"),
+            Object::from("missing")
+        );
+
+        assert_eq!(
+            eval("\"abc\".sort()"),
+            err!(
+                TypeMismatch::Index(Type::String, Type::String),
+                loc!(5..6, Evaluate)
+            )
+        );
+        assert_eq!(
+            eval("{a: 1}.sort"),
+            err!(
+                Reason::NoField(Key::new("sort"), Type::Map),
+                loc!(6..7, Evaluate)
+            )
+        );
+    }
+
     #[test]
     fn errors() {
         assert_eq!(
-            eval("a"),
-            err!(Reason::Unbound("a".key()), loc!(0, LookupName))
+            eval("a"),
+            err!(Reason::Unbound("a".key()), loc!(0, LookupName))
+        );
+        assert_eq!(
+            eval("let [a] = [] in a"),
+            err!(Unpack::ListTooShort, loc!(4..7, Bind))
+        );
+        assert_eq!(
+            eval("let [a] = [1, 2] in a"),
+            err!(Unpack::ListTooLong, loc!(4..7, Bind))
+        );
+        assert_eq!(
+            eval("(fn (a = 1, b) b)(1, 2)"),
+            err!(Syntax::DefaultSequence, loc!(12, Parse))
+        );
+        assert_eq!(
+            eval("let [a, b = 1, c, d] = [1, 2, 3, 4] in a"),
+            err!(Syntax::DefaultSequence, loc!(15, Parse))
+        );
+        assert_eq!(
+            eval("let [[a = 1, b]] = [[1, 2]] in a"),
+            err!(Syntax::DefaultSequence, loc!(13, Parse))
+        );
+        assert_eq!(
+            eval("{a: 1, a: 2}"),
+            err!(Syntax::DuplicateKey("a".key()), loc!(7, Parse))
+        );
+        assert_eq!(
+            eval("{a: 1, \"a\": 2}"),
+            err!(Syntax::DuplicateKey("a".key()), loc!(7..10, Parse))
+        );
+        assert_eq!(
+            eval("(fn (a, a) a)(1, 2)"),
+            err!(Syntax::DuplicateBinding("a".key()), loc!(8, Parse))
+        );
+        assert_eq!(
+            eval("(fn (a; a) a)(1, a: 2)"),
+            err!(Syntax::DuplicateBinding("a".key()), loc!(8, Parse))
+        );
+        assert_eq!(
+            eval("let [a, [b, a]] = [1, [2, 3]] in a"),
+            err!(Syntax::DuplicateBinding("a".key()), loc!(12, Parse))
+        );
+        assert_eq!(
+            eval("let {a, b as a} = {a: 1, b: 2} in a"),
+            err!(Syntax::DuplicateBinding("a".key()), loc!(13, Parse))
+        );
+        assert_eq!(
+            eval("[for i, i in [1]: i]"),
+            err!(Syntax::DuplicateBinding("i".key()), loc!(8, Parse))
+        );
+        assert_eq!(
+            eval("let final a = 1 let a = 2 in a"),
+            err!(Syntax::ShadowFinal("a".key()), loc!(20, Parse))
+        );
+        assert_eq!(
+            eval("let final a = 1 in let [b, a] = [2, 3] in a"),
+            err!(Syntax::ShadowFinal("a".key()), loc!(27, Parse))
+        );
+        assert_eq!(
+            eval("let final a = 1 in (fn (a) a)(2)"),
+            err!(Syntax::ShadowFinal("a".key()), loc!(24, Parse))
+        );
+        assert_eq!(
+            eval("let final a = 1 in [for a in [2]: a]"),
+            err!(Syntax::ShadowFinal("a".key()), loc!(24, Parse))
+        );
+        assert_eq!(
+            eval("chunk([1, 2], 0)"),
+            err!(Value::OutOfRange, loc!(5..16, Evaluate))
+        );
+        assert_eq!(
+            eval("windows([1, 2], -1)"),
+            err!(Value::OutOfRange, loc!(7..19, Evaluate))
+        );
+        assert_eq!(
+            eval("windows(range(100000), 50000)"),
+            err!(Value::TooLarge, loc!(7..29, Evaluate))
+        );
+        assert_eq!(
+            eval("check({a: {b: [1, \"x\"]}}, {a: {b: {type: \"list\", items: {type: \"int\"}}}})"),
+            err!(
+                Value::Check {
+                    path: "a.b[1]".to_owned(),
+                    reason: "expected int, found str".to_owned(),
+                },
+                loc!(5..73, Evaluate)
+            )
+        );
+        assert_eq!(
+            eval("check({port: 0}, {port: {type: \"int\", min: 1}, host: {}})"),
+            err!(
+                Value::Check {
+                    path: "port".to_owned(),
+                    reason: "expected at least 1, found 0".to_owned(),
+                },
+                loc!(5..57, Evaluate)
+            )
+        );
+        assert_eq!(
+            eval("check({port: 1}, {port: {type: \"int\", min: 1}, host: {}})"),
+            err!(
+                Value::Check {
+                    path: "host".to_owned(),
+                    reason: "missing field".to_owned(),
+                },
+                loc!(5..57, Evaluate)
+            )
+        );
+        assert_eq!(
+            eval("check(1, {type: \"integer\"})"),
+            err!(
+                Value::Schema("unknown type 'integer'".to_owned()),
+                loc!(5..27, Evaluate)
+            )
+        );
+        assert_eq!(
+            eval("regex_match(\"abc\", \"[0-9\")"),
+            err!(
+                Value::Regex("unclosed character class".to_owned()),
+                loc!(11..26, Evaluate)
+            )
+        );
+        assert_eq!(
+            eval("let [...a, ...b] = [1, 2] in a"),
+            err!(Syntax::MultiSlurp, loc!(11..15, Parse))
+        );
+        assert_eq!(
+            eval("let [x, ..., y, ...] = [1, 2] in x"),
+            err!(Syntax::MultiSlurp, loc!(16..19, Parse))
+        );
+        assert_eq!(
+            eval("(fn (a, ...b, c, ...d) a)(1, 2)"),
+            err!(Syntax::MultiSlurp, loc!(17..21, Parse))
+        );
+        assert_eq!(
+            eval("let {x, ...a, y, ...b} = {x: 1, y: 2} in x"),
+            err!(Syntax::MultiSlurp, loc!(17..21, Parse))
+        );
+        assert_eq!(
+            eval("let [[a, b], c] = [[1], 3] in a"),
+            err!(Unpack::ListTooShort, loc!(5..11, Bind), loc!(4..15, Bind))
+        );
+        assert_eq!(
+            eval("(fn ([[a, b], c]) a)([1, 3])"),
+            err!(
+                Unpack::TypeMismatch(BindingType::List, Type::Integer),
+                loc!(6..12, Bind),
+                loc!(5..16, Bind),
+                loc!(20..28, Evaluate)
+            )
+        );
+        assert_eq!(
+            eval("let {a} = {} in a"),
+            err!(
+                Unpack::KeyMissing("a".key()),
+                loc!(5, Bind),
+                loc!(4..7, Bind)
+            )
+        );
+        assert_eq!(
+            eval("let [a] = 1 in a"),
+            err!(
+                Unpack::TypeMismatch(BindingType::List, Type::Integer),
+                loc!(4..7, Bind)
+            )
+        );
+        assert_eq!(
+            eval("let {a} = true in a"),
+            err!(
+                Unpack::TypeMismatch(BindingType::Map, Type::Boolean),
+                loc!(4..7, Bind)
+            )
+        );
+        assert_eq!(
+            eval("[...1]"),
+            err!(TypeMismatch::SplatList(Type::Integer), loc!(4, Splat))
+        );
+        assert_eq!(
+            eval("[for x in 1: x]"),
+            err!(TypeMismatch::Iterate(Type::Integer), loc!(10, Iterate))
+        );
+        assert_eq!(
+            eval("{$null: 1}"),
+            err!(TypeMismatch::MapKey(Type::Null), loc!(2..6, Assign))
+        );
+        assert_eq!(
+            eval("{...[]}"),
+            err!(TypeMismatch::SplatMap(Type::List), loc!(4..6, Splat))
+        );
+        assert_eq!(
+            eval("{for x in 2.2: a: x}"),
+            err!(TypeMismatch::Iterate(Type::Float), loc!(10..13, Iterate))
+        );
+        assert_eq!(
+            eval("(fn (...x) 1)(...true)"),
+            err!(TypeMismatch::SplatArg(Type::Boolean), loc!(17..21, Splat))
+        );
+        assert_eq!(
+            eval("1 + true"),
+            err!(
+                TypeMismatch::BinOp(Type::Integer, Type::Boolean, BinOp::Eager(EagerOp::Add)),
+                loc!(2, Evaluate)
+            )
+        );
+        assert_eq!(
+            eval("\"t\" - 9"),
+            err!(
+                TypeMismatch::BinOp(Type::String, Type::Integer, BinOp::Eager(EagerOp::Subtract)),
+                loc!(4, Evaluate)
+            )
+        );
+        assert_eq!(
+            eval("[] * 9.0"),
+            err!(
+                TypeMismatch::BinOp(Type::List, Type::Float, BinOp::Eager(EagerOp::Multiply)),
+                loc!(3, Evaluate)
+            )
+        );
+        assert_eq!(
+            eval("\"a\" * 1.5"),
+            err!(
+                TypeMismatch::BinOp(Type::String, Type::Float, BinOp::Eager(EagerOp::Multiply)),
+                loc!(4, Evaluate)
+            )
+        );
+        assert_eq!(
+            eval("9 / {}"),
+            err!(
+                TypeMismatch::BinOp(Type::Integer, Type::Map, BinOp::Eager(EagerOp::Divide)),
+                loc!(2, Evaluate)
+            )
+        );
+        assert_eq!(
+            eval("1 / 0"),
+            err!(Value::DivisionByZero, loc!(2, Evaluate))
+        );
+        assert_eq!(
+            eval("1.0 / 0.0"),
+            err!(Value::DivisionByZero, loc!(4, Evaluate))
+        );
+        assert_eq!(
+            eval("1 // 0"),
+            err!(Value::DivisionByZero, loc!(2..4, Evaluate))
+        );
+        assert_eq!(
+            eval("1.5 // -0.0"),
+            err!(Value::DivisionByZero, loc!(4..6, Evaluate))
+        );
+        assert_eq!(
+            eval("2 ^ 100 // 0"),
+            err!(Value::DivisionByZero, loc!(8..10, Evaluate))
+        );
+        assert_eq!(
+            eval("null // {}"),
+            err!(
+                TypeMismatch::BinOp(Type::Null, Type::Map, BinOp::Eager(EagerOp::IntegerDivide)),
+                loc!(5..7, Evaluate)
+            )
+        );
+        assert_eq!(
+            eval("1 not in 2"),
+            err!(
+                TypeMismatch::BinOp(Type::Integer, Type::Integer, BinOp::Eager(EagerOp::NotIn)),
+                loc!(2..8, Evaluate)
+            )
+        );
+        assert_eq!(
+            eval("null < true"),
+            err!(
+                TypeMismatch::BinOp(Type::Null, Type::Boolean, BinOp::Eager(EagerOp::Less)),
+                loc!(5, Evaluate)
+            )
+        );
+        assert_eq!(
+            eval("1 > \"\""),
+            err!(
+                TypeMismatch::BinOp(Type::Integer, Type::String, BinOp::Eager(EagerOp::Greater)),
+                loc!(2, Evaluate)
+            )
+        );
+        assert_eq!(
+            eval("1 < 2 < {}.x"),
+            err!(Reason::NoField("x".key(), Type::Map), loc!(10, Evaluate))
+        );
+        assert_eq!(
+            eval("1 < 2 < \"a\""),
+            err!(
+                TypeMismatch::BinOp(Type::Integer, Type::String, BinOp::Eager(EagerOp::Less)),
+                loc!(6, Evaluate)
+            )
+        );
+        assert_eq!(
+            eval("[1, 2] < [1, \"a\"]"),
+            err!(
+                TypeMismatch::BinOp(Type::List, Type::List, BinOp::Eager(EagerOp::Less)),
+                loc!(7, Evaluate)
+            )
+        );
+        assert_eq!(
+            eval("[] <= 2.1"),
+            err!(
+                TypeMismatch::BinOp(Type::List, Type::Float, BinOp::Eager(EagerOp::LessEqual)),
+                loc!(3..5, Evaluate)
+            )
+        );
+        assert_eq!(
+            eval("{} >= false"),
+            err!(
+                TypeMismatch::BinOp(
+                    Type::Map,
+                    Type::Boolean,
+                    BinOp::Eager(EagerOp::GreaterEqual)
+                ),
+                loc!(3..5, Evaluate)
+            )
+        );
+        assert_eq!(
+            eval("1 has 2"),
+            err!(
+                TypeMismatch::BinOp(
+                    Type::Integer,
+                    Type::Integer,
+                    BinOp::Eager(EagerOp::Contains)
+                ),
+                loc!(2..5, Evaluate)
+            )
+        );
+        assert_eq!(
+            eval("\"${[]}\""),
+            err!(TypeMismatch::Interpolate(Type::List), loc!(3..5, Format))
+        );
+        assert_eq!(
+            eval("\"${iter_keys({})}\""),
+            err!(TypeMismatch::Interpolate(Type::List), loc!(3..16, Format))
         );
         assert_eq!(
-            eval("let [a] = [] in a"),
-            err!(Unpack::ListTooShort, loc!(4..7, Bind))
+            eval("\"${{}}\""),
+            err!(TypeMismatch::Interpolate(Type::Map), loc!(3..5, Format))
         );
         assert_eq!(
-            eval("let [a] = [1, 2] in a"),
-            err!(Unpack::ListTooLong, loc!(4..7, Bind))
+            eval("-null"),
+            err!(
+                TypeMismatch::UnOp(Type::Null, UnOp::ArithmeticalNegate),
+                loc!(0, Evaluate)
+            )
         );
         assert_eq!(
-            eval("let {a} = {} in a"),
+            eval("null[2]"),
             err!(
-                Unpack::KeyMissing("a".key()),
-                loc!(5, Bind),
-                loc!(4..7, Bind)
+                TypeMismatch::Index(Type::Null, Type::Integer),
+                loc!(4..7, Evaluate)
             )
         );
         assert_eq!(
-            eval("let [a] = 1 in a"),
+            eval("2[null]"),
             err!(
-                Unpack::TypeMismatch(BindingType::List, Type::Integer),
-                loc!(4..7, Bind)
+                TypeMismatch::Index(Type::Integer, Type::Null),
+                loc!(1..7, Evaluate)
             )
         );
         assert_eq!(
-            eval("let {a} = true in a"),
+            eval("(2).x"),
             err!(
-                Unpack::TypeMismatch(BindingType::Map, Type::Boolean),
-                loc!(4..7, Bind)
+                TypeMismatch::Index(Type::Integer, Type::String),
+                loc!(3, Evaluate)
             )
         );
         assert_eq!(
-            eval("[...1]"),
-            err!(TypeMismatch::SplatList(Type::Integer), loc!(4, Splat))
+            eval("{a: 1}.b"),
+            err!(Reason::NoField("b".key(), Type::Map), loc!(6, Evaluate))
         );
         assert_eq!(
-            eval("[for x in 1: x]"),
-            err!(TypeMismatch::Iterate(Type::Integer), loc!(10, Iterate))
+            eval("{a: 1}[\"bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb\"]"),
+            err!(
+                Reason::NoField(
+                    "bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb".key(),
+                    Type::Map
+                ),
+                loc!(6..66, Evaluate)
+            )
         );
         assert_eq!(
-            eval("{$null: 1}"),
-            err!(TypeMismatch::MapKey(Type::Null), loc!(2..6, Assign))
+            eval("{a: 1}[\"b\"]"),
+            err!(Reason::NoField("b".key(), Type::Map), loc!(6..11, Evaluate))
         );
         assert_eq!(
-            eval("{...[]}"),
-            err!(TypeMismatch::SplatMap(Type::List), loc!(4..6, Splat))
+            Reason::NoField("b".key(), Type::Map).to_string(),
+            "no field \"b\" on map"
         );
         assert_eq!(
-            eval("{for x in 2.2: a: x}"),
-            err!(TypeMismatch::Iterate(Type::Float), loc!(10..13, Iterate))
+            eval("let a = 1 in a.b"),
+            err!(
+                TypeMismatch::Index(Type::Integer, Type::String),
+                loc!(14, Evaluate)
+            )
         );
         assert_eq!(
-            eval("(fn (...x) 1)(...true)"),
-            err!(TypeMismatch::SplatArg(Type::Boolean), loc!(17..21, Splat))
+            eval("1[\"b\"]"),
+            err!(
+                TypeMismatch::Index(Type::Integer, Type::String),
+                loc!(1..6, Evaluate)
+            )
         );
         assert_eq!(
-            eval("1 + true"),
+            Reason::from(TypeMismatch::Index(Type::Integer, Type::String)).to_string(),
+            "cannot index int with str"
+        );
+        assert_eq!(
+            eval("[]()"),
+            err!(TypeMismatch::Call(Type::List), loc!(2..4, Evaluate))
+        );
+        assert_eq!(
+            eval("true(1)"),
+            err!(TypeMismatch::Call(Type::Boolean), loc!(4..7, Evaluate))
+        );
+
+        assert_eq!(
+            eval("range()"),
             err!(
-                TypeMismatch::BinOp(Type::Integer, Type::Boolean, BinOp::Eager(EagerOp::Add)),
-                loc!(2, Evaluate)
+                TypeMismatch::ArgCount {
+                    low: 1,
+                    high: 3,
+                    received: 0
+                },
+                loc!(5..7, Evaluate)
             )
         );
         assert_eq!(
-            eval("\"t\" - 9"),
+            eval("range(1, 2, 3, 4)"),
             err!(
-                TypeMismatch::BinOp(Type::String, Type::Integer, BinOp::Eager(EagerOp::Subtract)),
-                loc!(4, Evaluate)
+                TypeMismatch::ArgCount {
+                    low: 1,
+                    high: 3,
+                    received: 4
+                },
+                loc!(5..17, Evaluate)
             )
         );
         assert_eq!(
-            eval("[] * 9"),
+            eval("range(1, 2, 0)"),
+            err!(Value::OutOfRange, loc!(5..14, Evaluate))
+        );
+        assert_eq!(
+            eval("repeat(1)"),
             err!(
-                TypeMismatch::BinOp(Type::List, Type::Integer, BinOp::Eager(EagerOp::Multiply)),
-                loc!(3, Evaluate)
+                TypeMismatch::ArgCount {
+                    low: 2,
+                    high: 2,
+                    received: 1
+                },
+                loc!(6..9, Evaluate)
             )
         );
         assert_eq!(
-            eval("9 / {}"),
+            eval("unique([1, fn (x) x])"),
+            err!(TypeMismatch::Hash(Type::Function), loc!(6..21, Evaluate))
+        );
+        assert_eq!(
+            eval("unique([[fn (x) x]])"),
+            err!(TypeMismatch::Hash(Type::Function), loc!(6..20, Evaluate))
+        );
+        assert_eq!(
+            eval("sort([1, \"a\"])"),
             err!(
-                TypeMismatch::BinOp(Type::Integer, Type::Map, BinOp::Eager(EagerOp::Divide)),
-                loc!(2, Evaluate)
+                TypeMismatch::BinOp(Type::String, Type::Integer, BinOp::Eager(EagerOp::Less)),
+                loc!(4..14, Evaluate)
             )
         );
         assert_eq!(
-            eval("null // {}"),
+            eval("sort([1], reverse: 1)"),
             err!(
-                TypeMismatch::BinOp(Type::Null, Type::Map, BinOp::Eager(EagerOp::IntegerDivide)),
-                loc!(5..7, Evaluate)
+                TypeMismatch::ExpectedKwarg {
+                    name: Key::new("reverse"),
+                    allowed: Types::One(Type::Boolean),
+                    received: Type::Integer,
+                },
+                loc!(4..21, Evaluate)
             )
         );
         assert_eq!(
-            eval("null < true"),
+            eval("sort([1], casefold: 1)"),
             err!(
-                TypeMismatch::BinOp(Type::Null, Type::Boolean, BinOp::Eager(EagerOp::Less)),
-                loc!(5, Evaluate)
+                TypeMismatch::ExpectedKwarg {
+                    name: Key::new("casefold"),
+                    allowed: Types::One(Type::Boolean),
+                    received: Type::Integer,
+                },
+                loc!(4..22, Evaluate)
             )
         );
         assert_eq!(
-            eval("1 > \"\""),
+            eval("path({a: {b: 1}}, \"a.c\")"),
             err!(
-                TypeMismatch::BinOp(Type::Integer, Type::String, BinOp::Eager(EagerOp::Greater)),
-                loc!(2, Evaluate)
+                Reason::NoField(Key::new("c"), Type::Map),
+                loc!(4..24, Evaluate)
             )
         );
         assert_eq!(
-            eval("[] <= 2.1"),
+            eval("path({a: [1]}, \"a.1\")"),
+            err!(Value::OutOfRange, loc!(4..21, Evaluate))
+        );
+        assert_eq!(
+            eval("number(\"4.2.1\")"),
+            err!(Value::Convert(Type::Float), loc!(6..15, Evaluate))
+        );
+        assert_eq!(
+            eval("sum([1, \"a\"])"),
             err!(
-                TypeMismatch::BinOp(Type::List, Type::Float, BinOp::Eager(EagerOp::LessEqual)),
+                TypeMismatch::ExpectedElement {
+                    index: 1,
+                    allowed: Types::Two(Type::Integer, Type::Float),
+                    received: Type::String,
+                },
+                loc!(3..13, Evaluate)
+            )
+        );
+        assert_eq!(
+            eval_errstr("product([1, true])"),
+            Some(
+                concat!(
+                "Error: unsuitable type for element at index 1 - expected int or float, got bool\n",
+                "product([1, true])\n",
+                "       ^^^^^^^^^^^\n",
+                "while evaluating at 1:8",
+            )
+                .to_string()
+            )
+        );
+        assert_eq!(
+            eval("first([])"),
+            err!(Value::OutOfRange, loc!(5..9, Evaluate))
+        );
+        assert_eq!(
+            eval("last(\"\")"),
+            err!(Value::OutOfRange, loc!(4..8, Evaluate))
+        );
+        assert_eq!(
+            eval("nth([1], 1)"),
+            err!(Value::OutOfRange, loc!(3..11, Evaluate))
+        );
+        assert_eq!(
+            eval("count(\"banana\", 1)"),
+            err!(
+                TypeMismatch::ExpectedPosArg {
+                    index: 1,
+                    allowed: Types::One(Type::String),
+                    received: Type::Integer,
+                },
+                loc!(5..18, Evaluate)
+            )
+        );
+        assert_eq!(
+            eval("group_by(fn (x) [x], [1])"),
+            err!(TypeMismatch::MapKey(Type::List), loc!(8..25, Evaluate))
+        );
+        assert_eq!(
+            eval("group_by(fn (x) x, [1.5])"),
+            err!(TypeMismatch::MapKey(Type::Float), loc!(8..25, Evaluate))
+        );
+        assert_eq!(
+            eval("group_by(fn (x) len, [1])"),
+            err!(TypeMismatch::MapKey(Type::Function), loc!(8..25, Evaluate))
+        );
+        assert_eq!(
+            eval("map(fn ([k, v]) v, {a: 1})"),
+            err!(
+                Unpack::TypeMismatch(BindingType::List, Type::Integer),
+                loc!(3..26, Evaluate)
+            )
+        );
+        assert_eq!(
+            eval("map(fn ([k, v]) [k], {a: 1})"),
+            err!(Unpack::ListTooShort, loc!(3..28, Evaluate))
+        );
+        assert_eq!(
+            eval("map(fn ([k, v]) [v, k], {a: 1.5})"),
+            err!(TypeMismatch::MapKey(Type::Float), loc!(3..33, Evaluate))
+        );
+        assert_eq!(
+            eval("filter(fn (x) x, 1)"),
+            err!(
+                TypeMismatch::ExpectedPosArg {
+                    index: 1,
+                    allowed: Types::Two(Type::List, Type::Map),
+                    received: Type::Integer,
+                },
+                loc!(6..19, Evaluate)
+            )
+        );
+        assert_eq!(
+            eval("filter(fn (x) x, [], index: 1)"),
+            err!(
+                TypeMismatch::ExpectedKwarg {
+                    name: Key::new("index"),
+                    allowed: Types::One(Type::Boolean),
+                    received: Type::Integer,
+                },
+                loc!(6..30, Evaluate)
+            )
+        );
+        assert_eq!(
+            eval("filter(fn (x) x, {}, index: 1)"),
+            err!(
+                TypeMismatch::ExpectedKwarg {
+                    name: Key::new("index"),
+                    allowed: Types::One(Type::Boolean),
+                    received: Type::Integer,
+                },
+                loc!(6..30, Evaluate)
+            )
+        );
+        assert_eq!(
+            eval("filter_keys(fn (k) true, [])"),
+            err!(
+                TypeMismatch::ExpectedPosArg {
+                    index: 1,
+                    allowed: Types::One(Type::Map),
+                    received: Type::List,
+                },
+                loc!(11..28, Evaluate)
+            )
+        );
+        assert_eq!(
+            eval("map()"),
+            err!(
+                TypeMismatch::ArgCount {
+                    low: 2,
+                    high: 2,
+                    received: 0
+                },
                 loc!(3..5, Evaluate)
             )
         );
         assert_eq!(
-            eval("{} >= false"),
+            eval("map(fn (x) x)([1], [2])"),
             err!(
-                TypeMismatch::BinOp(
-                    Type::Map,
-                    Type::Boolean,
-                    BinOp::Eager(EagerOp::GreaterEqual)
-                ),
+                TypeMismatch::ArgCount {
+                    low: 2,
+                    high: 2,
+                    received: 3
+                },
+                loc!(13..23, Evaluate)
+            )
+        );
+        assert_eq!(
+            eval("range(1, 2, 1.0)"),
+            err!(
+                TypeMismatch::ExpectedPosArg {
+                    index: 2,
+                    allowed: Types::One(Type::Integer),
+                    received: Type::Float,
+                },
+                loc!(5..16, Evaluate)
+            )
+        );
+        assert_eq!(
+            eval("dict([[1.0, 2]])"),
+            err!(TypeMismatch::MapKey(Type::Float), loc!(4..16, Evaluate))
+        );
+        assert_eq!(
+            eval("dict([len], [2])"),
+            err!(TypeMismatch::MapKey(Type::Function), loc!(4..16, Evaluate))
+        );
+        assert_eq!(
+            eval("{$1.5: 2}"),
+            err!(TypeMismatch::MapKey(Type::Float), loc!(2..5, Assign))
+        );
+        assert_eq!(
+            eval("dict([[\"a\"]])"),
+            err!(Unpack::ListTooShort, loc!(4..13, Evaluate))
+        );
+        assert_eq!(
+            eval("dict([\"a\"], [1, 2])"),
+            err!(Unpack::ListTooShort, loc!(4..19, Evaluate))
+        );
+        assert_eq!(
+            eval("rjust(\"7\", 3, \"00\")"),
+            err!(Value::TooLong, loc!(5..19, Evaluate))
+        );
+        assert_eq!(
+            eval("rjust(\"7\", 3, \"\")"),
+            err!(Value::TooShort, loc!(5..17, Evaluate))
+        );
+        assert_eq!(
+            eval("ljust(\"a\", 9223372036854775807)"),
+            err!(Value::TooLarge, loc!(5..31, Evaluate))
+        );
+        assert_eq!(
+            eval("\"ab\" * 9223372036854775807"),
+            err!(Value::TooLarge, loc!(5..6, Evaluate))
+        );
+        assert_eq!(
+            eval("[1, 2] * 9223372036854775807"),
+            err!(Value::TooLarge, loc!(7..8, Evaluate))
+        );
+        assert_eq!(
+            eval("ljust(7, 3)"),
+            err!(
+                TypeMismatch::ExpectedPosArg {
+                    index: 0,
+                    allowed: Types::One(Type::String),
+                    received: Type::Integer,
+                },
+                loc!(5..11, Evaluate)
+            )
+        );
+        assert_eq!(
+            eval("str(1, pretty: 1)"),
+            err!(
+                TypeMismatch::ExpectedKwarg {
+                    name: "pretty".key(),
+                    allowed: Types::One(Type::Boolean),
+                    received: Type::Integer,
+                },
+                loc!(3..17, Evaluate)
+            )
+        );
+        assert_eq!(
+            eval("max()"),
+            err!(
+                TypeMismatch::ArgCount {
+                    low: 1,
+                    high: usize::MAX,
+                    received: 0
+                },
                 loc!(3..5, Evaluate)
             )
         );
         assert_eq!(
-            eval("1 has 2"),
+            eval("max([])"),
+            err!(Unpack::ListTooShort, loc!(3..7, Evaluate))
+        );
+        assert_eq!(
+            eval("min(1)"),
             err!(
-                TypeMismatch::BinOp(
-                    Type::Integer,
-                    Type::Integer,
-                    BinOp::Eager(EagerOp::Contains)
-                ),
-                loc!(2..5, Evaluate)
+                TypeMismatch::ExpectedPosArg {
+                    index: 0,
+                    allowed: Types::One(Type::List),
+                    received: Type::Integer,
+                },
+                loc!(3..6, Evaluate)
+            )
+        );
+        assert_eq!(
+            eval("min(1, \"a\")"),
+            err!(
+                TypeMismatch::BinOp(Type::String, Type::Integer, BinOp::Eager(EagerOp::Less)),
+                loc!(3..11, Evaluate)
+            )
+        );
+        assert_eq!(
+            eval("max(1, 2, key: 1)"),
+            err!(
+                TypeMismatch::ExpectedKwarg {
+                    name: "key".key(),
+                    allowed: Types::One(Type::Function),
+                    received: Type::Integer,
+                },
+                loc!(3..17, Evaluate)
             )
         );
         assert_eq!(
-            eval("\"${[]}\""),
-            err!(TypeMismatch::Interpolate(Type::List), loc!(3..5, Format))
+            eval("checked_add(1, 1.0)"),
+            err!(
+                TypeMismatch::ExpectedPosArg {
+                    index: 1,
+                    allowed: Types::One(Type::Integer),
+                    received: Type::Float,
+                },
+                loc!(11..19, Evaluate)
+            )
         );
         assert_eq!(
-            eval("\"${{}}\""),
-            err!(TypeMismatch::Interpolate(Type::Map), loc!(3..5, Format))
+            eval("getattr({a: 1}, \"b\")"),
+            err!(Reason::Unassigned("b".key()), loc!(7..20, Evaluate))
         );
         assert_eq!(
-            eval("-null"),
+            eval("getattr([], \"b\")"),
             err!(
-                TypeMismatch::UnOp(Type::Null, UnOp::ArithmeticalNegate),
-                loc!(0, Evaluate)
+                TypeMismatch::ExpectedPosArg {
+                    index: 0,
+                    allowed: Types::One(Type::Map),
+                    received: Type::List,
+                },
+                loc!(7..16, Evaluate)
             )
         );
         assert_eq!(
-            eval("null[2]"),
+            eval("hasattr({}, 1)"),
             err!(
-                TypeMismatch::BinOp(Type::Null, Type::Integer, BinOp::Eager(EagerOp::Index)),
-                loc!(4..7, Evaluate)
+                TypeMismatch::ExpectedPosArg {
+                    index: 1,
+                    allowed: Types::One(Type::String),
+                    received: Type::Integer,
+                },
+                loc!(7..14, Evaluate)
             )
         );
         assert_eq!(
-            eval("2[null]"),
-            err!(
-                TypeMismatch::BinOp(Type::Integer, Type::Null, BinOp::Eager(EagerOp::Index)),
-                loc!(1..7, Evaluate)
-            )
+            eval("repeat(1, -1)"),
+            err!(Value::OutOfRange, loc!(6..13, Evaluate))
         );
         assert_eq!(
-            eval("(2).x"),
+            eval("repeat(1, 9223372036854775807)"),
+            err!(Value::TooLarge, loc!(6..30, Evaluate))
+        );
+        assert_eq!(
+            eval("repeat(1, null)"),
             err!(
-                TypeMismatch::BinOp(Type::Integer, Type::String, BinOp::Eager(EagerOp::Index)),
-                loc!(3, Evaluate)
+                TypeMismatch::ExpectedPosArg {
+                    index: 1,
+                    allowed: Types::One(Type::Integer),
+                    received: Type::Null,
+                },
+                loc!(6..15, Evaluate)
             )
         );
+
         assert_eq!(
-            eval("{a: 1}.b"),
-            err!(Reason::Unassigned("b".key()), loc!(6, Evaluate))
+            eval("interpolate(\"\\${a} and \\${b}\", {a: 1})"),
+            err!(Reason::Unassigned("b".key()), loc!(11..38, Evaluate))
         );
         assert_eq!(
-            eval("{a: 1}[\"bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb\"]"),
+            eval("interpolate(\"\\${a}\", [1])"),
             err!(
-                Reason::Unassigned(
-                    "bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb".key()
-                ),
-                loc!(6..66, Evaluate)
+                TypeMismatch::ExpectedPosArg {
+                    index: 1,
+                    allowed: Types::One(Type::Map),
+                    received: Type::List,
+                },
+                loc!(11..25, Evaluate)
             )
         );
         assert_eq!(
-            eval("[]()"),
-            err!(TypeMismatch::Call(Type::List), loc!(2..4, Evaluate))
+            eval("format_number(\"1\")"),
+            err!(
+                TypeMismatch::ExpectedPosArg {
+                    index: 0,
+                    allowed: Types::Two(Type::Integer, Type::Float),
+                    received: Type::String,
+                },
+                loc!(13..18, Evaluate)
+            )
         );
         assert_eq!(
-            eval("true(1)"),
-            err!(TypeMismatch::Call(Type::Boolean), loc!(4..7, Evaluate))
+            eval("format_number(1, grouping: 1)"),
+            err!(
+                TypeMismatch::ExpectedKwarg {
+                    name: "grouping".key(),
+                    allowed: Types::One(Type::Boolean),
+                    received: Type::Integer,
+                },
+                loc!(13..29, Evaluate)
+            )
         );
-
         assert_eq!(
-            eval("range()"),
+            eval("iter_keys([1])"),
             err!(
-                TypeMismatch::ArgCount {
-                    low: 1,
-                    high: 2,
-                    received: 0
+                TypeMismatch::ExpectedPosArg {
+                    index: 0,
+                    allowed: Types::One(Type::Map),
+                    received: Type::List,
                 },
-                loc!(5..7, Evaluate)
+                loc!(9..14, Evaluate)
             )
         );
         assert_eq!(
-            eval("range(1, 2, 3)"),
+            eval("bytelen([])"),
             err!(
-                TypeMismatch::ArgCount {
-                    low: 1,
-                    high: 2,
-                    received: 3
+                TypeMismatch::ExpectedPosArg {
+                    index: 0,
+                    allowed: Types::One(Type::String),
+                    received: Type::List,
                 },
-                loc!(5..14, Evaluate)
+                loc!(7..11, Evaluate)
             )
         );
 
@@ -1896,6 +4827,25 @@ mod tests {
             )
         );
 
+        assert_eq!(
+            eval("isempty(null)"),
+            err!(
+                TypeMismatch::ExpectedPosArg {
+                    index: 0,
+                    allowed: Types::Three(Type::String, Type::List, Type::Map),
+                    received: Type::Null
+                },
+                loc!(7..13, Evaluate)
+            )
+        );
+
+        assert!(eval_errstr("let x = [...range(10000)] in x + 1")
+            .is_some_and(|x| x.len() < 200 && x.contains(": list and int\n")));
+        assert!(
+            eval_errstr("let x = {for i, y in range(10000): \"${i}\": y} in x(x)")
+                .is_some_and(|x| x.len() < 200)
+        );
+
         assert!(eval_errstr("a").is_some_and(|x| x.contains("\na\n^\n")));
         assert!(eval_errstr("\n\na\n").is_some_and(|x| x.contains("\na\n^\n")));
         assert!(eval_errstr("  a  \n").is_some_and(|x| x.contains("\n  a  \n  ^\n")));
@@ -1915,6 +4865,410 @@ mod tests {
             && x.contains(concat!("let h = fn (x) g(x)\n", "                ^^^",))
             && x.contains(concat!("in h(null)\n", "    ^^^^^",))));
     }
+
+    #[test]
+    fn function_arguments() {
+        let f = "let f = fn (a; b) [a, b] in ";
+        let call = |x: &str| eval(&format!("{}{}", f, x));
+
+        assert_seq!(call("f(1, b: 2)"), (1..3).map(Object::from).collect());
+        assert_seq!(call("f(1, b: 2, c: 3)"), (1..3).map(Object::from).collect());
+
+        assert_eq!(
+            call("f(1, 2)"),
+            err!(
+                Unpack::ArgCount {
+                    low: 1,
+                    high: 1,
+                    received: 2
+                },
+                loc!(29..35, Evaluate)
+            )
+        );
+        assert_eq!(
+            call("f(a: 1, b: 2)"),
+            err!(
+                Unpack::ArgCount {
+                    low: 1,
+                    high: 1,
+                    received: 0
+                },
+                loc!(29..41, Evaluate)
+            )
+        );
+        assert_eq!(
+            call("f(1)"),
+            err!(
+                Unpack::KwargMissing("b".key()),
+                loc!(15, Bind),
+                loc!(29..32, Evaluate)
+            )
+        );
+
+        assert_eq!(
+            eval("(fn (a, b = 1, ...c) a)()"),
+            err!(
+                Unpack::ArgCount {
+                    low: 1,
+                    high: usize::MAX,
+                    received: 0
+                },
+                loc!(23..25, Evaluate)
+            )
+        );
+        assert_eq!(
+            eval("(fn (a, ...rest, z) a)(1)"),
+            err!(
+                Unpack::ArgCount {
+                    low: 2,
+                    high: usize::MAX,
+                    received: 1
+                },
+                loc!(22..25, Evaluate)
+            )
+        );
+        assert_eq!(
+            eval("(fn (a, b = 1) a)(1, 2, 3)"),
+            err!(
+                Unpack::ArgCount {
+                    low: 1,
+                    high: 2,
+                    received: 3
+                },
+                loc!(17..26, Evaluate)
+            )
+        );
+
+        assert_eq!(
+            eval_errstr(&format!("{}f(1, 2)", f))
+                .unwrap()
+                .lines()
+                .next(),
+            Some("Error: unexpected positional argument (expected 1, got 2)")
+        );
+        assert_eq!(
+            eval_errstr(&format!("{}f(b: 2)", f))
+                .unwrap()
+                .lines()
+                .next(),
+            Some("Error: missing positional argument (expected 1, got 0)")
+        );
+        assert_eq!(
+            eval_errstr(&format!("{}f(1)", f)).unwrap().lines().next(),
+            Some("Error: missing keyword argument 'b'")
+        );
+    }
+
+    #[test]
+    fn import_extensions() {
+        let root = env::temp_dir().join(format!("gold-import-extensions-{}", process::id()));
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("a.gold"), "1").unwrap();
+        fs::write(root.join("b"), "2").unwrap();
+        fs::write(root.join("c"), "3").unwrap();
+        fs::write(root.join("c.gold"), "4").unwrap();
+        fs::write(root.join("d.gold"), "import \"a\" as a\na + 4").unwrap();
+
+        let plain = ImportConfig::with_path(root.clone());
+        let importer = ImportConfig::with_path(root.clone()).with_extensions(["", "gold"]);
+        let eval_with = |input: &str, importer: &ImportConfig| {
+            crate::eval(input, importer).map_err(Error::unrender)
+        };
+
+        assert_seq!(
+            eval_with("import \"a.gold\" as a\na", &plain),
+            Object::from(1)
+        );
+        assert_seq!(eval_with("import \"b\" as b\nb", &plain), Object::from(2));
+
+        assert_seq!(
+            eval_with("import \"a\" as a\na", &importer),
+            Object::from(1)
+        );
+        assert_seq!(
+            eval_with("import \"a.gold\" as a\na", &importer),
+            Object::from(1)
+        );
+        assert_seq!(
+            eval_with("import \"b\" as b\nb", &importer),
+            Object::from(2)
+        );
+        assert_seq!(
+            eval_with("import \"d\" as d\nd", &importer),
+            Object::from(5)
+        );
+
+        assert_eq!(
+            eval_with("import \"c\" as c\nc", &importer),
+            err!(Reason::AmbiguousImport("c".to_owned()), loc!(7..10, Import))
+        );
+        assert_eq!(
+            eval_with("import \"e\" as e\ne", &importer),
+            err!(Reason::UnknownImport("e".to_owned()), loc!(7..10, Import))
+        );
+        assert_eq!(
+            eval_with("import \"e\" as e\ne", &plain),
+            err!(Reason::UnknownImport("e".to_owned()), loc!(7..10, Import))
+        );
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn import_limits() {
+        let root = env::temp_dir().join(format!("gold-import-limits-{}", process::id()));
+        fs::create_dir_all(&root).unwrap();
+        fs::write(
+            root.join("loop.gold"),
+            "len([for x in range(1000000000): x])",
+        )
+        .unwrap();
+        fs::write(root.join("wrap.gold"), "9223372036854775807 + 1").unwrap();
+        fs::write(root.join("nested.gold"), "import \"wrap.gold\" as x\nx").unwrap();
+
+        let importer = ImportConfig::with_path(root.clone());
+        let eval_options = |input: &str, options: EvalOptions| {
+            eval_with(input, &importer, &options).map_err(Error::unrender)
+        };
+
+        let options = EvalOptions {
+            limits: Limits {
+                max_steps: Some(1000),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert!(
+            eval_options("import \"loop.gold\" as x\nx", options).is_err_and(|e| e
+                .render(None)
+                .rendered()
+                .unwrap()
+                .contains("step limit exceeded"))
+        );
+
+        let options = EvalOptions {
+            limits: Limits {
+                deadline: Some(Instant::now() + Duration::from_millis(1)),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert!(
+            eval_options("import \"loop.gold\" as x\nx", options).is_err_and(|e| e
+                .render(None)
+                .rendered()
+                .unwrap()
+                .contains("deadline exceeded"))
+        );
+
+        let options = EvalOptions {
+            overflow: Overflow::Wrap,
+            ..Default::default()
+        };
+        assert_seq!(
+            eval_options("import \"nested.gold\" as x\nx", options),
+            Object::from(i64::MIN)
+        );
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn import_default() {
+        let root = env::temp_dir().join(format!("gold-import-default-{}", process::id()));
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("a.gold"), "1").unwrap();
+        fs::write(root.join("b.gold"), "let x = in x").unwrap();
+        fs::write(root.join("c.gold"), "import \"d\" as d\n[1, d]").unwrap();
+
+        let importer = ImportConfig::with_path(root.clone())
+            .with_extensions(["gold"])
+            .with_default(Object::null());
+        let eval_with = |input: &str| crate::eval(input, &importer).map_err(Error::unrender);
+
+        // Present modules are imported as usual
+        assert_seq!(eval_with("import \"a\" as a\na"), Object::from(1));
+
+        // Absent modules fall back to the default, also in imported files
+        assert_seq!(eval_with("import \"d\" as d\nd"), Object::null());
+        assert_seq!(
+            eval_with("import \"c\" as c\nc"),
+            Object::from(vec![Object::from(1), Object::null()])
+        );
+
+        // Errors in present modules are not masked
+        assert!(eval_with("import \"b\" as b\nb").is_err());
+
+        // The std namespace is reserved
+        assert_eq!(
+            eval_with("import \"stdx\" as x\nx"),
+            err!(
+                Reason::UnknownImport("stdx".to_owned()),
+                loc!(7..13, Import)
+            )
+        );
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn interpolation() {
+        assert_seq!(
+            eval("\"${1}, ${true}, ${null}\""),
+            Object::new_str_natural("1, true, null")
+        );
+        assert_seq!(
+            eval("\"f = ${len}\""),
+            Object::new_str_natural("f = <function>")
+        );
+        assert_seq!(
+            eval("let f = fn (x) x in \"${f}${f}\""),
+            Object::new_str_natural("<function><function>")
+        );
+        assert_eq!(
+            eval("\"${[1, 2]}\""),
+            err!(TypeMismatch::Interpolate(Type::List), loc!(3..9, Format))
+        );
+        assert_eq!(
+            eval("\"${{a: 1}}\""),
+            err!(TypeMismatch::Interpolate(Type::Map), loc!(3..9, Format))
+        );
+        assert_eq!(
+            eval("\"${len:d}\""),
+            err!(
+                TypeMismatch::InterpolateSpec(Type::Function),
+                loc!(3..6, Format)
+            )
+        );
+    }
+
+    #[test]
+    fn environment() {
+        let mut env = Environment::new(ImportConfig::default());
+
+        assert_seq!(
+            env.eval("let a = 1"),
+            Object::from(vec![("a", Object::from(1))])
+        );
+        assert_seq!(env.eval("a + 1"), Object::from(2));
+        assert_eq!(env.get("a".key()), Some(&Object::from(1)));
+
+        // Expressions don't bind anything
+        assert_seq!(env.eval("let b = 2 in b"), Object::from(2));
+        assert!(env.get("b".key()).is_none());
+
+        // Redefinitions shadow, but don't affect existing values
+        assert!(env.eval("let f = fn () a").is_ok());
+        assert!(env.eval("let a = 10, b = a + 1").is_ok());
+        assert_seq!(env.eval("[a, b, f()]"), Object::from(vec![10, 11, 1]));
+
+        // Failing inputs leave the environment unchanged
+        assert!(env.eval("let a = 20, c = a + null").is_err());
+        assert!(env.eval("let a = ").is_err());
+        assert_eq!(env.get("a".key()), Some(&Object::from(10)));
+        assert!(env.get("c".key()).is_none());
+    }
+
+    #[test]
+    fn capabilities() {
+        let importer = ImportConfig::default().with_capabilities(Capabilities {
+            env: true,
+            ..Default::default()
+        });
+        let eval = |input: &str| crate::eval(input, &importer).map_err(Error::unrender);
+
+        // Use a variable the test process already has, since setting one
+        // would race with other tests
+        let path = Object::from(env::var("PATH").unwrap());
+        assert_seq!(eval("env(\"PATH\")"), path.clone());
+        assert_seq!(eval("env(\"PATH\", 1)"), path.clone());
+        assert_seq!(
+            eval("env(\"GOLD_TEST_CAPABILITIES_UNSET\", 1)"),
+            Object::from(1)
+        );
+        assert_seq!(
+            eval("env(\"GOLD_TEST_CAPABILITIES_UNSET\")"),
+            Object::null()
+        );
+        assert_seq!(eval("map(env, [\"PATH\"])"), Object::from(vec![path]));
+
+        // Disabled by default
+        assert_eq!(
+            eval_raw("env(\"PATH\")").map_err(Error::unrender),
+            err!(Reason::Disabled("env".key()), loc!(3..11, Evaluate))
+        );
+
+        let parent = env::temp_dir().join(format!("gold-read-file-{}", process::id()));
+        let root = parent.join("root");
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::write(root.join("sub").join("a.txt"), "contents").unwrap();
+        fs::write(parent.join("secret.txt"), "secret").unwrap();
+        fs::write(parent.join("outside.gold"), "read_file(\"secret.txt\")").unwrap();
+        fs::write(root.join("sub").join("b.gold"), "read_file(\"sub/a.txt\")").unwrap();
+
+        let importer = ImportConfig::with_path(root.clone()).with_capabilities(Capabilities {
+            read_file: true,
+            ..Default::default()
+        });
+        let eval = |input: &str| crate::eval(input, &importer).map_err(Error::unrender);
+
+        assert_seq!(eval("read_file(\"sub/a.txt\")"), Object::from("contents"));
+        assert_seq!(
+            eval("read_file(\"sub/../sub/a.txt\")"),
+            Object::from("contents")
+        );
+
+        // Files outside the root are indistinguishable from missing files
+        assert_eq!(
+            eval("read_file(\"../secret.txt\")"),
+            err!(
+                FileSystem::Read(PathBuf::from("../secret.txt")),
+                loc!(9..26, Evaluate)
+            )
+        );
+        assert_eq!(
+            eval("read_file(\"../missing.txt\")"),
+            err!(
+                FileSystem::Read(PathBuf::from("../missing.txt")),
+                loc!(9..27, Evaluate)
+            )
+        );
+
+        // Imported files read relative to the same root, wherever they are
+        assert_seq!(
+            eval("import \"sub/b.gold\" as b\nb"),
+            Object::from("contents")
+        );
+        assert!(eval("import \"../outside.gold\" as x\nx").is_err_and(|e| e
+            .render(None)
+            .rendered()
+            .unwrap()
+            .contains("couldn't read file: secret.txt")));
+
+        // Without a root path, there is nowhere to read from
+        let rootless = ImportConfig::default().with_capabilities(Capabilities {
+            read_file: true,
+            ..Default::default()
+        });
+        assert_eq!(
+            crate::eval("read_file(\"sub/a.txt\")", &rootless).map_err(Error::unrender),
+            err!(
+                FileSystem::Read(PathBuf::from("sub/a.txt")),
+                loc!(9..22, Evaluate)
+            )
+        );
+        assert_eq!(
+            eval_with_builtins(
+                "read_file(\"sub/a.txt\")",
+                &ImportConfig::with_path(root),
+                []
+            )
+            .map_err(Error::unrender),
+            err!(Reason::Disabled("read_file".key()), loc!(9..22, Evaluate))
+        );
+
+        fs::remove_dir_all(parent).unwrap();
+    }
 }
 
 #[cfg(test)]
@@ -2278,4 +5632,12 @@ mod examples {
     fn import() {
         assert_seq!(eval("import.gold"), Object::from(3));
     }
+
+    #[test]
+    fn import_module() {
+        assert_seq!(
+            eval("import-module.gold"),
+            Object::from(vec![Object::from(6), Object::from(9), Object::from(1)])
+        );
+    }
 }
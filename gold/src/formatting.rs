@@ -1,3 +1,5 @@
+use std::fmt::{Display, Write};
+
 use serde::{Deserialize, Serialize};
 
 use crate::{error::Error, object::Int};
@@ -173,6 +175,67 @@ impl FormatSpec {
     }
 }
 
+/// Write the format specifier in the syntax accepted by the parser.
+impl Display for FormatSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(align) = self.align {
+            if self.fill != ' ' {
+                f.write_char(self.fill)?;
+            }
+            f.write_char(match align {
+                AlignSpec::String(StringAlignSpec::Left) => '<',
+                AlignSpec::String(StringAlignSpec::Right) => '>',
+                AlignSpec::String(StringAlignSpec::Center) => '^',
+                AlignSpec::AfterSign => '=',
+            })?;
+        }
+
+        match self.sign {
+            Some(SignSpec::Plus) => f.write_char('+')?,
+            Some(SignSpec::Minus) => f.write_char('-')?,
+            Some(SignSpec::Space) => f.write_char(' ')?,
+            None => {}
+        }
+
+        if self.alternate {
+            f.write_char('#')?;
+        }
+
+        if let Some(width) = self.width {
+            write!(f, "{}", width)?;
+        }
+
+        match self.grouping {
+            Some(GroupingSpec::Comma) => f.write_char(',')?,
+            Some(GroupingSpec::Underscore) => f.write_char('_')?,
+            None => {}
+        }
+
+        if let Some(precision) = self.precision {
+            write!(f, ".{}", precision)?;
+        }
+
+        if let Some(fmt_type) = self.fmt_type {
+            f.write_char(match fmt_type {
+                FormatType::String => 's',
+                FormatType::Integer(IntegerFormatType::Binary) => 'b',
+                FormatType::Integer(IntegerFormatType::Character) => 'c',
+                FormatType::Integer(IntegerFormatType::Decimal) => 'd',
+                FormatType::Integer(IntegerFormatType::Octal) => 'o',
+                FormatType::Integer(IntegerFormatType::Hex(UppercaseSpec::Lower)) => 'x',
+                FormatType::Integer(IntegerFormatType::Hex(UppercaseSpec::Upper)) => 'X',
+                FormatType::Float(FloatFormatType::Sci(UppercaseSpec::Lower)) => 'e',
+                FormatType::Float(FloatFormatType::Sci(UppercaseSpec::Upper)) => 'E',
+                FormatType::Float(FloatFormatType::Fixed) => 'f',
+                FormatType::Float(FloatFormatType::General) => 'g',
+                FormatType::Float(FloatFormatType::Percentage) => '%',
+            })?;
+        }
+
+        Ok(())
+    }
+}
+
 impl Default for FormatSpec {
     fn default() -> Self {
         Self {
@@ -130,11 +130,15 @@ pub struct Token<'a> {
     pub text: &'a str,
 }
 
+/// The default maximal number of digits in an integer literal.
+pub const DEFAULT_MAX_DIGITS: usize = 10_000;
+
 /// A lexer, for tokenizing a string of code.
 #[derive(Clone, Copy, Debug)]
 pub struct Lexer<'a> {
     code: &'a str,
     position: Position,
+    max_digits: usize,
 }
 
 lazy_static! {
@@ -169,9 +173,15 @@ impl<'a> Lexer<'a> {
         Lexer {
             code,
             position: Position::zero(),
+            max_digits: DEFAULT_MAX_DIGITS,
         }
     }
 
+    /// Set the maximal number of digits allowed in integer literals.
+    pub fn with_max_digits(self, max_digits: usize) -> Lexer<'a> {
+        Lexer { max_digits, ..self }
+    }
+
     /// Return the current position of the lexer.
     fn position(&self) -> Position {
         self.position
@@ -203,7 +213,8 @@ impl<'a> Lexer<'a> {
     fn skip(self, offset: usize, delta_line: u32) -> Self {
         Lexer {
             code: &self.code[offset..],
-            position: self.position.adjust(offset, delta_line),
+            position: self.position.adjust(&self.code[..offset], delta_line),
+            ..self
         }
     }
 
@@ -214,11 +225,9 @@ impl<'a> Lexer<'a> {
     /// utility since it will be called from tail position in other methods that
     /// may return errors.
     fn skip_tag(self, offset: usize, delta_line: u32, kind: TokenType) -> LexResult<'a> {
-        let code = self.code[..offset].tag(self.position.with_length(offset));
-        Ok((
-            self.skip(offset, delta_line),
-            code.map(|span| Token { kind, text: span }),
-        ))
+        let next = self.skip(offset, delta_line);
+        let code = self.code[..offset].tag(next.position - self.position);
+        Ok((next, code.map(|span| Token { kind, text: span })))
     }
 
     /// Traverse over the match of a regular expression and return a token.
@@ -257,6 +266,22 @@ impl<'a> Lexer<'a> {
         self
     }
 
+    /// Skip to the beginning of the next line that doesn't start with
+    /// whitespace, or to the end of input. Used for error recovery.
+    fn skip_to_unindented_line(mut self) -> Self {
+        loop {
+            match self.code.find('\n') {
+                None => return self.skip(self.code.len(), 0),
+                Some(end) => {
+                    self = self.skip(end + 1, 1);
+                    if self.peek().is_some_and(|c| !c.is_whitespace()) {
+                        return self;
+                    }
+                }
+            }
+        }
+    }
+
     /// Skip whitespace at the beginning of a line. Will not skip comments or
     /// newlines.
     fn skip_indent(self) -> Self {
@@ -511,13 +536,22 @@ impl<'a> InputLength for Lexer<'a> {
 pub struct CachedLexer<'a> {
     lexer: Lexer<'a>,
     cache: &'a LexCache<'a>,
+
+    /// Whether the `in` keyword is reserved for ending a let-binding, rather
+    /// than being available as a binary operator. This is parser state, which
+    /// is carried along with the input for convenience.
+    no_in: bool,
 }
 
 impl<'a> CachedLexer<'a> {
     /// Construct a new cached lexer. Use [`Lexer::cache`] to make the cache
     /// cell.
     fn new(lexer: Lexer<'a>, cache: &'a LexCache<'a>) -> CachedLexer<'a> {
-        CachedLexer { lexer, cache }
+        CachedLexer {
+            lexer,
+            cache,
+            no_in: false,
+        }
     }
 
     /// Return the current buffer position.
@@ -525,12 +559,25 @@ impl<'a> CachedLexer<'a> {
         self.lexer.position()
     }
 
-    /// Construct a new cached lexer with the same cache cell as this one.
+    /// Return the maximal number of digits allowed in integer literals.
+    pub fn max_digits(&self) -> usize {
+        self.lexer.max_digits
+    }
+
+    /// Return true if the `in` keyword is reserved for ending a let-binding.
+    pub fn no_in(&self) -> bool {
+        self.no_in
+    }
+
+    /// Reserve the `in` keyword for ending a let-binding, or release it.
+    pub fn with_no_in(self, no_in: bool) -> CachedLexer<'a> {
+        CachedLexer { no_in, ..self }
+    }
+
+    /// Construct a new cached lexer with the same cache cell and state as this
+    /// one.
     fn cachify(&self, lexer: Lexer<'a>) -> CachedLexer<'a> {
-        CachedLexer {
-            lexer,
-            cache: self.cache,
-        }
+        CachedLexer { lexer, ..*self }
     }
 
     /// Return an error at the current position.
@@ -573,7 +620,13 @@ impl<'a> CachedLexer<'a> {
 
     /// Skip an arbitrary amount of whitespace (including comments and newlines).
     pub fn skip_whitespace(self) -> CachedLexer<'a> {
-        self.lexer.skip_whitespace().with_cache(self.cache)
+        self.cachify(self.lexer.skip_whitespace())
+    }
+
+    /// Skip to the beginning of the next line that doesn't start with
+    /// whitespace, or to the end of input.
+    pub fn skip_to_unindented_line(self) -> CachedLexer<'a> {
+        self.cachify(self.lexer.skip_to_unindented_line())
     }
 }
 
@@ -587,7 +640,7 @@ impl<'a> InputLength for CachedLexer<'a> {
 
 #[cfg(test)]
 mod tests {
-    use crate::error::Taggable;
+    use crate::error::{Taggable, Tagged};
 
     use super::{Lexer, Token, TokenType};
 
@@ -1270,4 +1323,34 @@ mod tests {
         lex = tok!(lex.next_token(), closebrace().tag(21));
         stop!(lex);
     }
+
+    #[test]
+    fn end_positions() {
+        let cache = Lexer::cache();
+        let end = |tok: &Tagged<Token>| (tok.span().end_line(), tok.span().end_column());
+
+        let lex = Lexer::new(concat!(
+            "{\n",
+            "   z:: here's some\n",
+            "       text\n",
+            "}\n",
+        ))
+        .with_cache(&cache);
+        let (lex, _) = lex.next_token().unwrap();
+        let (lex, _) = lex.next_key().unwrap();
+        let (lex, _) = lex.next_token().unwrap();
+        let (lex, tok) = lex.next_multistring(3).unwrap();
+        assert_eq!((tok.span().line(), tok.span().column()), (1, 6));
+        assert_eq!(end(&tok), (3, 0));
+        let (_, tok) = lex.next_token().unwrap();
+        assert_eq!(end(&tok), (3, 1));
+
+        let lex = Lexer::new("\"😀\" abc").with_cache(&cache);
+        let (lex, _) = lex.next_token().unwrap();
+        let (lex, tok) = lex.next_string().unwrap();
+        assert_eq!(end(&tok), (0, 2));
+        let (lex, _) = lex.next_string().unwrap();
+        let (_, tok) = lex.next_token().unwrap();
+        assert_eq!(end(&tok), (0, 7));
+    }
 }
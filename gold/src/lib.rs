@@ -36,17 +36,23 @@ mod compile;
 mod types;
 
 use std::fs::read_to_string;
+use std::io::{BufWriter, Write};
 use std::path::Path;
+use std::time::Instant;
 
 use error::FileSystem;
 use eval::Vm;
 
-pub use error::Error;
-pub use eval::ImportConfig;
-pub use object::Object;
-pub use parsing::parse;
+pub use ast::high::File;
+pub use error::{Error, Span, Tagged, Warning};
+pub use eval::{Capabilities, Environment, EvalOptions, ImportConfig, Limits, Overflow};
+pub use object::{DisplayOptions, ListBuilder, MapBuilder, NonFinite, Object, QuoteStyle};
+pub use parsing::{parse, parse_all_errors, parse_with_max_digits};
 pub use types::{Key, List, Map, Res, Type};
 
+#[cfg(feature = "python")]
+pub use ast::high::PyFile;
+
 #[cfg(feature = "python")]
 pub use eval::PyImportConfig;
 
@@ -56,13 +62,162 @@ pub use eval::PyImportConfig;
 /// imports will not be possible. Provide a custom import resolver for full
 /// control over imports.
 pub fn eval(input: &str, importer: &ImportConfig) -> Res<Object> {
-    let ast = parse(input)?;
-    let lowered = ast.lower()?;
-    let code = lowered.compile()?;
-    let mut vm = Vm::new(importer);
+    eval_with(input, importer, &EvalOptions::default())
+}
+
+/// Evaluate Gold code with the given options and return the result.
+///
+/// This is equivalent to [`eval()`], except that evaluation is configured by
+/// `options` (see [`EvalOptions`]).
+pub fn eval_with(input: &str, importer: &ImportConfig, options: &EvalOptions) -> Res<Object> {
+    eval_parsed_with(&parse(input)?, importer, options)
+}
+
+/// Evaluate an already parsed Gold file and return the result.
+///
+/// This is equivalent to [`eval()`], but allows callers to parse (and possibly
+/// inspect or transform) the code once and evaluate it as often as needed.
+pub fn eval_parsed(file: &File, importer: &ImportConfig) -> Res<Object> {
+    eval_parsed_with(file, importer, &EvalOptions::default())
+}
+
+/// Evaluate an already parsed Gold file with the given options and return the
+/// result.
+///
+/// This is the combination of [`eval_parsed()`] and [`eval_with()`].
+pub fn eval_parsed_with(
+    file: &File,
+    importer: &ImportConfig,
+    options: &EvalOptions,
+) -> Res<Object> {
+    let ast = file.clone().with_bindings(options.bindings.iter().cloned());
+    let mut builtins = importer.builtins();
+    for (key, value) in &options.builtins {
+        builtins.insert(*key, value.clone());
+    }
+    let code = ast.lower_with_builtins(builtins)?.compile()?;
+    let mut vm = Vm::new(importer)
+        .with_limits(options.limits)
+        .with_overflow(options.overflow);
     vm.eval(code)
 }
 
+/// Evaluate Gold code and write the result to `out` as compact JSON.
+///
+/// If the code evaluates to a list or map literal (possibly in the body of
+/// let-blocks), its elements are written as soon as they are produced, and
+/// are not kept in memory. This includes the elements produced by loops. In
+/// such a map, each key may only be given once, since an entry can't be
+/// replaced after it's been written. Any other result is evaluated in full
+/// before it's written.
+///
+/// If evaluation fails, or the result can't be serialized, e.g. because it
+/// contains a function, the output written up to that point is left in
+/// `out`.
+pub fn eval_to_writer(input: &str, importer: &ImportConfig, out: &mut impl Write) -> Res<()> {
+    let code = parse(input)?
+        .lower_streaming(importer.builtins())?
+        .compile()?;
+    let mut out = BufWriter::new(out);
+    let mut vm = Vm::new(importer).with_sink(&mut out);
+    let result = vm.eval(code)?;
+    vm.finish(result)?;
+    drop(vm);
+    out.flush().map_err(|_| Error::new(FileSystem::Write))
+}
+
+/// Evaluate Gold code with resource limits and return the result.
+///
+/// This is a shorthand for [`eval_with()`] with only [`EvalOptions::limits`]
+/// set. Use this for evaluating untrusted code.
+pub fn eval_with_limits(input: &str, importer: &ImportConfig, limits: Limits) -> Res<Object> {
+    let options = EvalOptions {
+        limits,
+        ..Default::default()
+    };
+    eval_with(input, importer, &options)
+}
+
+/// Evaluate Gold code with a deadline and return the result.
+///
+/// This is a shorthand for [`eval_with_limits()`] with only
+/// [`Limits::deadline`] set: evaluation fails if it doesn't finish by
+/// `deadline`.
+pub fn eval_with_deadline(input: &str, importer: &ImportConfig, deadline: Instant) -> Res<Object> {
+    let limits = Limits {
+        deadline: Some(deadline),
+        ..Default::default()
+    };
+    eval_with_limits(input, importer, limits)
+}
+
+/// Evaluate Gold code with the given integer overflow behavior and return the
+/// result.
+///
+/// This is a shorthand for [`eval_with()`] with only
+/// [`EvalOptions::overflow`] set.
+pub fn eval_with_overflow(input: &str, importer: &ImportConfig, overflow: Overflow) -> Res<Object> {
+    let options = EvalOptions {
+        overflow,
+        ..Default::default()
+    };
+    eval_with(input, importer, &options)
+}
+
+/// Evaluate Gold code with additional names bound to the given objects and
+/// return the result.
+///
+/// This is a shorthand for [`eval_with()`] with only
+/// [`EvalOptions::bindings`] set.
+pub fn eval_with_bindings(
+    input: &str,
+    importer: &ImportConfig,
+    bindings: impl IntoIterator<Item = (Key, Object)>,
+) -> Res<Object> {
+    let options = EvalOptions {
+        bindings: bindings.into_iter().collect(),
+        ..Default::default()
+    };
+    eval_with(input, importer, &options)
+}
+
+/// Evaluate Gold code with additional builtins and return the result.
+///
+/// This is a shorthand for [`eval_with()`] with only
+/// [`EvalOptions::builtins`] set.
+pub fn eval_with_builtins(
+    input: &str,
+    importer: &ImportConfig,
+    builtins: impl IntoIterator<Item = (Key, Object)>,
+) -> Res<Object> {
+    let options = EvalOptions {
+        builtins: builtins.into_iter().collect(),
+        ..Default::default()
+    };
+    eval_with(input, importer, &options)
+}
+
+/// Parse Gold code and format it as canonical source code.
+///
+/// The output is consistently indented and uses trailing commas in multi-line
+/// collections, normalized operator spacing and only necessary parentheses.
+/// Comments are not preserved. Formatting is idempotent: formatting the
+/// output again yields the same text.
+pub fn format_source(input: &str) -> Res<String> {
+    let ast = parse(input)?;
+    Ok(ast::print::file(&ast))
+}
+
+/// Parse Gold code and check it for code that is valid, but possibly a
+/// mistake, such as shadowed bindings.
+///
+/// This does not evaluate the code. Each warning is wrapped in a [`Tagged`]
+/// with the [`Span`] of the code it refers to.
+pub fn lint(input: &str) -> Res<Vec<Tagged<Warning>>> {
+    let ast = parse(input)?;
+    Ok(ast::lint::file(&ast))
+}
+
 /// Evaluate Gold code and return the result.
 ///
 /// This is equivalent to calling [`eval()`] with no path and an import resolver that always fails.
@@ -0,0 +1,72 @@
+//! Fluent builders for constructing collections from Rust.
+
+use super::{Key, List, Map, Object};
+
+/// Builder for Gold maps, obtained from [`Object::map()`].
+///
+/// ```
+/// use gold::Object;
+///
+/// let obj: Object = Object::map()
+///     .insert("name", "gold")
+///     .insert("version", 1)
+///     .build();
+/// assert_eq!(obj.to_string(), "{name: \"gold\", version: 1}");
+/// ```
+#[derive(Debug)]
+pub struct MapBuilder(Map);
+
+impl Default for MapBuilder {
+    fn default() -> Self {
+        Self(Map::new())
+    }
+}
+
+impl MapBuilder {
+    /// Add a key-value pair, replacing any previous value with the same key.
+    pub fn insert(mut self, key: impl AsRef<str>, value: impl Into<Object>) -> Self {
+        self.0.insert(Key::new(key), value.into());
+        self
+    }
+
+    /// Finish building and return the map object.
+    pub fn build(self) -> Object {
+        Object::from(self.0)
+    }
+}
+
+impl From<MapBuilder> for Object {
+    fn from(value: MapBuilder) -> Self {
+        value.build()
+    }
+}
+
+/// Builder for Gold lists, obtained from [`Object::list()`].
+///
+/// ```
+/// use gold::Object;
+///
+/// let obj: Object = Object::list().push(1).push("two").build();
+/// assert_eq!(obj.to_string(), "[1, \"two\"]");
+/// ```
+#[derive(Debug, Default)]
+pub struct ListBuilder(List);
+
+impl ListBuilder {
+    /// Append an element.
+    pub fn push(mut self, value: impl Into<Object>) -> Self {
+        self.0.push(value.into());
+        self
+    }
+
+    /// Finish building and return the list object.
+    pub fn build(self) -> Object {
+        Object::from(self.0)
+    }
+}
+
+impl From<ListBuilder> for Object {
+    fn from(value: ListBuilder) -> Self {
+        value.build()
+    }
+}
@@ -7,7 +7,7 @@ use gc::{Finalize, Gc, Trace};
 use serde::{Deserialize, Serialize};
 
 #[cfg(feature = "python")]
-use pyo3::{pyclass, pymethods, FromPyObject, PyErr, IntoPyObject, Bound, PyAny, Python};
+use pyo3::{pyclass, pymethods, Bound, FromPyObject, IntoPyObject, Py, PyAny, PyErr, Python};
 
 #[cfg(feature = "python")]
 use pyo3::types::{PyAnyMethods, PyDict, PyTuple};
@@ -15,13 +15,16 @@ use pyo3::types::{PyAnyMethods, PyDict, PyTuple};
 #[cfg(feature = "python")]
 use pyo3::exceptions::PyTypeError;
 
+#[cfg(feature = "python")]
+use crate::error::Reason;
+
 #[cfg(feature = "python")]
 use crate::Error;
 
 use super::{List, Map, Object};
 use crate::compile::CompiledFunction;
 use crate::error::Internal;
-use crate::eval::Vm;
+use crate::eval::{Context, Vm};
 use crate::types::{Builtin, Cell, GcCell, NativeClosure, Res};
 use crate::ImportConfig;
 
@@ -92,16 +95,28 @@ impl Func {
     pub fn call(&self, args: &List, kwargs: Option<&Map>) -> Res<Object> {
         let Self(this) = self;
         match this {
-            FuncV::NativeClosure(f) => f(args, kwargs),
-            FuncV::Builtin(f) => f.call(args, kwargs),
+            FuncV::NativeClosure(f) => Context::current().check_result(f(args, kwargs)?),
+            FuncV::Builtin(f) => Context::current().check_result(f.call(args, kwargs)?),
             FuncV::Closure(f, e) => {
                 let importer = ImportConfig::default();
-                let mut vm = Vm::new(&importer);
+                let mut vm = Vm::nested(&importer);
                 vm.eval_with_args(f.as_ref().clone(), e.clone(), args, kwargs)
             }
         }
     }
 
+    /// The minimal and maximal (if any) number of positional arguments this
+    /// function accepts. Native closures accept any number of arguments as
+    /// far as Gold can tell.
+    pub fn arity(&self) -> (usize, Option<usize>) {
+        let Self(this) = self;
+        match this {
+            FuncV::Closure(f, _) => f.arity,
+            FuncV::Builtin(f) => f.arity(),
+            FuncV::NativeClosure(_) => (0, None),
+        }
+    }
+
     pub fn push_cell(&self, other: Cell) -> Res<()> {
         let Self(this) = self;
         match this {
@@ -147,7 +162,7 @@ impl PyFunction {
         args: &Bound<'py, PyTuple>,
         kwargs: Option<&Bound<'py, PyDict>>,
     ) -> pyo3::PyResult<Bound<'py, PyAny>> {
-    // ) -> pyo3::PyResult<Py<PyAny>> {
+        // ) -> pyo3::PyResult<Py<PyAny>> {
         let func = Object::new_func(self.0.clone());
 
         let posargs_obj = args.extract::<Object>()?;
@@ -202,6 +217,25 @@ impl<'s> FromPyObject<'s> for Func {
     fn extract_bound(obj: &pyo3::Bound<'s, PyAny>) -> pyo3::PyResult<Self> {
         if let Ok(PyFunction(x)) = obj.extract::<PyFunction>() {
             Ok(x)
+        } else if obj.is_callable() {
+            // Wrap other Python callables in a closure that converts arguments
+            // and return values, and reports Python exceptions as Gold errors
+            let func: Py<PyAny> = obj.to_owned().unbind();
+            let closure: Rc<NativeClosure> = Rc::new(move |args: &List, kwargs: Option<&Map>| {
+                let result = Python::with_gil(|py| {
+                    let a = PyTuple::new(py, args.iter().map(Object::clone))?;
+                    let b = PyDict::new(py);
+                    if let Some(kws) = kwargs {
+                        for (k, v) in kws {
+                            b.set_item(k.as_str(), v.clone())?;
+                        }
+                    }
+                    let result = func.call(py, a, Some(&b))?.extract::<Object>(py)?;
+                    Ok(result)
+                });
+                result.map_err(|e: PyErr| Error::new(Reason::External(format!("{}", e))))
+            });
+            Ok(Func::from(closure))
         } else {
             Err(PyTypeError::new_err(format!(
                 "uncovertible type: {}",
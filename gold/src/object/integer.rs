@@ -2,12 +2,13 @@
 
 use std::cmp::Ordering;
 use std::fmt::Display;
+use std::hash::{Hash, Hasher};
 use std::iter::Step;
 use std::rc::Rc;
 use std::str::FromStr;
 
 use num_bigint::{BigInt, BigUint};
-use num_traits::{checked_pow, ToPrimitive};
+use num_traits::{checked_pow, FromPrimitive, Signed, ToPrimitive, Zero};
 use serde::{Deserialize, Serialize};
 
 use crate::error::{Error, Value};
@@ -17,7 +18,7 @@ use crate::formatting::{
 use crate::types::Res;
 
 #[cfg(feature = "python")]
-use pyo3::{Bound, Python, IntoPyObject, PyErr};
+use pyo3::{Bound, IntoPyObject, PyErr, Python};
 
 #[cfg(feature = "python")]
 use pyo3::types::PyInt;
@@ -51,27 +52,43 @@ impl PartialEq<f64> for Int {
     }
 }
 
+/// Integers compare with floats exactly, without rounding either operand.
 impl PartialOrd<f64> for Int {
     fn partial_cmp(&self, other: &f64) -> Option<Ordering> {
         let Self(this) = self;
-        match this {
-            IntV::Small(x) => (*x as f64).partial_cmp(other),
-            IntV::Big(x) => {
-                // Unfortunately the bigint library doesn't perform comparison with floats.
-                // Compute the floor and ceil of the float in as bignums
-                let (lo, hi) = f64_to_bigs(*other);
-
-                // A bignum is equal to a float if the floor, ceil and bignum
-                // are all equal to each other.
-                if x.as_ref() < &lo || x.as_ref() == &lo && lo != hi {
-                    Some(Ordering::Less)
-                } else if x.as_ref() > &hi || x.as_ref() == &hi && lo != hi {
-                    Some(Ordering::Greater)
-                } else {
-                    Some(Ordering::Equal)
-                }
+
+        // Machine integers up to 2^53 in magnitude are exactly representable
+        if let IntV::Small(x) = this {
+            if x.unsigned_abs() <= 1 << f64::MANTISSA_DIGITS {
+                return (*x as f64).partial_cmp(other);
             }
         }
+
+        if other.is_nan() {
+            return None;
+        } else if *other == f64::INFINITY {
+            return Some(Ordering::Less);
+        } else if *other == f64::NEG_INFINITY {
+            return Some(Ordering::Greater);
+        }
+
+        // Unfortunately the bigint library doesn't perform comparison with floats.
+        // Compute the floor and ceil of the float in as bignums
+        let (lo, hi) = f64_to_bigs(*other);
+        let x = match this {
+            IntV::Small(x) => &BigInt::from(*x),
+            IntV::Big(x) => x.as_ref(),
+        };
+
+        // An integer is equal to a float if the floor, ceil and integer are
+        // all equal to each other.
+        if x < &lo || x == &lo && lo != hi {
+            Some(Ordering::Less)
+        } else if x > &hi || x == &hi && lo != hi {
+            Some(Ordering::Greater)
+        } else {
+            Some(Ordering::Equal)
+        }
     }
 }
 
@@ -140,9 +157,28 @@ impl Int {
         )
     }
 
-    /// Integer division.
+    /// Integer division, rounding towards negative infinity. The divisor must
+    /// be nonzero.
     pub fn idiv(&self, other: &Self) -> Self {
-        self.operate(other, i64::checked_div, |x, y| x / y)
+        self.operate(
+            other,
+            |x, y| {
+                let q = x.checked_div(y)?;
+                Some(if x % y != 0 && (x < 0) != (y < 0) {
+                    q - 1
+                } else {
+                    q
+                })
+            },
+            |x, y| {
+                let q = x / y;
+                if !(x % y).is_zero() && x.is_negative() != y.is_negative() {
+                    q - 1
+                } else {
+                    q
+                }
+            },
+        )
     }
 
     /// Universal utility method for implementing operators.
@@ -152,8 +188,8 @@ impl Int {
     /// operand is not an integer, both operands are converted to bignums and
     /// the `bxb` function is applied. This one may not fail.
     ///
-    /// This method does not apply normalization to the result. That is the
-    /// responsibility of the caller.
+    /// Bignum results converted to [`Int`] are normalized by the conversion,
+    /// so that results in the machine integer range are always demoted.
     fn operate<S, T, U>(
         &self,
         other: &Int,
@@ -271,6 +307,19 @@ impl Int {
         Some(r)
     }
 
+    /// Reduce to the signed 64-bit range with two's complement wrapping, as if
+    /// the exact value were truncated to its lowest 64 bits.
+    pub fn wrap(&self) -> Self {
+        let Self(this) = self;
+        match this {
+            IntV::Small(_) => self.clone(),
+            IntV::Big(x) => {
+                let low = (x.as_ref() & BigInt::from(u64::MAX)).to_u64().unwrap();
+                Self::small(low as i64)
+            }
+        }
+    }
+
     /// Normalize self by converting bignums to machine integers when possible.
     /// Used as a postprocesssing step for most arithmetic operations.
     fn normalize(&mut self) {
@@ -291,6 +340,30 @@ impl Int {
         }
     }
 
+    /// Convert an integral float to an integer exactly. Returns None if the
+    /// float is not integral, infinite or NaN.
+    pub fn from_integral_f64(value: f64) -> Option<Self> {
+        if !value.is_finite() || value.fract() != 0.0 {
+            return None;
+        }
+        if let Some(x) = value.to_i64() {
+            return Some(Self::small(x));
+        }
+        BigInt::from_f64(value).map(Self::big)
+    }
+
+    /// Feed this number into a hasher, consistently with [`Int::user_eq()`].
+    pub fn user_hash<H: Hasher>(&self, state: &mut H) {
+        let Self(this) = self;
+        match this {
+            IntV::Small(x) => x.hash(state),
+            IntV::Big(x) => match x.to_i64() {
+                Some(y) => y.hash(state),
+                None => x.hash(state),
+            },
+        }
+    }
+
     /// Return true if this number is nonzero.
     pub fn nonzero(&self) -> bool {
         let Self(this) = self;
@@ -300,6 +373,16 @@ impl Int {
         }
     }
 
+    /// Interpret this number as a repetition count, where negative numbers
+    /// count as zero. Fails if the number is too large.
+    pub fn repeat_count(&self) -> Res<usize> {
+        if self < &Int::from(0) {
+            Ok(0)
+        } else {
+            usize::try_from(self).map_err(|_| Error::new(Value::TooLarge))
+        }
+    }
+
     /// User (not structural) equality does not differentiatie between bignums
     /// and machine integers, even though it should be impossible to create two
     /// distinct representations of the same number, as all arithmetic uses
@@ -479,6 +562,18 @@ impl TryFrom<&Int> for i64 {
     }
 }
 
+impl TryFrom<&Int> for u64 {
+    type Error = ();
+
+    fn try_from(value: &Int) -> Result<Self, Self::Error> {
+        let Int(this) = value;
+        match this {
+            IntV::Small(x) => Self::try_from(*x).map_err(|_| ()),
+            IntV::Big(x) => Self::try_from(x.as_ref()).map_err(|_| ()),
+        }
+    }
+}
+
 impl TryFrom<&Int> for usize {
     type Error = ();
 
@@ -495,22 +590,12 @@ fn big_to_f64(x: &BigInt) -> f64 {
     f64::from_str(x.to_string().as_str()).unwrap()
 }
 
+/// Compute the floor and ceiling of a finite float exactly, as bignums.
 fn f64_to_bigs(x: f64) -> (BigInt, BigInt) {
-    let s = x.to_string();
-    if let Some(i) = s.find('.') {
-        let b = BigInt::from_str(&s[0..i]).unwrap();
-        if x < 0.0 {
-            let c = b.clone() - 1;
-            (c, b)
-        } else {
-            let c = b.clone() + 1;
-            (b, c)
-        }
-    } else {
-        let b = BigInt::from_str(&s).unwrap();
-        let c = b.clone();
-        (b, c)
-    }
+    (
+        BigInt::from_f64(x.floor()).unwrap(),
+        BigInt::from_f64(x.ceil()).unwrap(),
+    )
 }
 
 #[cfg(feature = "python")]
@@ -545,7 +630,11 @@ impl<'a, 'py> IntoPyObject<'py> for &'a Int {
 mod tests {
     use num_bigint::BigInt;
 
-    use super::f64_to_bigs;
+    use super::{f64_to_bigs, Int, IntV};
+
+    fn is_small(x: &Int) -> bool {
+        matches!(x, Int(IntV::Small(_)))
+    }
 
     #[test]
     fn to_bigs() {
@@ -569,4 +658,28 @@ mod tests {
         assert_eq!(lo, BigInt::from(-1));
         assert_eq!(hi, BigInt::from(-1));
     }
+
+    #[test]
+    fn normalization() {
+        let one = Int::from(1);
+        let max = Int::from(i64::MAX);
+        let min = Int::from(i64::MIN);
+        let big = Int::from(2).pow(&Int::from(64)).unwrap();
+        assert!(!is_small(&big));
+
+        // Results that return to the machine integer range are demoted
+        assert!(is_small(&big.sub(&big)));
+        assert!(is_small(&big.sub(&big).add(&one)));
+        assert!(is_small(&max.add(&one).sub(&one)));
+        assert!(is_small(&min.sub(&one).add(&one)));
+        assert!(is_small(&big.mul(&Int::from(0))));
+        assert!(is_small(&big.idiv(&Int::from(1i64 << 60))));
+        assert!(is_small(&max.add(&one).neg()));
+        assert!(is_small(&Int::from(BigInt::from(5))));
+
+        // Results that don't are not
+        assert!(!is_small(&max.add(&one)));
+        assert!(!is_small(&min.sub(&one)));
+        assert!(!is_small(&min.neg()));
+    }
 }
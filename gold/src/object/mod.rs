@@ -1,18 +1,19 @@
 //! A Gold object is represented by the [`Object`] type.
 
+mod builder;
 mod function;
 mod integer;
+mod range;
 mod string;
+mod view;
 
 use std::cmp::Ordering;
 use std::fmt::{Debug, Display};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io::Write;
 use std::str::FromStr;
 
-#[cfg(feature = "python")]
-use std::collections::HashMap;
-
-#[cfg(feature = "python")]
-use std::rc::Rc;
+use std::collections::{HashMap, HashSet};
 
 use gc::{Finalize, GcCellRef, GcCellRefMut, Trace};
 use json::JsonValue;
@@ -21,26 +22,30 @@ use rmp_serde::{decode, encode};
 use serde::{Deserialize, Serialize};
 use symbol_table::GlobalSymbol;
 
+use crate::builtins;
 use crate::compile::CompiledFunction;
-use crate::error::{Error, Internal, Reason, TypeMismatch, Value};
+use crate::error::{Error, FileSystem, Internal, Reason, TypeMismatch, Value};
 use crate::formatting::FormatSpec;
+use crate::parsing::is_map_key;
 use crate::types::{BinOp, Cell, EagerOp, Gc, GcCell, Key, List, Map, Res, Type, UnOp};
 
-#[cfg(feature = "python")]
-use crate::types::NativeClosure;
-
+pub use builder::{ListBuilder, MapBuilder};
 pub use function::Func;
 pub use integer::Int;
+pub use range::Range;
 pub use string::Str;
 
+pub(crate) use string::escape;
+pub(crate) use view::{MapPart, MapView};
+
 #[cfg(feature = "python")]
-use pyo3::{FromPyObject, Bound, Borrowed, Py, PyAny, PyErr, PyResult, Python};
+use pyo3::{Borrowed, Bound, FromPyObject, PyAny, PyErr, PyResult, Python};
 
 #[cfg(feature = "python")]
 use pyo3::prelude::PyAnyMethods;
 
 #[cfg(feature = "python")]
-use pyo3::types::{PyDict, PyDictMethods, PyList, PyTuple};
+use pyo3::types::{PyDict, PyDictMethods, PyList};
 
 #[cfg(feature = "python")]
 use pyo3::exceptions::PyTypeError;
@@ -71,9 +76,23 @@ enum ObjV {
     /// Functions
     Func(Func),
 
+    /// Lazy integer ranges, which behave like lists
+    Range(Range),
+
+    /// Lazy views of the keys, values or items of a map, which behave like
+    /// lists
+    MapView(MapView),
+
     /// Iterator
     ListIter(GcCell<usize>, GcCell<List>),
 
+    /// Iterator over a lazy range
+    RangeIter(GcCell<usize>, Range),
+
+    /// Iterator over the keys, values or items of a map, which produces each
+    /// element only as it's consumed
+    MapIter(GcCell<usize>, GcCell<Map>, #[unsafe_ignore_trace] MapPart),
+
     /// Null
     Null,
 }
@@ -88,10 +107,14 @@ impl Clone for ObjV {
             Self::List(x) => Self::List(GcCell::new(x.borrow().clone())),
             Self::Map(x) => Self::Map(GcCell::new(x.borrow().clone())),
             Self::Func(x) => Self::Func(x.clone()),
+            Self::Range(x) => Self::Range(x.clone()),
+            Self::MapView(x) => Self::MapView(x.clone()),
             Self::ListIter(x, y) => Self::ListIter(
                 GcCell::new(x.borrow().clone()),
                 GcCell::new(y.borrow().clone()),
             ),
+            Self::RangeIter(x, y) => Self::RangeIter(GcCell::new(*x.borrow()), y.clone()),
+            Self::MapIter(x, y, z) => Self::MapIter(GcCell::new(*x.borrow()), y.clone(), *z),
             Self::Null => Self::Null,
         }
     }
@@ -115,6 +138,9 @@ macro_rules! extract {
     ($index:expr , $args:ident , list) => {
         $args.get($index).and_then(|x| x.get_list())
     };
+    ($index:expr , $args:ident , range) => {
+        $args.get($index).and_then(|x| x.get_range())
+    };
     ($index:expr , $args:ident , map) => {
         $args.get($index).and_then(|x| x.get_map())
     };
@@ -143,6 +169,13 @@ macro_rules! extractkw {
         $kwargs.and_then(|kws| kws.get(&$crate::types::Key::from(stringify!($key))))
     };
 
+    ($kwargs:ident , $key:ident , bool) => {
+        $kwargs.and_then(|kws| {
+            kws.get(&$crate::types::Key::from(stringify!($key)))
+                .and_then(|x| x.get_bool())
+        })
+    };
+
     ($kwargs:ident , $key:ident , tofloat) => {{
         let key = $crate::types::Key::from(stringify!($key));
         $kwargs.and_then(|kws| {
@@ -222,14 +255,20 @@ impl PartialEq<Object> for Object {
             (ObjV::Float(x), ObjV::Float(y)) => x.eq(y),
             (ObjV::Str(x), ObjV::Str(y)) => x.eq(y),
             (ObjV::Boolean(x), ObjV::Boolean(y)) => x.eq(y),
-            (ObjV::List(x), ObjV::List(y)) => x.eq(y),
             (ObjV::Map(x), ObjV::Map(y)) => x.eq(y),
             (ObjV::Null, ObjV::Null) => true,
-            _ => false,
+            _ => match (self.list_cell(), other.list_cell()) {
+                (Some(x), Some(y)) => x.eq(y),
+                _ => false,
+            },
         }
     }
 }
 
+/// Numbers compare by value, also between integers and floats. Strings compare
+/// lexicographically by Unicode scalar value (the same as [`str`] in Rust),
+/// so e.g. `"Z" < "a"`. Lists compare lexicographically by their elements.
+/// Other types, or mixtures of types, are not comparable.
 impl PartialOrd<Object> for Object {
     fn partial_cmp(&self, other: &Object) -> Option<Ordering> {
         let Self(this) = self;
@@ -240,7 +279,19 @@ impl PartialOrd<Object> for Object {
             (ObjV::Float(x), ObjV::Int(y)) => y.partial_cmp(x).map(Ordering::reverse),
             (ObjV::Float(x), ObjV::Float(y)) => x.partial_cmp(y),
             (ObjV::Str(x), ObjV::Str(y)) => x.partial_cmp(y),
-            _ => None,
+
+            // Lists compare lexicographically: the first pair of unequal
+            // elements decides, and if there is none, the shorter list is
+            // smaller. If that pair is not comparable, neither are the lists.
+            _ => {
+                let (x, y) = (self.list_cell()?, other.list_cell()?);
+                let xx = x.borrow();
+                let yy = y.borrow();
+                match xx.iter().zip(yy.iter()).find(|(a, b)| !a.user_eq(b)) {
+                    Some((a, b)) => a.partial_cmp(b),
+                    None => Some(xx.len().cmp(&yy.len())),
+                }
+            }
         }
     }
 }
@@ -250,15 +301,22 @@ impl Object {
     // ------------------------------------------------------------------------------------------------
 
     /// Construct an interned string.
-    pub(crate) fn new_str_interned<T>(val: T) -> Self
+    ///
+    /// All interned strings with the same contents share storage, which saves
+    /// memory for values that are repeated many times, such as enum-like tags.
+    /// Interned strings are never freed, so this is unsuitable for large or
+    /// unique strings.
+    pub fn new_str_interned<T>(val: T) -> Self
     where
         Key: From<T>,
     {
         Self(ObjV::Str(Str::interned(val)))
     }
 
-    /// Construct a non-interned string.
-    pub(crate) fn new_str_natural(val: impl AsRef<str>) -> Self {
+    /// Construct a non-interned string with its own storage.
+    ///
+    /// Such strings compare equal to interned strings with the same contents.
+    pub fn new_str_natural(val: impl AsRef<str>) -> Self {
         Self(ObjV::Str(Str::natural(val)))
     }
 
@@ -288,11 +346,26 @@ impl Object {
         Self(ObjV::List(GcCell::new(vec![])))
     }
 
+    /// Construct a lazy range of integers.
+    pub fn new_range(val: Range) -> Self {
+        Self(ObjV::Range(val))
+    }
+
     /// Construct an empty map.
     pub fn new_map() -> Self {
         Self(ObjV::Map(GcCell::new(Map::new())))
     }
 
+    /// Start building a list with a [`ListBuilder`].
+    pub fn list() -> ListBuilder {
+        ListBuilder::default()
+    }
+
+    /// Start building a map with a [`MapBuilder`].
+    pub fn map() -> MapBuilder {
+        MapBuilder::default()
+    }
+
     /// Return the null object.
     pub fn null() -> Self {
         Self(ObjV::Null)
@@ -307,14 +380,81 @@ impl Object {
     }
 
     /// Construct an iterator
+    ///
+    /// Lists iterate over their elements, strings over their characters (as
+    /// one-character strings) and maps over their key-value pairs (as
+    /// two-element lists).
     pub fn new_iterator(obj: &Object) -> Res<Self> {
-        if let Object(ObjV::List(l)) = obj {
-            Ok(Object(ObjV::ListIter(GcCell::new(0), l.clone())))
-        } else {
-            Err(Error::new(TypeMismatch::Iterate(obj.type_of())))
+        let Object(this) = obj;
+        let list = match this {
+            ObjV::List(l) => l.clone(),
+            ObjV::Range(r) => return Ok(Object(ObjV::RangeIter(GcCell::new(0), r.clone()))),
+            ObjV::Str(s) => GcCell::new(
+                s.as_str()
+                    .chars()
+                    .map(|c| Object::new_str(c.to_string()))
+                    .collect(),
+            ),
+            ObjV::Map(m) => {
+                return Ok(Object(ObjV::MapIter(
+                    GcCell::new(0),
+                    m.clone(),
+                    MapPart::Items,
+                )))
+            }
+            ObjV::MapView(v) => {
+                return Ok(Object(ObjV::MapIter(
+                    GcCell::new(0),
+                    v.map().clone(),
+                    v.part(),
+                )))
+            }
+            _ => return Err(Error::new(TypeMismatch::Iterate(obj.type_of()))),
+        };
+        Ok(Object(ObjV::ListIter(GcCell::new(0), list)))
+    }
+
+    /// Construct a lazy view of the keys, values or items (key-value pairs)
+    /// of a map. The view behaves like a list, but iterating over it produces
+    /// one element at a time without building the list. Returns `None` if
+    /// `obj` is not a map.
+    pub(crate) fn new_map_view(obj: &Object, part: MapPart) -> Option<Self> {
+        match &obj.0 {
+            ObjV::Map(m) => Some(Object(ObjV::MapView(MapView::new(m.clone(), part)))),
+            _ => None,
         }
     }
 
+    // Functional updates
+    // ------------------------------------------------------------------------------------------------
+
+    /// Return a new list with `value` appended, leaving this one unchanged.
+    /// Equivalent to `[...self, value]` in Gold.
+    pub fn pushed(&self, value: impl Into<Object>) -> Res<Object> {
+        let result = Self::new_list();
+        result.splat_into(self.clone())?;
+        result.push(value.into())?;
+        Ok(result)
+    }
+
+    /// Return a new list with the elements of this list followed by those of
+    /// `other`. Equivalent to `[...self, ...other]` in Gold.
+    pub fn concat(&self, other: &Object) -> Res<Object> {
+        let result = Self::new_list();
+        result.splat_into(self.clone())?;
+        result.splat_into(other.clone())?;
+        Ok(result)
+    }
+
+    /// Return a new map with `key` assigned to `value`, leaving this one
+    /// unchanged. Equivalent to `{...self, key: value}` in Gold.
+    pub fn set(&self, key: impl AsRef<str>, value: impl Into<Object>) -> Res<Object> {
+        let result = Self::new_map();
+        result.splat_into(self.clone())?;
+        result.insert_key(Key::new(key), value.into())?;
+        Ok(result)
+    }
+
     // Mutation
     // ------------------------------------------------------------------------------------------------
 
@@ -345,13 +485,10 @@ impl Object {
         }
     }
 
-    /// Assign a new key-value pair to a map.
+    /// Assign a new key-value pair to a map. The key is converted with
+    /// [`Object::to_key`].
     pub fn insert(&self, key: Self, value: Self) -> Res<()> {
-        self.insert_key(
-            key.get_key()
-                .ok_or_else(|| Error::new(TypeMismatch::MapKey(key.type_of())))?,
-            value,
-        )
+        self.insert_key(key.to_key()?, value)
     }
 
     /// Assign a new key-value pair to a map.
@@ -369,18 +506,41 @@ impl Object {
 
     /// Get next value from an iterator
     pub fn next(&self) -> Res<Option<Self>> {
-        if let Object(ObjV::ListIter(index_cell, list)) = self {
-            let mut index_cell_ref = index_cell.borrow_mut();
-            let l = list.borrow();
-            if *index_cell_ref < l.len() {
-                let obj = l[*index_cell_ref].clone();
-                *index_cell_ref += 1;
-                Ok(Some(obj))
-            } else {
-                Ok(None)
+        Ok(self.next_indexed()?.map(|(_, obj)| obj))
+    }
+
+    /// Get next value from an iterator, together with its index
+    pub fn next_indexed(&self) -> Res<Option<(usize, Self)>> {
+        match &self.0 {
+            ObjV::ListIter(index_cell, list) => {
+                let mut index_cell_ref = index_cell.borrow_mut();
+                let l = list.borrow();
+                if *index_cell_ref < l.len() {
+                    let index = *index_cell_ref;
+                    let obj = l[index].clone();
+                    *index_cell_ref += 1;
+                    Ok(Some((index, obj)))
+                } else {
+                    Ok(None)
+                }
             }
-        } else {
-            Err(Internal::NextNotIterator.err())
+            ObjV::RangeIter(index_cell, range) => {
+                let mut index_cell_ref = index_cell.borrow_mut();
+                let index = *index_cell_ref;
+                Ok(range.get(index).map(|value| {
+                    *index_cell_ref += 1;
+                    (index, Object::from(value))
+                }))
+            }
+            ObjV::MapIter(index_cell, map, part) => {
+                let mut index_cell_ref = index_cell.borrow_mut();
+                let index = *index_cell_ref;
+                Ok(map.borrow().get_index(index).map(|(key, value)| {
+                    *index_cell_ref += 1;
+                    (index, part.element(key, value))
+                }))
+            }
+            _ => Err(Internal::NextNotIterator.err()),
         }
     }
 
@@ -390,15 +550,15 @@ impl Object {
         let Self(this) = self;
         let Self(that) = &other;
         match (this, that) {
-            (ObjV::List(x), ObjV::List(y)) => {
+            (ObjV::List(x), _) => {
+                let yy = other
+                    .get_list()
+                    .ok_or_else(|| Error::new(TypeMismatch::SplatList(other.type_of())))?;
                 let mut xx = x.borrow_mut();
-                let yy = y.borrow();
-                xx.extend_from_slice(&*yy);
+                xx.extend_from_slice(&yy);
                 Ok(())
             }
 
-            (ObjV::List(_), _) => Err(Error::new(TypeMismatch::SplatList(other.type_of()))),
-
             (ObjV::Map(x), ObjV::Map(y)) => {
                 let mut xx = x.borrow_mut();
                 let yy = y.borrow();
@@ -431,19 +591,29 @@ impl Object {
     // Serialization
     // ------------------------------------------------------------------------------------------------
 
-    #[allow(dead_code)]
-    fn serialize(&self) -> Option<Vec<u8>> {
-        encode::to_vec(self).ok()
+    /// Serialize to MessagePack. This fails if the object contains native
+    /// functions anywhere, since those can't be represented.
+    pub fn serialize(&self) -> Res<Vec<u8>> {
+        encode::to_vec(self).map_err(|_| Error::new(Value::Serialize))
     }
 
-    #[allow(dead_code)]
-    fn deserialize(data: &Vec<u8>) -> Option<Self> {
-        decode::from_slice::<Self>(data.as_slice()).ok()
+    /// Deserialize from MessagePack, as produced by [`Object::serialize`].
+    pub fn deserialize(data: &[u8]) -> Option<Self> {
+        decode::from_slice::<Self>(data).ok()
     }
 
     // Mathematical operators
     // ------------------------------------------------------------------------------------------------
 
+    /// Reduce integers to the signed 64-bit range with two's complement
+    /// wrapping (see [`Int::wrap`]). Other objects are returned unchanged.
+    pub(crate) fn wrap(self) -> Self {
+        match &self.0 {
+            ObjV::Int(x) => Self(ObjV::Int(x.wrap())),
+            _ => self,
+        }
+    }
+
     /// Mathematical negation.
     pub fn neg(&self) -> Res<Self> {
         let Self(this) = self;
@@ -494,12 +664,12 @@ impl Object {
     pub fn add(&self, other: &Self) -> Res<Self> {
         let Self(this) = self;
         let Self(that) = other;
+        if let (Some(x), Some(y)) = (self.get_list(), other.get_list()) {
+            let result = Self::new_list();
+            result.append(x.iter().chain(y.iter()).cloned())?;
+            return Ok(result);
+        }
         match (this, that) {
-            (ObjV::List(x), ObjV::List(y)) => {
-                let result = Self::new_list();
-                result.append(x.borrow().iter().chain(y.borrow().iter()).cloned())?;
-                Ok(result)
-            }
             (ObjV::Str(x), ObjV::Str(y)) => Ok(Self(ObjV::Str(x.add(y)))),
             _ => self.operate(other, Int::add, |x, y| x + y, BinOp::Eager(EagerOp::Add)),
         }
@@ -515,23 +685,114 @@ impl Object {
         )
     }
 
-    /// The asterisk operator: mathematical multiplication.
+    /// The asterisk operator: repeat strings and lists, or delegate to
+    /// mathematical multiplication.
+    ///
+    /// Repeating a string or list a negative number of times produces an empty
+    /// result, like in Python.
     pub fn mul(&self, other: &Self) -> Res<Self> {
-        self.operate(
-            other,
-            Int::mul,
-            |x, y| x * y,
-            BinOp::Eager(EagerOp::Multiply),
-        )
+        let Self(this) = self;
+        let Self(that) = other;
+        let list = match (this, that) {
+            (_, ObjV::Int(n)) => self.list_cell().map(|x| (x, n)),
+            (ObjV::Int(n), _) => other.list_cell().map(|x| (x, n)),
+            _ => None,
+        };
+        if let Some((x, n)) = list {
+            let count = n.repeat_count()?;
+            let x = x.borrow();
+            let len = repeated_len(x.len(), count)?;
+            let mut result = List::new();
+            result
+                .try_reserve_exact(len)
+                .map_err(|_| Error::new(Value::TooLarge))?;
+            if len > 0 {
+                for _ in 0..count {
+                    result.extend(x.iter().cloned());
+                }
+            }
+            return Ok(Self::from(result));
+        }
+        match (this, that) {
+            (ObjV::Str(x), ObjV::Int(n)) | (ObjV::Int(n), ObjV::Str(x)) => {
+                let count = n.repeat_count()?;
+                let x = x.as_str();
+                let len = repeated_len(x.len(), count)?;
+                let mut result = String::new();
+                result
+                    .try_reserve_exact(len)
+                    .map_err(|_| Error::new(Value::TooLarge))?;
+                if len > 0 {
+                    for _ in 0..count {
+                        result.push_str(x);
+                    }
+                }
+                Ok(Self::from(result))
+            }
+            _ => self.operate(
+                other,
+                Int::mul,
+                |x, y| x * y,
+                BinOp::Eager(EagerOp::Multiply),
+            ),
+        }
+    }
+
+    /// The length in bytes of this object if it's a string, or of the longest
+    /// string directly contained in it if it's a list.
+    pub(crate) fn max_str_len(&self) -> usize {
+        match &self.0 {
+            ObjV::Str(x) => x.as_str().len(),
+            ObjV::List(x) => x
+                .borrow()
+                .iter()
+                .filter_map(Object::get_str)
+                .map(str::len)
+                .max()
+                .unwrap_or(0),
+            _ => 0,
+        }
+    }
+
+    /// The length in bytes of the string that multiplying this object by the
+    /// other would produce, if that would be a string. This allows checking
+    /// the length before building the string.
+    pub(crate) fn repeated_str_len(&self, other: &Self) -> Option<Res<usize>> {
+        match (&self.0, &other.0) {
+            (ObjV::Str(x), ObjV::Int(n)) | (ObjV::Int(n), ObjV::Str(x)) => Some(
+                n.repeat_count()
+                    .and_then(|count| repeated_len(x.as_str().len(), count)),
+            ),
+            _ => None,
+        }
+    }
+
+    /// Return an error if dividing this object by the other would be a
+    /// division by zero.
+    fn check_divisor(&self, other: &Self) -> Res<()> {
+        match (&self.0, &other.0) {
+            (ObjV::Int(_) | ObjV::Float(_), ObjV::Int(y)) if !y.nonzero() => {
+                Err(Error::new(Value::DivisionByZero))
+            }
+            (ObjV::Int(_) | ObjV::Float(_), ObjV::Float(y)) if *y == 0.0 => {
+                Err(Error::new(Value::DivisionByZero))
+            }
+            _ => Ok(()),
+        }
     }
 
-    /// The slash operator: mathematical division.
+    /// The slash operator: mathematical division. The result is always a
+    /// float, even if both operands are integers.
     pub fn div(&self, other: &Self) -> Res<Self> {
+        self.check_divisor(other)?;
         self.operate(other, Int::div, |x, y| x / y, BinOp::Eager(EagerOp::Divide))
     }
 
-    /// The double slash operator: integer division.
+    /// The double slash operator: integer division, rounding towards negative
+    /// infinity. The result is an integer if both operands are integers, and
+    /// otherwise an integral float.
     pub fn idiv(&self, other: &Self) -> Res<Self> {
+        self.check_divisor(other)?;
         self.operate(
             other,
             Int::idiv,
@@ -578,9 +839,13 @@ impl Object {
             ObjV::Str(_) => Type::String,
             ObjV::Boolean(_) => Type::Boolean,
             ObjV::List(_) => Type::List,
+            ObjV::Range(_) => Type::List,
+            ObjV::MapView(_) => Type::List,
             ObjV::Map(_) => Type::Map,
             ObjV::Func(_) => Type::Function,
             ObjV::ListIter(_, _) => Type::Iterator,
+            ObjV::RangeIter(_, _) => Type::Iterator,
+            ObjV::MapIter(_, _, _) => Type::Iterator,
             ObjV::Null => Type::Null,
         }
     }
@@ -593,6 +858,13 @@ impl Object {
         }
     }
 
+    /// The address of the storage backing the string variant, if applicable.
+    /// This is intended for debugging, e.g. to check whether strings are
+    /// shared.
+    pub fn str_ptr(&self) -> Option<*const u8> {
+        self.get_str().map(str::as_ptr)
+    }
+
     /// Extract the integer variant if applicable.
     pub fn get_int(&self) -> Option<&Int> {
         match &self.0 {
@@ -617,10 +889,35 @@ impl Object {
         }
     }
 
-    /// Extract the list variant if applicable.
+    /// Extract the list variant if applicable. This materializes lazy ranges.
     pub fn get_list<'a>(&'a self) -> Option<GcCellRef<'a, List>> {
+        self.list_cell().map(GcCell::borrow)
+    }
+
+    /// Extract the lazy range variant if applicable.
+    pub fn get_range(&self) -> Option<&Range> {
+        match &self.0 {
+            ObjV::Range(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    /// The length of a list, without materializing lazy lists.
+    pub(crate) fn list_len(&self) -> Option<usize> {
         match &self.0 {
-            ObjV::List(x) => Some(x.borrow()),
+            ObjV::List(x) => Some(x.borrow().len()),
+            ObjV::Range(x) => Some(x.len()),
+            ObjV::MapView(x) => Some(x.len()),
+            _ => None,
+        }
+    }
+
+    /// Extract the list variant if applicable, materializing lazy ranges.
+    fn list_cell(&self) -> Option<&GcCell<List>> {
+        match &self.0 {
+            ObjV::List(x) => Some(x),
+            ObjV::Range(x) => Some(x.elements()),
+            ObjV::MapView(x) => Some(x.elements()),
             _ => None,
         }
     }
@@ -641,6 +938,28 @@ impl Object {
         }
     }
 
+    /// Iterate over the elements of a list (or lazy range), if applicable.
+    ///
+    /// The iterator holds the borrow of the underlying list for as long as it
+    /// lives. Elements are yielded as shallow copies, which share their own
+    /// contents with the originals.
+    pub fn iter_list(&self) -> Option<impl Iterator<Item = Object> + '_> {
+        let list = self.get_list()?;
+        Some((0..list.len()).map(move |i| list[i].clone()))
+    }
+
+    /// Iterate over the entries of a map, in order, if applicable.
+    ///
+    /// As with [`Object::iter_list`], the iterator holds the borrow of the
+    /// underlying map and yields shallow copies of the values.
+    pub fn iter_map(&self) -> Option<impl Iterator<Item = (Key, Object)> + '_> {
+        let map = self.get_map()?;
+        Some((0..map.len()).map(move |i| {
+            let (key, value) = map.get_index(i).unwrap();
+            (*key, value.clone())
+        }))
+    }
+
     /// Extract the function variant if applicable.
     #[cfg(feature = "python")]
     pub fn get_func_variant<'a>(&'a self) -> Option<&'a Func> {
@@ -658,6 +977,17 @@ impl Object {
         }
     }
 
+    /// Convert to a map key, as when building maps. Strings are used as is,
+    /// and integers are converted to their decimal representation, so that
+    /// `123` becomes the key `"123"`. All other types are rejected.
+    pub fn to_key(&self) -> Res<Key> {
+        match &self.0 {
+            ObjV::Str(x) => Ok(Key::from(x)),
+            ObjV::Int(x) => Ok(Key::new(x.to_string())),
+            _ => Err(Error::new(TypeMismatch::MapKey(self.type_of()))),
+        }
+    }
+
     /// Extract the function variant if applicable.
     pub fn get_func(&self) -> Option<&Func> {
         match &self.0 {
@@ -721,7 +1051,189 @@ impl Object {
     // Unchecked functions
     // ------------------------------------------------------------------------------------------------
 
+    /// Plain string representation of this object, for top-level output.
+    ///
+    /// This differs from the [`Display`] implementation only for strings,
+    /// which are rendered verbatim rather than quoted and escaped. Strings
+    /// nested inside lists and maps are always quoted, as in [`Display`]. This
+    /// is what the `str` builtin returns. String interpolation without a
+    /// format specification also produces a verbatim string, but it only
+    /// accepts strings, numbers, booleans and null.
+    pub fn render(&self) -> String {
+        self.display_with(&DisplayOptions {
+            quotes: QuoteStyle::Nested,
+            ..Default::default()
+        })
+    }
+
+    /// Render this object as indented, human-readable Gold source.
+    ///
+    /// Non-empty lists and maps are spread across multiple lines with one
+    /// element per line, each nesting level indented by `indent` spaces, and
+    /// with trailing commas. Map keys that are simple identifiers are written
+    /// bare, all others are quoted. Everything else is rendered as with
+    /// [`Display`].
+    pub fn pretty(&self, indent: usize) -> String {
+        self.display_with(&DisplayOptions {
+            indent: Some(indent),
+            ..Default::default()
+        })
+    }
+
+    /// String representation of this object, controlled by `opts`.
+    ///
+    /// With default options this is the same as the [`Display`]
+    /// implementation. [`Object::render`] and [`Object::pretty`] are shortcuts
+    /// for particular options.
+    pub fn display_with(&self, opts: &DisplayOptions) -> String {
+        let mut out = String::new();
+        self.write_with(&mut out, opts, 0);
+        out
+    }
+
+    fn write_with(&self, out: &mut String, opts: &DisplayOptions, level: usize) {
+        let pad = |out: &mut String, level: usize| {
+            if let Some(indent) = opts.indent {
+                out.push('\n');
+                out.extend(std::iter::repeat_n(' ', indent * level));
+            }
+        };
+        let separator = if opts.indent.is_some() { "," } else { ", " };
+
+        match &self.0 {
+            ObjV::Str(r) if level == 0 && opts.quotes == QuoteStyle::Nested => {
+                out.push_str(r.as_str())
+            }
+
+            ObjV::Float(r) if opts.float_point && r.is_finite() => {
+                let text = r.to_string();
+                let bare = !text.contains('.');
+                out.push_str(&text);
+                if bare {
+                    out.push_str(".0");
+                }
+            }
+
+            ObjV::List(_) | ObjV::Range(_) | ObjV::MapView(_) => {
+                let elements = self.get_list().unwrap();
+                out.push('[');
+                for (i, element) in elements.iter().enumerate() {
+                    if i > 0 {
+                        out.push_str(separator);
+                    }
+                    pad(out, level + 1);
+                    element.write_with(out, opts, level + 1);
+                }
+                if !elements.is_empty() {
+                    if opts.indent.is_some() {
+                        out.push(',');
+                    }
+                    pad(out, level);
+                }
+                out.push(']');
+            }
+
+            ObjV::Map(elements) => {
+                let elements = elements.borrow();
+                let mut elements: Vec<_> = elements.iter().collect();
+                if opts.sort_keys {
+                    elements.sort_by(|(a, _), (b, _)| a.as_str().cmp(b.as_str()));
+                }
+                out.push('{');
+                for (i, (key, value)) in elements.iter().enumerate() {
+                    if i > 0 {
+                        out.push_str(separator);
+                    }
+                    pad(out, level + 1);
+                    out.push_str(&map_key(**key));
+                    out.push_str(": ");
+                    value.write_with(out, opts, level + 1);
+                }
+                if !elements.is_empty() {
+                    if opts.indent.is_some() {
+                        out.push(',');
+                    }
+                    pad(out, level);
+                }
+                out.push('}');
+            }
+
+            ObjV::Str(r) => out.push_str(&r.to_string()),
+            ObjV::Int(r) => out.push_str(&r.to_string()),
+            ObjV::Float(r) => out.push_str(&r.to_string()),
+            ObjV::Boolean(true) => out.push_str("true"),
+            ObjV::Boolean(false) => out.push_str("false"),
+            ObjV::Null => out.push_str("null"),
+            ObjV::Func(_) => out.push_str("<function>"),
+
+            _ => out.push('?'),
+        }
+    }
+
+    /// Readable multi-line representation of this object's structure, for
+    /// test failure messages and logging.
+    ///
+    /// Unlike [`Display`], every value is labeled with its type, so that e.g.
+    /// the float `1.0` and the integer `1` can be told apart. Each list
+    /// element and map entry goes on its own line, indented by two spaces per
+    /// level, and ranges are shown as the lists they represent. This format
+    /// is intended to be stable, so it's suitable for snapshot tests.
+    pub fn debug_tree(&self) -> String {
+        let mut out = String::new();
+        self.write_tree(&mut out, 0);
+        out
+    }
+
+    fn write_tree(&self, out: &mut String, level: usize) {
+        let pad = |out: &mut String, level: usize| {
+            out.push('\n');
+            out.extend(std::iter::repeat_n(' ', 2 * level));
+        };
+
+        match &self.0 {
+            ObjV::Int(x) => out.push_str(&format!("int {}", x)),
+            ObjV::Float(x) => out.push_str(&format!("float {:?}", x)),
+            ObjV::Str(x) => out.push_str(&format!("str {:?}", x.as_str())),
+            ObjV::Boolean(x) => out.push_str(&format!("bool {}", x)),
+            ObjV::Null => out.push_str("null"),
+            ObjV::Func(_) => out.push_str("function"),
+
+            ObjV::List(_) | ObjV::Range(_) | ObjV::MapView(_) => {
+                let elements = self.get_list().unwrap();
+                out.push_str("list [");
+                for element in elements.iter() {
+                    pad(out, level + 1);
+                    element.write_tree(out, level + 1);
+                }
+                if !elements.is_empty() {
+                    pad(out, level);
+                }
+                out.push(']');
+            }
+
+            ObjV::Map(elements) => {
+                let elements = elements.borrow();
+                out.push_str("map {");
+                for (key, value) in elements.iter() {
+                    pad(out, level + 1);
+                    out.push_str(&map_key(*key));
+                    out.push_str(": ");
+                    value.write_tree(out, level + 1);
+                }
+                if elements.len() > 0 {
+                    pad(out, level);
+                }
+                out.push('}');
+            }
+
+            _ => out.push_str(&self.type_of().to_string()),
+        }
+    }
+
     /// String representation of this object. Used for string interpolation.
+    ///
+    /// Lists and maps can't be interpolated. Functions are shown as
+    /// `<function>`, like with [`Display`].
     pub fn format(&self, spec: &FormatSpec) -> Res<String> {
         let Self(this) = self;
         match this {
@@ -769,6 +1281,15 @@ impl Object {
                     Err(Error::new(TypeMismatch::InterpolateSpec(self.type_of())))
                 }
             }
+            ObjV::Func(_) => {
+                // Functions have no meaningful string form, but interpolating
+                // them is harmless, so use the same placeholder as Display
+                if let Some(str_spec) = spec.string_spec() {
+                    Ok(str_spec.format("<function>"))
+                } else {
+                    Err(Error::new(TypeMismatch::InterpolateSpec(self.type_of())))
+                }
+            }
             _ => Err(Error::new(TypeMismatch::Interpolate(self.type_of()))),
         }
     }
@@ -777,6 +1298,13 @@ impl Object {
     ///
     /// We use a stricter form of equality checking for testing purposes. This
     /// method implements equality under Gold semantics.
+    ///
+    /// Floats follow IEEE 754: NaN is not equal to anything, including itself,
+    /// and `-0.0` is equal to `0.0`. Integers and floats are equal if they
+    /// represent the same number. Lists and maps are equal if their elements
+    /// are, so e.g. a list containing NaN is not equal to itself. Maps are
+    /// compared by key, regardless of insertion order. This is consistent with
+    /// [`Object::hash()`].
     pub fn user_eq(&self, other: &Self) -> bool {
         match (&self.0, &other.0) {
             // Equality between disparate types
@@ -794,9 +1322,12 @@ impl Object {
             // Composite objects: we must implement equality the hard way, since
             // `eq` would not delegate to checking contained objects using
             // `user_eq`.
-            (ObjV::List(x), ObjV::List(y)) => {
-                let xx = x.borrow();
-                let yy = y.borrow();
+            (
+                ObjV::List(_) | ObjV::Range(_) | ObjV::MapView(_),
+                ObjV::List(_) | ObjV::Range(_) | ObjV::MapView(_),
+            ) => {
+                let xx = self.get_list().unwrap();
+                let yy = other.get_list().unwrap();
                 if xx.len() != yy.len() {
                     return false;
                 }
@@ -830,12 +1361,115 @@ impl Object {
             _ => false,
         }
     }
+
+    /// User-facing hash, consistent with [`Object::user_eq()`]: objects that
+    /// compare equal have equal hashes. In particular, integral floats hash
+    /// like the equivalent integers, and maps hash independently of the order
+    /// of their keys. The hash is stable between runs.
+    ///
+    /// Functions and iterators are not hashable, nor are collections that
+    /// contain them.
+    pub fn hash(&self) -> Res<u64> {
+        let mut state = DefaultHasher::new();
+        self.hash_into(&mut state)?;
+        Ok(state.finish())
+    }
+
+    fn hash_into<H: Hasher>(&self, state: &mut H) -> Res<()> {
+        match &self.0 {
+            ObjV::Null => state.write_u8(0),
+            ObjV::Boolean(x) => {
+                state.write_u8(1);
+                x.hash(state);
+            }
+            ObjV::Int(x) => {
+                state.write_u8(2);
+                x.user_hash(state);
+            }
+            ObjV::Float(x) => match Int::from_integral_f64(*x) {
+                Some(y) => {
+                    state.write_u8(2);
+                    y.user_hash(state);
+                }
+                None => {
+                    state.write_u8(3);
+                    x.to_bits().hash(state);
+                }
+            },
+            ObjV::Str(x) => {
+                state.write_u8(4);
+                x.as_str().hash(state);
+            }
+            ObjV::List(_) | ObjV::Range(_) | ObjV::MapView(_) => {
+                let elements = self.get_list().unwrap();
+                state.write_u8(5);
+                elements.len().hash(state);
+                for element in elements.iter() {
+                    element.hash_into(state)?;
+                }
+            }
+            ObjV::Map(x) => {
+                // Combine the entries with a commutative operation so that
+                // the order doesn't matter
+                let mut sum: u64 = 0;
+                for (key, value) in x.borrow().iter() {
+                    let mut entry = DefaultHasher::new();
+                    key.as_str().hash(&mut entry);
+                    value.hash_into(&mut entry)?;
+                    sum = sum.wrapping_add(entry.finish());
+                }
+                state.write_u8(6);
+                x.borrow().len().hash(state);
+                sum.hash(state);
+            }
+            _ => return Err(Error::new(TypeMismatch::Hash(self.type_of()))),
+        }
+        Ok(())
+    }
+
     /// The function call operator.
-    #[cfg(feature = "python")]
-    fn call(&self, args: &List, kwargs: Option<&Map>) -> Res<Object> {
-        match self.get_func_variant() {
+    pub fn call(&self, args: &List, kwargs: Option<&Map>) -> Res<Object> {
+        match self.get_func() {
             Some(func) => func.call(args, kwargs),
-            None => return Err(Error::new(TypeMismatch::Call(self.type_of()))),
+            None => Err(Error::new(TypeMismatch::Call(self.type_of()))),
+        }
+    }
+
+    /// Call a function with arguments given as Gold objects: `args` must be a
+    /// list and `kwargs` must be a map or null.
+    pub fn call_obj(&self, args: &Object, kwargs: &Object) -> Res<Object> {
+        let args = args
+            .get_list()
+            .ok_or_else(|| Error::new(TypeMismatch::SplatArg(args.type_of())))?;
+        match kwargs {
+            Object(ObjV::Null) => self.call(&args, None),
+            _ => {
+                let kwargs = kwargs
+                    .get_map()
+                    .ok_or_else(|| Error::new(TypeMismatch::SplatArg(kwargs.type_of())))?;
+                self.call(&args, Some(&kwargs))
+            }
+        }
+    }
+
+    /// Call a function with positional and keyword arguments given as slices.
+    ///
+    /// ```
+    /// use gold::{eval_raw, Object};
+    ///
+    /// let f = eval_raw("fn (x; y) x - y").unwrap();
+    /// let result = f.call_with(&[Object::from(3)], &[("y", Object::from(1))]);
+    /// assert_eq!(result, Ok(Object::from(2)));
+    /// ```
+    pub fn call_with(&self, args: &[Object], kwargs: &[(&str, Object)]) -> Res<Object> {
+        if kwargs.is_empty() {
+            self.call(&args.to_vec(), None)
+        } else {
+            let mut map = Map::new();
+            for (k, v) in kwargs {
+                map.insert(Key::new(k), v.clone());
+            }
+            self.call(&args.to_vec(), Some(&map))
         }
     }
 
@@ -858,28 +1492,64 @@ impl Object {
                     Ok(xx[i].clone())
                 }
             }
+            (ObjV::Range(x), ObjV::Int(y)) => {
+                let i: usize = y.try_into().map_err(|_| Error::new(Value::OutOfRange))?;
+                x.get(i)
+                    .map(Object::from)
+                    .ok_or_else(|| Error::new(Value::OutOfRange))
+            }
+            (ObjV::MapView(x), ObjV::Int(y)) => {
+                let i: usize = y.try_into().map_err(|_| Error::new(Value::OutOfRange))?;
+                x.get(i).ok_or_else(|| Error::new(Value::OutOfRange))
+            }
             (ObjV::Map(x), ObjV::Str(y)) => {
                 let xx = x.borrow();
                 let yy = GlobalSymbol::from(y);
                 xx.get(&yy)
-                    .ok_or_else(|| Error::new(Reason::Unassigned(yy)))
-                    .map(Object::clone)
+                    .cloned()
+                    .ok_or_else(|| Error::new(Reason::NoField(yy, Type::Map)))
             }
-            _ => Err(Error::new(TypeMismatch::BinOp(
+            _ => Err(Error::new(TypeMismatch::Index(
                 self.type_of(),
                 other.type_of(),
-                BinOp::Eager(EagerOp::Index),
             ))),
         }
     }
 
+    /// The attribute access operator (dot syntax).
+    ///
+    /// This is like indexing, except that a name that is not a key resolves
+    /// to a method if one by that name exists, so that e.g. `x.len()` is
+    /// equivalent to `len(x)`. Map keys take precedence.
+    pub fn access(&self, other: &Object) -> Res<Object> {
+        self.index(other).or_else(|err| {
+            other
+                .get_str()
+                .and_then(|name| builtins::method(self, name))
+                .ok_or(err)
+        })
+    }
+
     /// The containment operator.
     pub fn contains(&self, other: &Object) -> Res<bool> {
         let Self(this) = self;
         let Self(that) = other;
 
         if let ObjV::List(x) = this {
-            return Ok(x.borrow().contains(other));
+            return Ok(x.borrow().iter().any(|y| y.user_eq(other)));
+        }
+
+        if let ObjV::MapView(x) = this {
+            return Ok((0..x.len()).any(|i| x.get(i).unwrap().user_eq(other)));
+        }
+
+        if let ObjV::Range(x) = this {
+            let value = match that {
+                ObjV::Int(y) => i64::try_from(y).ok(),
+                ObjV::Float(y) => Int::from_integral_f64(*y).and_then(|y| i64::try_from(&y).ok()),
+                _ => None,
+            };
+            return Ok(value.is_some_and(|y| x.contains(y)));
         }
 
         if let (ObjV::Str(haystack), ObjV::Str(needle)) = (this, that) {
@@ -904,45 +1574,56 @@ impl Object {
     }
 }
 
+/// The display form of an object is the same as its Gold source code
+/// representation (strings are quoted and escaped). Functions, which have no
+/// such representation, are shown as `<function>`. Use [`Object::render`] for
+/// a plain top-level representation.
 impl Display for Object {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let Self(this) = self;
-        match this {
-            ObjV::Str(r) => f.write_fmt(format_args!("{}", r)),
-            ObjV::Int(r) => f.write_fmt(format_args!("{}", r)),
-            ObjV::Float(r) => f.write_fmt(format_args!("{}", r)),
-            ObjV::Boolean(true) => f.write_str("true"),
-            ObjV::Boolean(false) => f.write_str("false"),
-            ObjV::Null => f.write_str("null"),
-
-            ObjV::List(elements) => {
-                f.write_str("[")?;
-                let temp = elements.borrow();
-                let mut iter = temp.iter().peekable();
-                while let Some(element) = iter.next() {
-                    f.write_fmt(format_args!("{}", element))?;
-                    if iter.peek().is_some() {
-                        f.write_str(", ")?;
-                    }
-                }
-                f.write_str("]")
-            }
+        f.write_str(&self.display_with(&DisplayOptions::default()))
+    }
+}
 
-            ObjV::Map(elements) => {
-                f.write_str("{")?;
-                let temp = elements.borrow();
-                let mut iter = temp.iter().peekable();
-                while let Some((k, v)) = iter.next() {
-                    f.write_fmt(format_args!("{}: {}", k, v))?;
-                    if iter.peek().is_some() {
-                        f.write_str(", ")?;
-                    }
-                }
-                f.write_str("}")
-            }
+/// Options controlling the string representation produced by
+/// [`Object::display_with`]. The default options produce the same output as
+/// the [`Display`] implementation.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DisplayOptions {
+    /// Number of spaces per nesting level. If set, non-empty lists and maps
+    /// are spread across multiple lines with trailing commas, as with
+    /// [`Object::pretty`]. Otherwise everything is written on one line.
+    pub indent: Option<usize>,
+
+    /// Write map keys in sorted order instead of insertion order.
+    pub sort_keys: bool,
+
+    /// When to quote and escape strings.
+    pub quotes: QuoteStyle,
+
+    /// Always write finite floats with a decimal point, so that they can be
+    /// told apart from integers.
+    pub float_point: bool,
+}
 
-            _ => f.write_str("?"),
-        }
+/// When strings are quoted by [`Object::display_with`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum QuoteStyle {
+    /// All strings are quoted and escaped, as in Gold source.
+    #[default]
+    Always,
+
+    /// A top-level string is written verbatim, as with [`Object::render`].
+    /// Strings nested in lists and maps are still quoted.
+    Nested,
+}
+
+/// Render a map key as it would appear in Gold source: bare if it's a simple
+/// identifier, quoted otherwise.
+fn map_key(key: Key) -> String {
+    if is_map_key(key.as_str()) {
+        key.as_str().to_owned()
+    } else {
+        Str::from(key).to_string()
     }
 }
 
@@ -991,9 +1672,14 @@ impl From<f64> for Object {
     }
 }
 
-impl From<List> for Object {
-    fn from(value: List) -> Self {
-        Self(ObjV::List(GcCell::new(value)))
+impl<T> From<Vec<T>> for Object
+where
+    T: Into<Object>,
+{
+    fn from(value: Vec<T>) -> Self {
+        Self(ObjV::List(GcCell::new(
+            value.into_iter().map(Into::into).collect(),
+        )))
     }
 }
 
@@ -1007,18 +1693,40 @@ impl From<Vec<(&str, Object)>> for Object {
     }
 }
 
+impl<T> From<HashMap<String, T>> for Object
+where
+    T: Into<Object>,
+{
+    fn from(value: HashMap<String, T>) -> Self {
+        let mut map = Map::new();
+        for (k, v) in value {
+            map.insert(Key::new(k), v.into());
+        }
+        Self::from(map)
+    }
+}
+
 impl From<Map> for Object {
     fn from(value: Map) -> Self {
         Self(ObjV::Map(GcCell::new(value)))
     }
 }
 
+/// Collect objects into a list object.
 impl FromIterator<Object> for Object {
     fn from_iter<T: IntoIterator<Item = Object>>(iter: T) -> Self {
         Object(ObjV::List(GcCell::new(iter.into_iter().collect())))
     }
 }
 
+/// Collect key-value pairs into a map object. Later values replace earlier
+/// ones with the same key.
+impl FromIterator<(Key, Object)> for Object {
+    fn from_iter<T: IntoIterator<Item = (Key, Object)>>(iter: T) -> Self {
+        Object(ObjV::Map(GcCell::new(iter.into_iter().collect())))
+    }
+}
+
 impl TryFrom<Object> for JsonValue {
     type Error = Error;
 
@@ -1031,29 +1739,288 @@ impl TryFrom<&Object> for JsonValue {
     type Error = Error;
 
     fn try_from(value: &Object) -> Result<Self, Self::Error> {
-        let Object(this) = value;
+        value.to_json(NonFinite::default())
+    }
+}
+
+/// Policy for converting non-finite floats (NaN and the infinities) to JSON,
+/// which has no representation for them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NonFinite {
+    /// Conversion fails. This is the default, and guarantees spec-compliant
+    /// output.
+    #[default]
+    Error,
+
+    /// Non-finite floats are converted to null.
+    Null,
+
+    /// Non-finite floats are converted to the strings `"NaN"`, `"Infinity"` and
+    /// `"-Infinity"`.
+    String,
+}
+
+impl Object {
+    /// Convert to a [`JsonValue`], treating non-finite floats according to
+    /// `non_finite`. This fails for functions and for integers outside the
+    /// 64-bit range.
+    pub fn to_json(&self, non_finite: NonFinite) -> Res<JsonValue> {
+        let Object(this) = self;
         match this {
             ObjV::Int(x) => i64::try_from(x)
                 .map_err(|_| Error::new(Value::TooLarge))
                 .map(JsonValue::from),
-            ObjV::Float(x) => Ok(JsonValue::from(*x)),
+            ObjV::Float(x) if x.is_finite() => Ok(JsonValue::from(*x)),
+            ObjV::Float(x) => match non_finite {
+                NonFinite::Error => Err(Error::new(Value::OutOfRange)),
+                NonFinite::Null => Ok(JsonValue::Null),
+                NonFinite::String if x.is_nan() => Ok(JsonValue::from("NaN")),
+                NonFinite::String if *x > 0.0 => Ok(JsonValue::from("Infinity")),
+                NonFinite::String => Ok(JsonValue::from("-Infinity")),
+            },
             ObjV::Str(x) => Ok(JsonValue::from(x.as_str())),
             ObjV::Boolean(x) => Ok(JsonValue::from(*x)),
-            ObjV::List(x) => {
+            ObjV::List(_) | ObjV::Range(_) | ObjV::MapView(_) => {
                 let mut val = JsonValue::new_array();
-                for element in x.borrow().iter() {
-                    val.push(JsonValue::try_from(element)?).unwrap();
+                for element in self.get_list().unwrap().iter() {
+                    val.push(element.to_json(non_finite)?).unwrap();
                 }
                 Ok(val)
             }
             ObjV::Map(x) => {
                 let mut val = JsonValue::new_object();
                 for (key, element) in x.borrow().iter() {
-                    val[key.as_str()] = JsonValue::try_from(element)?;
+                    val[key.as_str()] = element.to_json(non_finite)?;
                 }
                 Ok(val)
             }
             ObjV::Null => Ok(JsonValue::Null),
+            _ => Err(Error::new(TypeMismatch::Json(self.type_of()))),
+        }
+    }
+
+    /// Return a copy where the keys of all maps, including nested ones, are
+    /// sorted. Serializing the result produces the same output regardless of
+    /// the order in which the maps were built.
+    pub fn with_sorted_keys(&self) -> Object {
+        let Object(this) = self;
+        match this {
+            ObjV::List(x) => x.borrow().iter().map(Object::with_sorted_keys).collect(),
+            ObjV::MapView(_) => self
+                .get_list()
+                .unwrap()
+                .iter()
+                .map(Object::with_sorted_keys)
+                .collect(),
+            ObjV::Map(x) => {
+                let x = x.borrow();
+                let mut elements: Vec<_> = x.iter().collect();
+                elements.sort_by(|(a, _), (b, _)| a.as_str().cmp(b.as_str()));
+                Object::from(
+                    elements
+                        .into_iter()
+                        .map(|(key, value)| (*key, value.with_sorted_keys()))
+                        .collect::<Map>(),
+                )
+            }
+            _ => self.clone(),
+        }
+    }
+}
+
+impl Object {
+    /// Serialize as compact JSON directly to a writer.
+    ///
+    /// The output is identical to dumping the equivalent [`JsonValue`], but
+    /// no intermediate representation is built. In particular, ranges are
+    /// written without being materialized.
+    ///
+    /// Values are written as they are visited, so if serialization fails
+    /// partway (e.g. on a function), the output written so far is left in
+    /// `out`. Callers that need all-or-nothing output should write to a
+    /// buffer first.
+    pub fn write_json(&self, out: &mut impl Write) -> Res<()> {
+        let Object(this) = self;
+        match this {
+            ObjV::List(x) => {
+                write_bytes(out, b"[")?;
+                for (i, element) in x.borrow().iter().enumerate() {
+                    if i > 0 {
+                        write_bytes(out, b",")?;
+                    }
+                    element.write_json(out)?;
+                }
+                write_bytes(out, b"]")
+            }
+            ObjV::Range(x) => {
+                write_bytes(out, b"[")?;
+                for i in 0..x.len() {
+                    if i > 0 {
+                        write_bytes(out, b",")?;
+                    }
+                    write_bytes(out, x.get(i).unwrap().to_string().as_bytes())?;
+                }
+                write_bytes(out, b"]")
+            }
+            ObjV::MapView(x) => {
+                write_bytes(out, b"[")?;
+                for i in 0..x.len() {
+                    if i > 0 {
+                        write_bytes(out, b",")?;
+                    }
+                    x.get(i).unwrap().write_json(out)?;
+                }
+                write_bytes(out, b"]")
+            }
+            ObjV::Map(x) => {
+                write_bytes(out, b"{")?;
+                for (i, (key, element)) in x.borrow().iter().enumerate() {
+                    if i > 0 {
+                        write_bytes(out, b",")?;
+                    }
+                    write_bytes(out, json::stringify(key.as_str()).as_bytes())?;
+                    write_bytes(out, b":")?;
+                    element.write_json(out)?;
+                }
+                write_bytes(out, b"}")
+            }
+            _ => write_bytes(out, JsonValue::try_from(self)?.dump().as_bytes()),
+        }
+    }
+
+    /// Remove all elements from a list, or all entries from a map, and write
+    /// them to `out` as JSON. Each is preceded by a comma, unless it's the
+    /// first one written (as counted by `written`).
+    ///
+    /// The keys of map entries are recorded in `keys`, and it's an error to
+    /// write a key a second time, since the first entry can't be replaced
+    /// after the fact.
+    pub(crate) fn drain_json(
+        &self,
+        out: &mut impl Write,
+        written: &mut usize,
+        keys: &mut HashSet<Key>,
+    ) -> Res<()> {
+        if let Some(mut map) = self.get_map_mut() {
+            let entries = std::mem::replace(&mut *map, Map::new());
+            drop(map);
+            for (key, element) in entries.iter() {
+                if !keys.insert(*key) {
+                    return Err(Error::new(Value::DuplicateKey(*key)));
+                }
+                if *written > 0 {
+                    write_bytes(out, b",")?;
+                }
+                write_bytes(out, json::stringify(key.as_str()).as_bytes())?;
+                write_bytes(out, b":")?;
+                element.write_json(out)?;
+                *written += 1;
+            }
+        } else if let Some(list) = self.list_cell() {
+            let elements = std::mem::take(&mut *list.borrow_mut());
+            for element in elements.iter() {
+                if *written > 0 {
+                    write_bytes(out, b",")?;
+                }
+                element.write_json(out)?;
+                *written += 1;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The length of a string or list of length `len` repeated `count` times.
+/// Fails if this doesn't fit in memory.
+pub(crate) fn repeated_len(len: usize, count: usize) -> Res<usize> {
+    len.checked_mul(count)
+        .filter(|&total| total <= isize::MAX as usize)
+        .ok_or_else(|| Error::new(Value::TooLarge))
+}
+
+fn write_bytes(out: &mut impl Write, bytes: &[u8]) -> Res<()> {
+    out.write_all(bytes)
+        .map_err(|_| Error::new(FileSystem::Write))
+}
+
+#[cfg(feature = "json")]
+impl From<serde_json::Value> for Object {
+    fn from(value: serde_json::Value) -> Self {
+        Object::from(&value)
+    }
+}
+
+#[cfg(feature = "json")]
+impl From<&serde_json::Value> for Object {
+    fn from(value: &serde_json::Value) -> Self {
+        use serde_json::Value as V;
+        match value {
+            V::Null => Object::null(),
+            V::Bool(x) => Object::from(*x),
+            V::Number(x) => {
+                if let Some(y) = x.as_i64() {
+                    Object::from(y)
+                } else if let Some(y) = x.as_u64() {
+                    Object::from(BigInt::from(y))
+                } else {
+                    // JSON numbers are always finite
+                    x.as_f64().map_or_else(Object::null, Object::from)
+                }
+            }
+            V::String(x) => Object::from(x.as_str()),
+            V::Array(x) => Object::from(x.iter().map(Object::from).collect::<List>()),
+            V::Object(x) => Object::from(
+                x.iter()
+                    .map(|(k, v)| (Key::new(k), Object::from(v)))
+                    .collect::<Map>(),
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "json")]
+impl TryFrom<Object> for serde_json::Value {
+    type Error = Error;
+
+    fn try_from(value: Object) -> Result<Self, Self::Error> {
+        serde_json::Value::try_from(&value)
+    }
+}
+
+/// Convert to a `serde_json` value. This fails for functions, for integers
+/// outside the 64-bit range and for non-finite floats, none of which can be
+/// represented in JSON.
+#[cfg(feature = "json")]
+impl TryFrom<&Object> for serde_json::Value {
+    type Error = Error;
+
+    fn try_from(value: &Object) -> Result<Self, Self::Error> {
+        use serde_json::Value as V;
+        let Object(this) = value;
+        match this {
+            ObjV::Int(x) => i64::try_from(x)
+                .map(V::from)
+                .or_else(|_| u64::try_from(x).map(V::from))
+                .map_err(|_| Error::new(Value::TooLarge)),
+            ObjV::Float(x) => serde_json::Number::from_f64(*x)
+                .map(V::Number)
+                .ok_or_else(|| Error::new(Value::OutOfRange)),
+            ObjV::Str(x) => Ok(V::from(x.as_str())),
+            ObjV::Boolean(x) => Ok(V::from(*x)),
+            ObjV::List(_) | ObjV::Range(_) | ObjV::MapView(_) => value
+                .get_list()
+                .unwrap()
+                .iter()
+                .map(V::try_from)
+                .collect::<Res<Vec<_>>>()
+                .map(V::Array),
+            ObjV::Map(x) => x
+                .borrow()
+                .iter()
+                .map(|(k, v)| Ok((k.as_str().to_owned(), V::try_from(v)?)))
+                .collect::<Res<serde_json::Map<_, _>>>()
+                .map(V::Object),
+            ObjV::Null => Ok(V::Null),
             _ => Err(Error::new(TypeMismatch::Json(value.type_of()))),
         }
     }
@@ -1083,23 +2050,6 @@ impl<'s> FromPyObject<'s> for Object {
             )))
         } else if obj.is_none() {
             Ok(Object::null())
-        } else if obj.is_callable() {
-            let func: Py<PyAny> = obj.to_owned().unbind();
-            let closure: Rc<NativeClosure> = Rc::new(move |args: &List, kwargs: Option<&Map>| {
-                let result = Python::with_gil(|py| {
-                    let a = PyTuple::new(py, args.iter().map(Object::clone))?;
-                    let b = PyDict::new(py);
-                    if let Some(kws) = kwargs {
-                        for (k, v) in kws {
-                            b.set_item(k.as_str(), v.clone())?;
-                        }
-                    }
-                    let result = func.call(py, a, Some(&b))?.extract::<Object>(py)?;
-                    Ok(result)
-                });
-                result.map_err(|e: PyErr| Error::new(Reason::External(format!("{}", e))))
-            });
-            Ok(Object::new_func(closure))
         } else {
             Err(PyTypeError::new_err(format!(
                 "uncovertible type: {}",
@@ -1118,14 +2068,22 @@ impl<'py> pyo3::IntoPyObject<'py> for Object {
     fn into_pyobject(self, py: Python<'py>) -> Result<Self::Output, Self::Error> {
         match &self.0 {
             ObjV::Int(x) => x.into_pyobject(py).map(Bound::into_any),
-            ObjV::Float(x) => x.into_pyobject(py).map(Bound::into_any).map_err(|_| unreachable!()),
-            ObjV::Str(x) => x.into_pyobject(py).map(Bound::into_any).map_err(|_| unreachable!()),
-            ObjV::Boolean(x) => (*x).into_pyobject(py).map(Borrowed::to_owned).map(Bound::into_any).map_err(|_| unreachable!()),
-            ObjV::List(x) => {
-                PyList::new(
-                    py,
-                    x.borrow().iter().map(Object::clone),
-                ).map(Bound::into_any)
+            ObjV::Float(x) => x
+                .into_pyobject(py)
+                .map(Bound::into_any)
+                .map_err(|_| unreachable!()),
+            ObjV::Str(x) => x
+                .into_pyobject(py)
+                .map(Bound::into_any)
+                .map_err(|_| unreachable!()),
+            ObjV::Boolean(x) => (*x)
+                .into_pyobject(py)
+                .map(Borrowed::to_owned)
+                .map(Bound::into_any)
+                .map_err(|_| unreachable!()),
+            ObjV::List(_) | ObjV::Range(_) | ObjV::MapView(_) => {
+                PyList::new(py, self.get_list().unwrap().iter().map(Object::clone))
+                    .map(Bound::into_any)
             }
             ObjV::Map(x) => {
                 let r = PyDict::new(py);
@@ -1136,6 +2094,8 @@ impl<'py> pyo3::IntoPyObject<'py> for Object {
             }
             ObjV::Null => Ok(py.None().into_bound(py)),
             ObjV::ListIter(_, _) => Ok(py.None().into_bound(py)),
+            ObjV::RangeIter(_, _) => Ok(py.None().into_bound(py)),
+            ObjV::MapIter(_, _, _) => Ok(py.None().into_bound(py)),
             ObjV::Func(x) => x.into_pyobject(py).map(Bound::into_any),
         }
     }
@@ -1144,13 +2104,19 @@ impl<'py> pyo3::IntoPyObject<'py> for Object {
 #[cfg(test)]
 mod tests {
     use core::cmp::Ordering;
+    use std::collections::HashMap;
 
     use crate::formatting::{
         AlignSpec, FloatFormatType, FormatSpec, FormatType, GroupingSpec, IntegerFormatType,
         SignSpec, UppercaseSpec,
     };
 
-    use super::Object;
+    use json::JsonValue;
+
+    use super::{DisplayOptions, MapPart, ObjV, Object, QuoteStyle, Range};
+    use crate::error::{Error, TypeMismatch};
+    use crate::types::Key;
+    use crate::{eval_raw, Type};
 
     #[test]
     fn to_string() {
@@ -1189,6 +2155,257 @@ mod tests {
         );
     }
 
+    #[test]
+    fn builders() {
+        let obj = Object::map()
+            .insert("name", "gold")
+            .insert("tags", vec!["a", "b"])
+            .insert(
+                "server",
+                Object::map()
+                    .insert("host", "localhost")
+                    .insert("port", 8080),
+            )
+            .insert("ratio", 0.5)
+            .insert("debug", false)
+            .build();
+
+        assert_eq!(
+            obj,
+            Object::from(vec![
+                ("name", Object::from("gold")),
+                (
+                    "tags",
+                    Object::from(vec![Object::from("a"), Object::from("b")])
+                ),
+                (
+                    "server",
+                    Object::from(vec![
+                        ("host", Object::from("localhost")),
+                        ("port", Object::from(8080)),
+                    ])
+                ),
+                ("ratio", Object::from(0.5)),
+                ("debug", Object::from(false)),
+            ])
+        );
+
+        assert_eq!(
+            Object::list()
+                .push(1)
+                .push("two")
+                .push(Object::null())
+                .build(),
+            Object::from(vec![Object::from(1), Object::from("two"), Object::null()])
+        );
+        assert_eq!(Object::list().build(), Object::new_list());
+        assert_eq!(Object::map().build(), Object::new_map());
+
+        let hashmap: HashMap<String, i64> = [("x".to_string(), 1)].into_iter().collect();
+        assert_eq!(
+            Object::from(hashmap),
+            Object::from(vec![("x", Object::from(1))])
+        );
+
+        let func = eval_raw("fn (cfg) \"${cfg.server.host}:${cfg.server.port + 1}\"").unwrap();
+        assert_eq!(
+            func.get_func()
+                .unwrap()
+                .call(&vec![obj], None)
+                .unwrap()
+                .get_str(),
+            Some("localhost:8081")
+        );
+    }
+
+    #[test]
+    fn call() {
+        let func = eval_raw("fn (x, y = 10; z = 100) x + 2 * y + 3 * z").unwrap();
+
+        assert_eq!(
+            func.call_with(&[Object::from(1), Object::from(2)], &[]),
+            Ok(Object::from(305))
+        );
+        assert_eq!(
+            func.call_with(&[Object::from(1)], &[("z", Object::from(0))]),
+            Ok(Object::from(21))
+        );
+        assert_eq!(
+            func.call_obj(
+                &Object::from(vec![Object::from(1)]),
+                &Object::from(vec![("z", Object::from(1))])
+            ),
+            Ok(Object::from(24))
+        );
+        assert_eq!(
+            func.call_obj(&Object::from(vec![Object::from(1)]), &Object::null()),
+            Ok(Object::from(321))
+        );
+
+        assert_eq!(
+            func.call_obj(&Object::from(1), &Object::null()),
+            Err(Error::new(TypeMismatch::SplatArg(Type::Integer)))
+        );
+        assert_eq!(
+            func.call_obj(&Object::new_list(), &Object::new_list()),
+            Err(Error::new(TypeMismatch::SplatArg(Type::List)))
+        );
+        assert_eq!(
+            Object::from(1).call_with(&[], &[]),
+            Err(Error::new(TypeMismatch::Call(Type::Integer)))
+        );
+    }
+
+    #[test]
+    fn pretty() {
+        assert_eq!(Object::from(1).pretty(4), "1");
+        assert_eq!(Object::from("a\"b").pretty(4), "\"a\\\"b\"");
+        assert_eq!(Object::new_list().pretty(4), "[]");
+        assert_eq!(Object::new_map().pretty(4), "{}");
+
+        let obj = Object::map()
+            .insert("name", "gold")
+            .insert("my-key", vec![1, 2])
+            .insert("with space", Object::new_list())
+            .insert("$x", true)
+            .insert(
+                "nested",
+                Object::map()
+                    .insert("list", vec![Object::from("x"), Object::new_map()])
+                    .insert("null", Object::null()),
+            )
+            .build();
+
+        assert_eq!(
+            obj.pretty(2),
+            concat!(
+                "{\n",
+                "  name: \"gold\",\n",
+                "  my-key: [\n",
+                "    1,\n",
+                "    2,\n",
+                "  ],\n",
+                "  \"with space\": [],\n",
+                "  \"\\$x\": true,\n",
+                "  nested: {\n",
+                "    list: [\n",
+                "      \"x\",\n",
+                "      {},\n",
+                "    ],\n",
+                "    null: null,\n",
+                "  },\n",
+                "}",
+            )
+        );
+        assert_eq!(eval_raw(&obj.pretty(4)), Ok(obj));
+    }
+
+    #[test]
+    fn display_with() {
+        let obj = Object::map()
+            .insert("b", vec![Object::from(1.0), Object::from(2.5)])
+            .insert("a", "x")
+            .insert("c", Object::new_map())
+            .build();
+
+        assert_eq!(
+            obj.display_with(&DisplayOptions::default()),
+            obj.to_string()
+        );
+
+        assert_eq!(
+            obj.display_with(&DisplayOptions {
+                sort_keys: true,
+                float_point: true,
+                ..Default::default()
+            }),
+            "{a: \"x\", b: [1.0, 2.5], c: {}}"
+        );
+
+        assert_eq!(
+            obj.display_with(&DisplayOptions {
+                indent: Some(2),
+                sort_keys: true,
+                ..Default::default()
+            }),
+            concat!(
+                "{\n",
+                "  a: \"x\",\n",
+                "  b: [\n",
+                "    1,\n",
+                "    2.5,\n",
+                "  ],\n",
+                "  c: {},\n",
+                "}",
+            )
+        );
+
+        let opts = DisplayOptions {
+            quotes: QuoteStyle::Nested,
+            float_point: true,
+            ..Default::default()
+        };
+        assert_eq!(Object::from("x").display_with(&opts), "x");
+        assert_eq!(Object::from(3.0).display_with(&opts), "3.0");
+        assert_eq!(Object::from(f64::INFINITY).display_with(&opts), "inf");
+        assert_eq!(obj.display_with(&opts), "{b: [1.0, 2.5], a: \"x\", c: {}}");
+    }
+
+    #[test]
+    fn debug_tree() {
+        assert_eq!(Object::from(1).debug_tree(), "int 1");
+        assert_eq!(Object::from(1.0).debug_tree(), "float 1.0");
+        assert_eq!(Object::from("a\"b").debug_tree(), "str \"a\\\"b\"");
+        assert_eq!(Object::new_list().debug_tree(), "list []");
+
+        let obj = eval_raw(
+            "{name: \"gold\", \"my key\": [1, 2.5, range(2)], nested: {f: len, x: null, y: {}}}",
+        )
+        .unwrap();
+        assert_eq!(
+            obj.debug_tree(),
+            concat!(
+                "map {\n",
+                "  name: str \"gold\"\n",
+                "  \"my key\": list [\n",
+                "    int 1\n",
+                "    float 2.5\n",
+                "    list [\n",
+                "      int 0\n",
+                "      int 1\n",
+                "    ]\n",
+                "  ]\n",
+                "  nested: map {\n",
+                "    f: function\n",
+                "    x: null\n",
+                "    y: map {}\n",
+                "  }\n",
+                "}",
+            )
+        );
+    }
+
+    #[test]
+    fn render() {
+        assert_eq!(Object::from("alpha").render(), "alpha");
+        assert_eq!(Object::from("\"alpha\\").render(), "\"alpha\\");
+        assert_eq!(Object::from(1).render(), "1");
+        assert_eq!(Object::null().render(), "null");
+
+        assert_eq!(
+            Object::from(vec![Object::from(1), Object::from("alpha")]).render(),
+            "[1, \"alpha\"]"
+        );
+        assert_eq!(
+            Object::from(vec![("a", Object::from("alpha"))]).render(),
+            "{a: \"alpha\"}"
+        );
+        assert_eq!(
+            Object::from(vec![("a b", Object::from(1)), ("$c", Object::from(2))]).render(),
+            "{\"a b\": 1, \"\\$c\": 2}"
+        );
+    }
+
     #[test]
     fn format() {
         assert_eq!(
@@ -1954,15 +3171,336 @@ mod tests {
             Some(Ordering::Greater)
         );
     }
+
+    #[test]
+    fn hashing() {
+        let hash = |x: &str| eval_raw(x).unwrap().hash();
+
+        assert_eq!(hash("1"), hash("1.0"));
+        assert_eq!(hash("-0.0"), hash("0"));
+        assert_eq!(hash("9223372036854775808"), hash("9223372036854775808.0"));
+        assert_ne!(hash("1"), hash("1.5"));
+        assert_ne!(hash("1"), hash("\"1\""));
+        assert_ne!(hash("1"), hash("true"));
+        assert_ne!(hash("[1, 2]"), hash("[2, 1]"));
+        assert_eq!(hash("[1, 2]"), hash("range(1, 3)"));
+        assert_eq!(hash("{a: 1, b: 2}"), hash("{b: 2, a: 1}"));
+        assert_ne!(hash("{a: 1, b: 2}"), hash("{a: 2, b: 1}"));
+
+        assert_eq!(
+            hash("fn (x) x"),
+            Err(Error::new(TypeMismatch::Hash(Type::Function)))
+        );
+        assert_eq!(
+            hash("{a: [fn (x) x]}"),
+            Err(Error::new(TypeMismatch::Hash(Type::Function)))
+        );
+    }
+
+    #[test]
+    fn sorted_keys() {
+        let a = eval_raw("{b: 1, a: [{z: 1, y: 2}], c: {e: 1, d: 2}}").unwrap();
+        let b = eval_raw("{c: {d: 2, e: 1}, a: [{y: 2, z: 1}], b: 1}").unwrap();
+        assert_ne!(
+            JsonValue::try_from(&a).unwrap().dump(),
+            JsonValue::try_from(&b).unwrap().dump()
+        );
+
+        let a = JsonValue::try_from(a.with_sorted_keys()).unwrap().dump();
+        let b = JsonValue::try_from(b.with_sorted_keys()).unwrap().dump();
+        assert_eq!(a, b);
+        assert_eq!(
+            a,
+            "{\"a\":[{\"y\":2,\"z\":1}],\"b\":1,\"c\":{\"d\":2,\"e\":1}}"
+        );
+    }
+
+    #[test]
+    fn json_non_finite() {
+        use super::NonFinite;
+        use crate::error::Value;
+
+        let nan = Object::from(f64::NAN);
+        assert_eq!(
+            nan.to_json(NonFinite::Error),
+            Err(Error::new(Value::OutOfRange))
+        );
+        assert_eq!(nan.to_json(NonFinite::Null), Ok(JsonValue::Null));
+        assert_eq!(nan.to_json(NonFinite::String), Ok(JsonValue::from("NaN")));
+        assert_eq!(
+            JsonValue::try_from(&nan),
+            Err(Error::new(Value::OutOfRange))
+        );
+
+        let obj = Object::from(vec![
+            Object::from(f64::INFINITY),
+            Object::from(-f64::INFINITY),
+            Object::from(1.5),
+        ]);
+        assert_eq!(
+            obj.to_json(NonFinite::String).map(|x| x.dump()),
+            Ok("[\"Infinity\",\"-Infinity\",1.5]".to_owned())
+        );
+        assert_eq!(
+            obj.to_json(NonFinite::Null).map(|x| x.dump()),
+            Ok("[null,null,1.5]".to_owned())
+        );
+        assert!(obj.to_json(NonFinite::Error).is_err());
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn serde_json() {
+        use serde_json::{json, Value as V};
+
+        use crate::error::Value;
+        use crate::types::Key;
+
+        let value = json!({
+            "name": "gold",
+            "version": 3,
+            "ratio": 3.0,
+            "big": u64::MAX,
+            "tags": ["a", true, null],
+            "nested": {"z": 1, "a": [1.5]},
+        });
+        let obj = Object::from(value.clone());
+        assert_eq!(
+            obj,
+            eval_raw(concat!(
+                "{name: \"gold\", version: 3, ratio: 3.0, big: 18446744073709551615, ",
+                "tags: [\"a\", true, null], nested: {z: 1, a: [1.5]}}",
+            ))
+            .unwrap()
+        );
+        assert_eq!(
+            obj.get_map().unwrap().get(&Key::new("ratio")),
+            Some(&Object::from(3.0))
+        );
+        assert_eq!(V::try_from(&obj), Ok(value));
+
+        // Integers and floats are kept apart, and order is preserved
+        let value = V::try_from(eval_raw("{b: 1, a: 1.0}").unwrap()).unwrap();
+        assert_eq!(value.to_string(), "{\"b\":1,\"a\":1.0}");
+
+        assert_eq!(
+            V::try_from(eval_raw("[fn () 1]").unwrap()),
+            Err(Error::new(TypeMismatch::Json(Type::Function)))
+        );
+        assert_eq!(
+            V::try_from(eval_raw("2 ^ 64").unwrap()),
+            Err(Error::new(Value::TooLarge))
+        );
+        assert_eq!(
+            V::try_from(Object::from(f64::INFINITY)),
+            Err(Error::new(Value::OutOfRange))
+        );
+    }
+
+    #[test]
+    fn iterators() {
+        let obj = eval_raw("[1, [2, 3], 4, {a: 5}, 6.0]").unwrap();
+        let total: i64 = obj
+            .iter_list()
+            .unwrap()
+            .filter_map(|x| x.get_int().and_then(|x| i64::try_from(x).ok()))
+            .sum();
+        assert_eq!(total, 5);
+        assert_eq!(obj.iter_list().unwrap().count(), 5);
+        assert!(obj.iter_map().is_none());
+
+        let obj = eval_raw("{a: 1, b: {c: 2}, d: 3, e: [4]}").unwrap();
+        let total: i64 = obj
+            .iter_map()
+            .unwrap()
+            .filter_map(|(_, x)| x.get_int().and_then(|x| i64::try_from(x).ok()))
+            .sum();
+        assert_eq!(total, 4);
+        let keys: Vec<_> = obj
+            .iter_map()
+            .unwrap()
+            .map(|(k, _)| k.as_str().to_owned())
+            .collect();
+        assert_eq!(keys, vec!["a", "b", "d", "e"]);
+        assert!(obj.iter_list().is_none());
+
+        let obj = Object::new_range(Range::new(0, 4, 1).unwrap());
+        assert_eq!(obj.iter_list().unwrap().count(), 4);
+    }
+
+    #[test]
+    fn collect() {
+        let objects = vec![Object::from(1), Object::from("a"), Object::null()];
+        let obj: Object = objects.into_iter().collect();
+        assert_eq!(obj, eval_raw("[1, \"a\", null]").unwrap());
+
+        let obj: Object = (1..4).map(Object::from).collect();
+        assert_eq!(obj, eval_raw("[1, 2, 3]").unwrap());
+
+        let obj: Object = Vec::<Object>::new().into_iter().collect();
+        assert_eq!(obj, Object::new_list());
+
+        let pairs = vec![
+            (Key::new("a"), Object::from(1)),
+            (Key::new("b"), Object::from(2)),
+            (Key::new("a"), Object::from(3)),
+        ];
+        let obj: Object = pairs.into_iter().collect();
+        assert_eq!(obj, eval_raw("{a: 3, b: 2}").unwrap());
+        assert_eq!(
+            obj.iter_map()
+                .unwrap()
+                .map(|(k, _)| k.as_str().to_owned())
+                .collect::<Vec<_>>(),
+            vec!["a", "b"]
+        );
+    }
+
+    #[test]
+    fn pushed() {
+        let x = Object::from(vec![1, 2]);
+        assert_eq!(x.pushed(3), Ok(Object::from(vec![1, 2, 3])));
+        assert_eq!(x, Object::from(vec![1, 2]));
+        assert_eq!(
+            Object::new_list().pushed("a"),
+            Ok(Object::from(vec![Object::from("a")]))
+        );
+        assert_eq!(
+            Object::from(1).pushed(2),
+            Err(Error::new(TypeMismatch::SplatList(Type::Integer)))
+        );
+    }
+
+    #[test]
+    fn concat() {
+        let x = Object::from(vec![1, 2]);
+        let y = Object::from(vec![3]);
+        assert_eq!(x.concat(&y), Ok(Object::from(vec![1, 2, 3])));
+        assert_eq!(x, Object::from(vec![1, 2]));
+        assert_eq!(y, Object::from(vec![3]));
+        assert_eq!(
+            x.concat(&Object::new_range(Range::new(0, 2, 1).unwrap())),
+            Ok(Object::from(vec![1, 2, 0, 1]))
+        );
+        assert_eq!(
+            x.concat(&Object::from("a")),
+            Err(Error::new(TypeMismatch::SplatList(Type::String)))
+        );
+        assert_eq!(
+            Object::null().concat(&x),
+            Err(Error::new(TypeMismatch::SplatList(Type::Null)))
+        );
+    }
+
+    #[test]
+    fn set() {
+        let x = Object::map().insert("a", 1).insert("b", 2).build();
+        assert_eq!(
+            x.set("c", 3),
+            Ok(Object::map()
+                .insert("a", 1)
+                .insert("b", 2)
+                .insert("c", 3)
+                .build())
+        );
+        assert_eq!(
+            x.set("a", "z").map(|y| y.to_string()),
+            Ok("{a: \"z\", b: 2}".to_string())
+        );
+        assert_eq!(x, Object::map().insert("a", 1).insert("b", 2).build());
+        assert_eq!(
+            Object::from(vec![1]).set("a", 1),
+            Err(Error::new(TypeMismatch::SplatMap(Type::List)))
+        );
+    }
+
+    #[test]
+    fn lazy_map_view() {
+        let map = Object::map().insert("a", 1).insert("b", 2).build();
+
+        // Iterating over a map view doesn't build a list of all its elements
+        // up front
+        let items = Object::new_map_view(&map, MapPart::Items).unwrap();
+        assert_eq!(items.type_of(), Type::List);
+        let iter = Object::new_iterator(&items).unwrap();
+        assert!(matches!(iter.0, ObjV::MapIter(..)));
+        assert_eq!(
+            iter.next(),
+            Ok(Some(Object::from(vec![Object::from("a"), Object::from(1)])))
+        );
+        assert!(matches!(&items.0, ObjV::MapView(v) if !v.is_materialized()));
+        assert_eq!(
+            iter.next(),
+            Ok(Some(Object::from(vec![Object::from("b"), Object::from(2)])))
+        );
+        assert_eq!(iter.next(), Ok(None));
+
+        // Iterating over a map directly works the same way
+        let iter = Object::new_iterator(&map).unwrap();
+        assert!(matches!(iter.0, ObjV::MapIter(..)));
+
+        let keys = Object::new_map_view(&map, MapPart::Keys).unwrap();
+        assert_eq!(
+            Object::new_iterator(&keys).unwrap().next_indexed(),
+            Ok(Some((0, Object::from("a"))))
+        );
+        assert_eq!(keys.index(&Object::from(1)), Ok(Object::from("b")));
+        assert!(matches!(&keys.0, ObjV::MapView(v) if !v.is_materialized()));
+
+        let values = Object::new_map_view(&map, MapPart::Values).unwrap();
+        assert_eq!(values.get_list().unwrap().len(), 2);
+        assert!(matches!(&values.0, ObjV::MapView(v) if v.is_materialized()));
+
+        let mut out = Vec::new();
+        items.write_json(&mut out).unwrap();
+        assert_eq!(out, b"[[\"a\",1],[\"b\",2]]");
+
+        assert!(Object::new_map_view(&Object::new_list(), MapPart::Keys).is_none());
+    }
+
+    #[test]
+    fn lazy_range() {
+        let range = Range::new(0, 1_000_000_000_000, 1).unwrap();
+        let obj = Object::new_range(range);
+        let iter = Object::new_iterator(&obj).unwrap();
+        for i in 0..3 {
+            assert_eq!(iter.next_indexed(), Ok(Some((i, Object::from(i)))));
+        }
+        assert_eq!(obj.get_range().map(Range::len), Some(1_000_000_000_000));
+    }
+
+    #[test]
+    fn interning() {
+        let interned: Vec<_> = (0..10000)
+            .map(|_| Object::new_str_interned(String::from("some-tag")))
+            .collect();
+        let ptr = interned[0].str_ptr();
+        assert!(ptr.is_some());
+        assert!(interned.iter().all(|x| x.str_ptr() == ptr));
+
+        let natural: Vec<_> = (0..2)
+            .map(|_| Object::new_str_natural("some-tag"))
+            .collect();
+        assert_ne!(natural[0].str_ptr(), natural[1].str_ptr());
+        assert_ne!(natural[0].str_ptr(), ptr);
+
+        assert!(interned[0].user_eq(&natural[0]));
+        assert_eq!(interned[0].to_string(), natural[0].to_string());
+        assert_eq!(Object::from(1).str_ptr(), None);
+    }
 }
 
 #[cfg(test)]
 mod test_serialization {
-    use super::Object;
+    use std::rc::Rc;
+
+    use super::{Func, Object};
+    use crate::error::{Error, Value};
+    use crate::types::NativeClosure;
 
     fn check(x: Object) {
         assert_eq!(
-            x.serialize().map(|y| Object::deserialize(&y)).flatten(),
+            x.serialize().ok().and_then(|y| Object::deserialize(&y)),
             Some(x)
         )
     }
@@ -2016,4 +3554,17 @@ mod test_serialization {
             Object::from(false),
         ]));
     }
+
+    #[test]
+    fn native_closures() {
+        let closure: Rc<NativeClosure> = Rc::new(|_, _| Ok(Object::null()));
+        let x = Object::from(vec![
+            ("a", Object::from(1)),
+            (
+                "b",
+                Object::from(vec![Object::new_func(Func::from(closure))]),
+            ),
+        ]);
+        assert_eq!(x.serialize(), Err(Error::new(Value::Serialize)));
+    }
 }
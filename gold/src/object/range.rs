@@ -0,0 +1,118 @@
+//! Lazy integer ranges.
+
+use std::cell::OnceCell;
+
+use gc::{custom_trace, Finalize, Trace};
+use serde::{Deserialize, Serialize};
+
+use super::Object;
+use crate::types::{GcCell, List};
+
+/// A lazy range of integers, as returned by the `range` builtin.
+///
+/// To Gold code, a range is indistinguishable from a list. Its length and its
+/// elements are computed on demand, and iterating over it does not allocate.
+/// Operations that need a list proper (e.g. concatenation) materialize the
+/// elements, which happens at most once.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Range {
+    start: i64,
+    step: i64,
+    len: usize,
+
+    #[serde(skip)]
+    elements: OnceCell<GcCell<List>>,
+}
+
+unsafe impl Trace for Range {
+    custom_trace!(this, {
+        if let Some(elements) = this.elements.get() {
+            mark(elements);
+        }
+    });
+}
+
+impl Finalize for Range {}
+
+impl Range {
+    /// Construct the range from `start` (inclusive) to `stop` (exclusive) in
+    /// increments of `step`, which must be nonzero. Returns `None` if the
+    /// range is too long to be indexed.
+    pub fn new(start: i64, stop: i64, step: i64) -> Option<Self> {
+        let (start_w, stop_w, step_w) = (start as i128, stop as i128, step as i128);
+        let len = if step_w > 0 && start_w < stop_w {
+            (stop_w - start_w - 1) / step_w + 1
+        } else if step_w < 0 && start_w > stop_w {
+            (start_w - stop_w - 1) / -step_w + 1
+        } else {
+            0
+        };
+        Some(Self {
+            start,
+            step,
+            len: usize::try_from(len).ok()?,
+            elements: OnceCell::new(),
+        })
+    }
+
+    /// The number of elements.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// The element at a given index, if it exists.
+    pub fn get(&self, index: usize) -> Option<i64> {
+        // Elements are always within bounds, but intermediate values may not be
+        (index < self.len).then(|| (self.start as i128 + index as i128 * self.step as i128) as i64)
+    }
+
+    /// Return true if the given integer is an element.
+    pub fn contains(&self, value: i64) -> bool {
+        let offset = value as i128 - self.start as i128;
+        let step = self.step as i128;
+        offset % step == 0 && usize::try_from(offset / step).is_ok_and(|i| i < self.len)
+    }
+
+    /// The materialized list of elements.
+    pub fn elements(&self) -> &GcCell<List> {
+        self.elements.get_or_init(|| {
+            GcCell::new(
+                (0..self.len)
+                    .map(|i| Object::from(self.get(i).unwrap()))
+                    .collect(),
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Range;
+
+    #[test]
+    fn lengths() {
+        assert_eq!(Range::new(0, 10, 1).unwrap().len(), 10);
+        assert_eq!(Range::new(0, 10, 3).unwrap().len(), 4);
+        assert_eq!(Range::new(10, 0, -3).unwrap().len(), 4);
+        assert_eq!(Range::new(0, 10, -1).unwrap().len(), 0);
+        assert_eq!(Range::new(i64::MIN, i64::MAX, i64::MAX).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn elements() {
+        let r = Range::new(1, 10, 4).unwrap();
+        assert_eq!(r.get(0), Some(1));
+        assert_eq!(r.get(2), Some(9));
+        assert_eq!(r.get(3), None);
+
+        assert!(r.contains(5));
+        assert!(!r.contains(6));
+        assert!(!r.contains(13));
+        assert!(!r.contains(-3));
+
+        let r = Range::new(0, 1_000_000_000_000, 1).unwrap();
+        assert_eq!(r.get(999_999_999_999), Some(999_999_999_999));
+        assert!(r.contains(123_456_789_000));
+        assert!(r.elements.get().is_none());
+    }
+}
@@ -9,7 +9,7 @@ use serde::{Deserialize, Serialize};
 use super::Key;
 
 /// Convert a string to a displayable representation by adding escape sequences.
-fn escape(s: &str) -> String {
+pub(crate) fn escape(s: &str) -> String {
     let mut r = String::with_capacity(s.len());
     for c in s.chars() {
         match c {
@@ -0,0 +1,113 @@
+//! Lazy views of maps.
+
+use std::cell::OnceCell;
+
+use gc::{custom_trace, Finalize, Trace};
+use serde::{Deserialize, Serialize};
+
+use super::Object;
+use crate::types::{GcCell, Key, List, Map};
+
+/// The part of a map that a map view or map iterator produces.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) enum MapPart {
+    /// Keys, as strings.
+    Keys,
+
+    /// Values.
+    Values,
+
+    /// Key-value pairs, as two-element lists.
+    Items,
+}
+
+impl MapPart {
+    /// The element produced for a given map entry.
+    pub fn element(self, key: &Key, value: &Object) -> Object {
+        match self {
+            Self::Keys => Object::from(*key),
+            Self::Values => value.clone(),
+            Self::Items => Object::from(vec![Object::from(*key), value.clone()]),
+        }
+    }
+}
+
+/// A lazy list of the keys, values or items of a map, as returned by the
+/// `iter_keys`, `iter_values` and `iter_items` builtins.
+///
+/// Like [`super::Range`], a view is indistinguishable from a list to Gold
+/// code. Iterating over it produces one element at a time, while operations
+/// that need a list proper materialize the elements, which happens at most
+/// once.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) struct MapView {
+    map: GcCell<Map>,
+    part: MapPart,
+
+    #[serde(skip)]
+    elements: OnceCell<GcCell<List>>,
+}
+
+unsafe impl Trace for MapView {
+    custom_trace!(this, {
+        mark(&this.map);
+        if let Some(elements) = this.elements.get() {
+            mark(elements);
+        }
+    });
+}
+
+impl Finalize for MapView {}
+
+impl MapView {
+    /// Construct a view of the given part of a map.
+    pub fn new(map: GcCell<Map>, part: MapPart) -> Self {
+        Self {
+            map,
+            part,
+            elements: OnceCell::new(),
+        }
+    }
+
+    /// The underlying map.
+    pub fn map(&self) -> &GcCell<Map> {
+        &self.map
+    }
+
+    /// The part of the map this is a view of.
+    pub fn part(&self) -> MapPart {
+        self.part
+    }
+
+    /// The number of elements.
+    pub fn len(&self) -> usize {
+        self.map.borrow().len()
+    }
+
+    /// The element at a given index, if it exists.
+    pub fn get(&self, index: usize) -> Option<Object> {
+        self.map
+            .borrow()
+            .get_index(index)
+            .map(|(key, value)| self.part.element(key, value))
+    }
+
+    /// Return true if the elements have been materialized.
+    #[cfg(test)]
+    pub fn is_materialized(&self) -> bool {
+        self.elements.get().is_some()
+    }
+
+    /// The materialized list of elements.
+    pub fn elements(&self) -> &GcCell<List> {
+        self.elements.get_or_init(|| {
+            GcCell::new(
+                self.map
+                    .borrow()
+                    .iter()
+                    .map(|(key, value)| self.part.element(key, value))
+                    .collect(),
+            )
+        })
+    }
+}
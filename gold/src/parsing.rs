@@ -6,11 +6,11 @@ use num_bigint::ParseBigIntError;
 
 use nom::{
     branch::alt,
-    combinator::{map, map_res, opt, value, verify},
+    combinator::{map, map_res, opt, peek, value, verify},
     error::{ContextError, ErrorKind, FromExternalError, ParseError},
     multi::{many0, many1},
     sequence::{delimited, preceded, terminated, tuple},
-    Err as NomError, IResult, Parser as NomParser,
+    Err as NomError, IResult, InputLength, Parser as NomParser,
 };
 
 use crate::ast::high::*;
@@ -19,7 +19,7 @@ use crate::formatting::{
     AlignSpec, FloatFormatType, FormatSpec, FormatType, GroupingSpec, IntegerFormatType, SignSpec,
     StringAlignSpec, UppercaseSpec,
 };
-use crate::lexing::{CachedLexResult, CachedLexer, Lexer, TokenType};
+use crate::lexing::{CachedLexResult, CachedLexer, Lexer, TokenType, DEFAULT_MAX_DIGITS};
 use crate::types::{BinOp, EagerOp, Key, Res, UnOp};
 use crate::Object;
 
@@ -191,6 +191,8 @@ type PMap = Paren<MapElement>;
 
 type OpCons = fn(Tagged<Expr>, loc: Span) -> Transform;
 
+type Assignment = (Tagged<Binding>, Tagged<Expr>);
+
 type In<'a> = CachedLexer<'a>;
 type Out<'a, T> = IResult<In<'a>, T, SyntaxError>;
 
@@ -262,7 +264,8 @@ where
     U: Copy,
     ItemR: Debug,
 {
-    move |mut i: In<'a>| {
+    // The `in` operator is unambiguous inside delimiters
+    in_context(false, move |mut i: In<'a>| {
         let (j, initr) = initializer.parse(i)?;
         i = j;
 
@@ -333,7 +336,7 @@ where
                 }
             }
         }
-    }
+    })
 }
 
 /// Separated list with delimiters and optional trailing separator.
@@ -483,7 +486,7 @@ fn map_keyword<'a>(value: &'a str) -> impl Parser<'a, Tagged<&'a str>> {
 }
 
 /// List of keywords that must be avoided by the [`identifier`] parser.
-static KEYWORDS: [&'static str; 17] = [
+pub(crate) static KEYWORDS: [&'static str; 17] = [
     "for", "when", "if", "then", "else", "let", "in", "has", "true", "false", "null", "and", "or",
     "not", "as", "import", "fn",
 ];
@@ -505,6 +508,31 @@ fn map_identifier<'a>(input: In<'a>) -> Out<'a, Tagged<Key>> {
     map(map_name, |span| span.map(Key::new))(input)
 }
 
+/// Return true if `name` can be written as a bare map key: it is matched by
+/// [`map_identifier`], and is not a keyword that starts a map element.
+pub(crate) fn is_map_key(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+        && name != "for"
+        && name != "when"
+}
+
+/// Match an integer token, failing hard if it has too many digits. This
+/// protects against tiny documents that allocate huge integers.
+fn bounded_integer<'a>(input: In<'a>) -> Out<'a, Tagged<&'a str>> {
+    let start = input.skip_whitespace().position();
+    let (rest, span) = integer(input)?;
+    let digits = span.as_ref().chars().filter(char::is_ascii_digit).count();
+    if digits > input.max_digits() {
+        return Err(NomError::Failure(SyntaxError::new(
+            start,
+            Some(Syntax::NumberTooLong),
+        )));
+    }
+    Ok((rest, span))
+}
+
 /// Match a number.
 fn number<'a>(input: In<'a>) -> Out<'a, PExpr> {
     naked(alt((
@@ -514,7 +542,7 @@ fn number<'a>(input: In<'a>) -> Out<'a, PExpr> {
                 .parse::<f64>()
                 .map(|x| Expr::Literal(Object::from(x)).tag(&span))
         }),
-        map_res(integer, |span| {
+        map_res(bounded_integer, |span| {
             let text = span.as_ref().replace('_', "");
             let y = text
                 .parse::<i64>()
@@ -731,7 +759,7 @@ fn string_interp<'a>(input: In<'a>) -> Out<'a, StringElement> {
         delimited(
             terminated(string_dollar, fail(open_brace, TokenType::OpenBrace)),
             tuple((
-                fail(expression, SyntaxElement::Expression),
+                in_context(false, fail(expression, SyntaxElement::Expression)),
                 opt(preceded(colon, format_specifier)),
             )),
             fail(close_brace, TokenType::CloseBrace),
@@ -811,7 +839,7 @@ fn atomic<'a>(input: In<'a>) -> Out<'a, PExpr> {
 /// - singleton elements: `[2]`
 /// - splatted iterables: `[...x]`
 /// - conditional elements: `[if cond: @]`
-/// - iterated elements: `[for x in y: @]`
+/// - iterated elements: `[for x in y: @]` or `[for i, x in y: @]`
 fn list_element<'a>(input: In<'a>) -> Out<'a, PList> {
     alt((
         // Splat
@@ -827,6 +855,7 @@ fn list_element<'a>(input: In<'a>) -> Out<'a, PList> {
             tuple((
                 keyword("for"),
                 fail(binding, SyntaxElement::Binding),
+                opt(preceded(comma, fail(binding, SyntaxElement::Binding))),
                 preceded(
                     fail(keyword("in"), SyntaxElement::In),
                     fail(expression, SyntaxElement::Expression),
@@ -836,9 +865,14 @@ fn list_element<'a>(input: In<'a>) -> Out<'a, PList> {
                     fail(list_element, SyntaxElement::ListElement),
                 ),
             )),
-            |(start, binding, iterable, expr)| {
+            |(start, first, second, iterable, expr)| {
                 let span = start.span()..expr.outer();
+                let (index, binding) = match second {
+                    Some(binding) => (Some(first), binding),
+                    None => (None, first),
+                };
                 ListElement::Loop {
+                    index,
                     binding,
                     iterable: iterable.inner(),
                     element: Box::new(expr.inner()),
@@ -953,7 +987,7 @@ fn map_element_singleton<'a>(input: In<'a>) -> Out<'a, (PMap, bool)> {
 /// - singleton elements
 /// - splatted iterables: `{...x}`
 /// - conditional elements: `{if cond: @}`
-/// - iterated elements: `{for x in y: @}`
+/// - iterated elements: `{for x in y: @}` or `{for i, x in y: @}`
 fn map_element<'a>(input: In<'a>) -> Out<'a, (PMap, bool)> {
     alt((
         // Splat
@@ -969,6 +1003,7 @@ fn map_element<'a>(input: In<'a>) -> Out<'a, (PMap, bool)> {
             tuple((
                 map_keyword("for"),
                 fail(binding, SyntaxElement::Binding),
+                opt(preceded(comma, fail(binding, SyntaxElement::Binding))),
                 preceded(
                     fail(keyword("in"), SyntaxElement::In),
                     fail(expression, SyntaxElement::Expression),
@@ -978,9 +1013,14 @@ fn map_element<'a>(input: In<'a>) -> Out<'a, (PMap, bool)> {
                     fail(map_element, SyntaxElement::MapElement),
                 ),
             )),
-            |(start, binding, iterable, (expr, skip))| {
+            |(start, first, second, iterable, (expr, skip))| {
                 let span = start.span()..expr.outer();
+                let (index, binding) = match second {
+                    Some(binding) => (Some(first), binding),
+                    None => (None, first),
+                };
                 let ret = MapElement::Loop {
+                    index,
                     binding,
                     iterable: iterable.inner(),
                     element: Box::new(expr.inner()),
@@ -1041,7 +1081,7 @@ fn paren<'a>(input: In<'a>) -> Out<'a, PExpr> {
     map(
         tuple((
             open_paren,
-            fail(expression, SyntaxElement::Expression),
+            in_context(false, fail(expression, SyntaxElement::Expression)),
             fail(close_paren, TokenType::CloseParen),
         )),
         |(start, expr, end)| PExpr::Parenthesized(expr.inner().tag(start.span()..end.span())),
@@ -1071,11 +1111,8 @@ fn object_access<'a>(input: In<'a>) -> Out<'a, Tagged<Transform>> {
     map(
         tuple((dot, fail(identifier, SyntaxElement::Identifier))),
         |(dot, out)| {
-            Transform::BinOp(
-                BinOp::Eager(EagerOp::Index).tag(&dot),
-                Box::new(out.map(Object::from).map(Expr::Literal)),
-            )
-            .tag(dot.span()..out.span())
+            Transform::access(out.map(Object::from).map(Expr::Literal), &dot)
+                .tag(dot.span()..out.span())
         },
     )(input)
 }
@@ -1087,7 +1124,7 @@ fn object_index<'a>(input: In<'a>) -> Out<'a, Tagged<Transform>> {
     map(
         tuple((
             open_bracket,
-            fail(expression, SyntaxElement::Expression),
+            in_context(false, fail(expression, SyntaxElement::Expression)),
             fail(close_bracket, TokenType::CloseBracket),
         )),
         |(a, expr, b)| {
@@ -1273,7 +1310,9 @@ fn binops<'a>(
 ///
 /// The exponentiation operator, unlike practically every other operator, is
 /// right-associative, and asymmetric in its operands: it binds tighter than
-/// prefix operators on the left, but not on the right.
+/// prefix operators on the left, but not on the right. Thus `-2 ^ 2` is
+/// `-(2 ^ 2)`, as is conventional in mathematics, while `2 ^ -1` is allowed
+/// and means `2 ^ (-1)`.
 fn power<'a>(input: In<'a>) -> Out<'a, PExpr> {
     binops(
         binop(
@@ -1322,48 +1361,123 @@ fn sum<'a>(input: In<'a>) -> Out<'a, PExpr> {
     .parse(input)
 }
 
-/// Matches the inequality comparison precedence level.
-fn inequality<'a>(input: In<'a>) -> Out<'a, PExpr> {
-    lbinop(
-        alt((
-            map(less_eq, |x| (Transform::less_equal as OpCons).tag(&x)),
-            map(less, |x| (Transform::less as OpCons).tag(&x)),
-            map(greater_eq, |x| (Transform::greater_equal as OpCons).tag(&x)),
-            map(greater, |x| (Transform::greater as OpCons).tag(&x)),
+/// Matches a comparison operator.
+fn comparison_operator<'a>(input: In<'a>) -> Out<'a, Tagged<EagerOp>> {
+    alt((
+        map(less_eq, |x| EagerOp::LessEqual.tag(&x)),
+        map(less, |x| EagerOp::Less.tag(&x)),
+        map(greater_eq, |x| EagerOp::GreaterEqual.tag(&x)),
+        map(greater, |x| EagerOp::Greater.tag(&x)),
+        map(double_eq, |x| EagerOp::Equal.tag(&x)),
+        map(exclam_eq, |x| EagerOp::NotEqual.tag(&x)),
+    ))(input)
+}
+
+/// Matches the comparison precedence level.
+///
+/// Comparisons chain like in Python: `a < b == c` is equivalent to
+/// `a < b and b == c`, except that `b` is evaluated only once. A single
+/// comparison is an ordinary binary operator.
+fn comparison<'a>(input: In<'a>) -> Out<'a, PExpr> {
+    map(
+        tuple((
+            sum,
+            many0(tuple((
+                comparison_operator,
+                fail(sum, SyntaxElement::Operand),
+            ))),
         )),
-        sum,
-    )
-    .parse(input)
+        |(expr, mut links)| match links.len() {
+            0 => expr,
+            1 => {
+                let (op, rhs) = links.pop().unwrap();
+                let span = expr.outer()..rhs.outer();
+                let transform = Transform::BinOp(op.map(BinOp::Eager), Box::new(rhs.inner()));
+                PExpr::Naked(
+                    Expr::Transformed {
+                        operand: Box::new(expr.inner()),
+                        transform,
+                    }
+                    .tag(span),
+                )
+            }
+            _ => {
+                let span = expr.outer()..links.last().unwrap().1.outer();
+                let links = links
+                    .into_iter()
+                    .map(|(op, rhs)| (op, rhs.inner()))
+                    .collect();
+                PExpr::Naked(expr.inner().chain(links).tag(span))
+            }
+        },
+    )(input)
 }
 
-/// Matches the equality comparison precedence level.
-fn equality<'a>(input: In<'a>) -> Out<'a, PExpr> {
+/// Matches the `in` keyword as a binary operator. This doesn't match in the
+/// value of a let-binding, where `in` ends the binding instead (see
+/// [`let_assignment`]).
+fn in_operator<'a>(input: In<'a>) -> Out<'a, Tagged<&'a str>> {
+    let enabled = !input.no_in();
+    verify(keyword("in"), move |_| enabled)(input)
+}
+
+/// Run a parser with the `in` keyword reserved for ending a let-binding
+/// (`no_in` true) or available as a binary operator (`no_in` false). The
+/// previous state is restored afterwards.
+fn in_context<'a, O>(no_in: bool, mut parser: impl Parser<'a, O>) -> impl Parser<'a, O> {
+    move |input: In<'a>| {
+        let outer = input.no_in();
+        parser
+            .parse(input.with_no_in(no_in))
+            .map(|(i, out)| (i.with_no_in(outer), out))
+    }
+}
+
+/// Matches the contains precedence level: `x has y`, `y in x` or
+/// `y not in x`.
+fn contains<'a>(input: In<'a>) -> Out<'a, PExpr> {
     lbinop(
         alt((
-            map(double_eq, |x| (Transform::equal as OpCons).tag(&x)),
-            map(exclam_eq, |x| (Transform::not_equal as OpCons).tag(&x)),
+            map(keyword("has"), |x| (Transform::contains as OpCons).tag(&x)),
+            map(in_operator, |x| (Transform::is_in as OpCons).tag(&x)),
+            map(tuple((keyword("not"), keyword("in"))), |(x, y)| {
+                (Transform::not_in as OpCons).tag(x.span()..y.span())
+            }),
         )),
-        inequality,
+        comparison,
     )
     .parse(input)
 }
 
-/// Matches the contains precedence level.
-fn contains<'a>(input: In<'a>) -> Out<'a, PExpr> {
-    lbinop(
-        alt((map(keyword("has"), |x| {
-            (Transform::contains as OpCons).tag(&x)
-        }),)),
-        equality,
-    )
-    .parse(input)
+/// Matches the negation ('not') precedence level.
+///
+/// A leading 'not' binds looser than comparison and containment operators, so
+/// `not x in y` is `not (x in y)`. After other prefix operators, 'not' binds as
+/// tightly as those (see [`prefixed`]), so `-not x in y` is `(-(not x)) in y`.
+fn negation<'a>(input: In<'a>) -> Out<'a, PExpr> {
+    alt((
+        map(
+            tuple((keyword("not"), fail(negation, SyntaxElement::Operand))),
+            |(op, expr)| {
+                let span = op.span()..expr.outer();
+                PExpr::Naked(
+                    Expr::Transformed {
+                        operand: Box::new(expr.inner()),
+                        transform: Transform::UnOp(op.map(|_| Some(UnOp::LogicalNegate))),
+                    }
+                    .tag(span),
+                )
+            },
+        ),
+        contains,
+    ))(input)
 }
 
 /// Matches the conjunction ('and') precedence level.
 fn conjunction<'a>(input: In<'a>) -> Out<'a, PExpr> {
     lbinop(
         alt((map(keyword("and"), |x| (Transform::and as OpCons).tag(&x)),)),
-        contains,
+        negation,
     )
     .parse(input)
 }
@@ -1757,25 +1871,60 @@ fn function<'a>(input: In<'a>) -> Out<'a, PExpr> {
     ))(input)
 }
 
+/// Matches a final identifier binding: the word 'final' followed by an
+/// identifier. Since 'final' is not a reserved keyword, `let final = 1` still
+/// binds the name `final`.
+fn final_binding<'a>(input: In<'a>) -> Out<'a, Tagged<Binding>> {
+    map(tuple((keyword("final"), identifier)), |(kw, name)| {
+        Binding::Final(name).tag(kw.span()..name.span())
+    })(input)
+}
+
+/// Matches a binding pattern, an equals sign and an expression.
+fn let_assignment<'a>(input: In<'a>) -> Out<'a, Assignment> {
+    map(
+        tuple((
+            fail(alt((final_binding, binding)), SyntaxElement::Binding),
+            preceded(
+                fail(eq, TokenType::Eq),
+                in_context(true, fail(expression, SyntaxElement::Expression)),
+            ),
+        )),
+        |(binding, expr)| (binding, expr.inner()),
+    )(input)
+}
+
+/// Matches a single let statement in a let block: the `let` keyword followed
+/// by one or more comma-separated assignments.
+fn let_binding<'a>(input: In<'a>) -> Out<'a, (Tagged<&'a str>, Vec<Assignment>)> {
+    map(
+        tuple((
+            keyword("let"),
+            let_assignment,
+            many0(preceded(comma, let_assignment)),
+        )),
+        |(kw, first, rest)| {
+            let mut bindings = vec![first];
+            bindings.extend(rest);
+            (kw, bindings)
+        },
+    )(input)
+}
+
 /// Matches a let-binding block.
 ///
 /// This is an arbitrary (non-empty) sequence of let-bindings followed by the
 /// keyword 'in' and then an expression.
 ///
 /// A let-binding consists of the keyword 'let' followed by a binding, an equals
-/// symbol and an expression.
+/// symbol and an expression. Several such assignments may share a single
+/// 'let', separated by commas: `let a = 1, b = 2 in ...` is equivalent to
+/// `let a = 1 let b = 2 in ...`.
 fn let_block<'a>(input: In<'a>) -> Out<'a, PExpr> {
     map(
         tuple((
             // position,
-            many1(tuple((
-                keyword("let"),
-                fail(binding, SyntaxElement::Binding),
-                preceded(
-                    fail(eq, TokenType::Eq),
-                    fail(expression, SyntaxElement::Expression),
-                ),
-            ))),
+            many1(let_binding),
             preceded(
                 fail(keyword("in"), SyntaxElement::In),
                 fail(expression, SyntaxElement::Expression),
@@ -1785,10 +1934,7 @@ fn let_block<'a>(input: In<'a>) -> Out<'a, PExpr> {
             let span = bindings.first().unwrap().0.span()..expr.outer();
             PExpr::Naked(
                 Expr::Let {
-                    bindings: bindings
-                        .into_iter()
-                        .map(|(_, x, y)| (x, y.inner()))
-                        .collect(),
+                    bindings: bindings.into_iter().flat_map(|(_, x)| x).collect(),
                     expression: Box::new(expr.inner()),
                 }
                 .tag(span),
@@ -1805,10 +1951,10 @@ fn branch<'a>(input: In<'a>) -> Out<'a, PExpr> {
     map(
         tuple((
             keyword("if"),
-            fail(expression, SyntaxElement::Expression),
+            in_context(false, fail(expression, SyntaxElement::Expression)),
             preceded(
                 fail(keyword("then"), SyntaxElement::Then),
-                fail(expression, SyntaxElement::Expression),
+                in_context(false, fail(expression, SyntaxElement::Expression)),
             ),
             preceded(
                 fail(keyword("else"), SyntaxElement::Else),
@@ -1862,50 +2008,182 @@ fn import<'a>(input: In<'a>) -> Out<'a, TopLevel> {
             ),
             preceded(
                 fail(keyword("as"), SyntaxElement::As),
-                fail(binding, SyntaxElement::Binding),
+                fail(alt((final_binding, binding)), SyntaxElement::Binding),
             ),
         )),
         |((a, path, b), binding)| TopLevel::Import(path.tag(a.span()..b.span()), binding),
     )(input)
 }
 
+/// Parser that only succeeds at the end of input (save for whitespace and
+/// comments). This produces an error pointing at any trailing tokens after an
+/// otherwise complete file.
+fn end_of_input<'a>(input: In<'a>) -> Out<'a, ()> {
+    let input = input.skip_whitespace();
+    if input.input_len() == 0 {
+        Ok((input, ()))
+    } else {
+        Err(NomError::Failure(SyntaxError::error(
+            input,
+            SyntaxElement::EndOfInput,
+        )))
+    }
+}
+
+/// Matches the body of a module: one or more let bindings without a final
+/// expression, extending to the end of input. Such a file evaluates to a map
+/// of all the names it binds.
+fn module<'a>(input: In<'a>) -> Out<'a, Tagged<Expr>> {
+    let (rest, bindings) = many1(let_binding)(input)?;
+    if rest.skip_whitespace().input_len() != 0 {
+        // Backtrack to let the ordinary let block report any errors
+        return Err(NomError::Error(SyntaxError::error(
+            rest,
+            SyntaxElement::EndOfInput,
+        )));
+    }
+    let start = bindings.first().unwrap().0.span();
+    let bindings: Vec<_> = bindings.into_iter().flat_map(|(_, x)| x).collect();
+    let span = start..bindings.last().unwrap().1.span();
+    Ok((rest, Expr::module(bindings).tag(span)))
+}
+
+/// Matches the final expression of a file: either a single expression or a
+/// module body, extending to the end of input.
+fn body<'a>(input: In<'a>) -> Out<'a, Tagged<Expr>> {
+    terminated(
+        alt((
+            module,
+            map(fail(expression, SyntaxElement::Expression), PExpr::inner),
+        )),
+        end_of_input,
+    )(input)
+}
+
 /// Matches a file.
 ///
-/// A file consists of an arbitrary number of top-level statements followed by a
-/// single expression.
+/// A file consists of an arbitrary number of top-level statements followed by
+/// either a single expression or a module body.
 fn file<'a>(input: In<'a>) -> Out<'a, File> {
-    map(
-        tuple((many0(import), fail(expression, SyntaxElement::Expression))),
-        |(statements, expression)| File {
+    map(tuple((many0(import), body)), |(statements, expression)| {
+        File {
             statements,
-            expression: expression.inner(),
+            expression,
+        }
+    })(input)
+}
+
+/// Matches a single input to an interactive session. This is like a file,
+/// but also reports whether the body is a module.
+fn interactive<'a>(input: In<'a>) -> Out<'a, (File, bool)> {
+    map(
+        tuple((
+            many0(import),
+            terminated(
+                alt((
+                    map(module, |expr| (expr, true)),
+                    map(fail(expression, SyntaxElement::Expression), |expr| {
+                        (expr.inner(), false)
+                    }),
+                )),
+                end_of_input,
+            ),
+        )),
+        |(statements, (expression, is_module))| {
+            (
+                File {
+                    statements,
+                    expression,
+                },
+                is_module,
+            )
         },
     )(input)
 }
 
+/// Convert a parser error to the general error type.
+fn to_error(err: NomError<SyntaxError>) -> Error {
+    match err {
+        NomError::Incomplete(_) => Error::default(),
+        NomError::Error(e) | NomError::Failure(e) => e.to_error(),
+    }
+}
+
 /// Parse the input and return a File object.
 pub fn parse(input: &str) -> Res<File> {
+    parse_with_max_digits(input, DEFAULT_MAX_DIGITS)
+}
+
+/// Parse the input and return a [`File`] object, rejecting integer literals
+/// with more than `max_digits` digits.
+///
+/// [`parse`] uses a generous, but finite, default limit.
+pub fn parse_with_max_digits(input: &str, max_digits: usize) -> Res<File> {
+    let cache = Lexer::cache();
+    let lexer = Lexer::new(input)
+        .with_max_digits(max_digits)
+        .with_cache(&cache);
+    file(lexer).map_or_else(|err| Err(to_error(err)), |(_, node)| Ok(node))
+}
+
+/// Parse a single input to an interactive session and return a [`File`]
+/// object, together with a flag indicating whether it is a module body (a
+/// sequence of let bindings without a final expression).
+pub(crate) fn parse_interactive(input: &str) -> Res<(File, bool)> {
     let cache = Lexer::cache();
     let lexer = Lexer::new(input).with_cache(&cache);
-    file(lexer).map_or_else(
-        |err| match err {
-            NomError::Incomplete(_) => Err(Error::default()),
-            NomError::Error(e) | NomError::Failure(e) => Err(e.to_error()),
-        },
-        |(_, node)| Ok(node),
-    )
+    interactive(lexer).map_or_else(|err| Err(to_error(err)), |(_, node)| Ok(node))
+}
+
+/// Parse the input and return a [`File`] object, reporting as many errors as
+/// possible instead of stopping at the first one.
+///
+/// Recovery is limited: after a broken import statement, parsing resumes at
+/// the next line that isn't indented. Errors in the final expression are
+/// reported as with [`parse`].
+pub fn parse_all_errors(input: &str) -> Result<File, Vec<Error>> {
+    let cache = Lexer::cache();
+    let mut lexer = Lexer::new(input).with_cache(&cache);
+    let mut statements = Vec::new();
+    let mut errors = Vec::new();
+
+    while peek(keyword("import"))(lexer).is_ok() {
+        match import(lexer) {
+            Ok((rest, statement)) => {
+                statements.push(statement);
+                lexer = rest;
+            }
+            Err(err) => {
+                errors.push(to_error(err));
+                lexer = lexer.skip_whitespace().skip_to_unindented_line();
+            }
+        }
+    }
+
+    match body(lexer) {
+        Ok((_, expression)) if errors.is_empty() => Ok(File {
+            statements,
+            expression,
+        }),
+        Ok(_) => Err(errors),
+        Err(err) => {
+            errors.push(to_error(err));
+            Err(errors)
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::parse as parse_file;
+    use super::{parse_all_errors, parse_with_max_digits};
     use crate::ast::high::*;
     use crate::error::{Action, Span, Syntax, SyntaxElement as S, Taggable, Tagged};
     use crate::formatting::{
         AlignSpec, FormatSpec, FormatType, GroupingSpec, SignSpec, StringAlignSpec,
     };
     use crate::lexing::TokenType as T;
-    use crate::types::{Key, Res};
+    use crate::types::{EagerOp, Key, Res};
     use crate::{Error, Object};
 
     trait ToExpr {
@@ -2077,6 +2355,24 @@ mod tests {
                 .unwrap()
                 .expr(0..19))
         );
+
+        let too_long = |offset: u32| {
+            Err(Error::new(Syntax::NumberTooLong)
+                .with_locations_vec(vec![(Span::from(offset..offset), Action::Parse)]))
+        };
+        let long = "1".repeat(1_000_000);
+        assert_eq!(expr(&long), too_long(0));
+        assert_eq!(expr(&format!("[1, {}]", long)), too_long(4));
+        assert_eq!(
+            parse_with_max_digits("1_000", 4).map(|x| x.expression),
+            Ok(1000.expr(0..5))
+        );
+        assert_eq!(
+            parse_with_max_digits("1 + 10_000", 4)
+                .map(|x| x.expression)
+                .map_err(Error::unrender),
+            too_long(4)
+        );
     }
 
     #[test]
@@ -2270,6 +2566,7 @@ mod tests {
             Ok(Expr::List(vec![
                 1.lel(1),
                 ListElement::Loop {
+                    index: None,
                     binding: "x".bid(8),
                     iterable: "y".id(13),
                     element: "x".id(16).wrap(ListElement::Singleton).to_box(),
@@ -2280,6 +2577,18 @@ mod tests {
             .tag(0..21)),
         );
 
+        assert_eq!(
+            expr("[for i, x in y: x]"),
+            Ok(Expr::List(vec![ListElement::Loop {
+                index: Some("i".bid(5)),
+                binding: "x".bid(8),
+                iterable: "y".id(13),
+                element: "x".id(16).wrap(ListElement::Singleton).to_box(),
+            }
+            .tag(1..17)])
+            .tag(0..18)),
+        );
+
         assert_eq!(
             expr("[when f(x): x]"),
             Ok(Expr::List(vec![ListElement::Cond {
@@ -2304,6 +2613,7 @@ mod tests {
                 }
                 .tag(14..24),
                 ListElement::Loop {
+                    index: None,
                     binding: "x".bid(31),
                     iterable: "y".id(36),
                     element: "z".id(40).wrap(ListElement::Singleton).to_box(),
@@ -2324,6 +2634,7 @@ mod tests {
                 }
                 .tag(17..28),
                 ListElement::Loop {
+                    index: None,
                     binding: "x".bid(35),
                     iterable: "y".id(40),
                     element: "z".id(44).wrap(ListElement::Singleton).to_box(),
@@ -2555,6 +2866,7 @@ mod tests {
         assert_eq!(
             expr("{for [x,y] in z: x: y}"),
             Ok(Expr::Map(vec![MapElement::Loop {
+                index: None,
                 binding: Binding::List(
                     ListBinding::new(vec![
                         ListBindingElement::Binding {
@@ -2578,6 +2890,24 @@ mod tests {
             .tag(0..22)),
         );
 
+        assert_eq!(
+            expr("{for i, x in y: $i: x}"),
+            Ok(Expr::Map(vec![MapElement::Loop {
+                index: Some("i".bid(5)),
+                binding: "x".bid(8),
+                iterable: "y".id(13),
+                element: Box::new(
+                    MapElement::Singleton {
+                        key: "i".id(17),
+                        value: "x".id(20),
+                    }
+                    .tag(16..21)
+                ),
+            }
+            .tag(1..21)])
+            .tag(0..22)),
+        );
+
         assert_eq!(
             expr("{when f(x): z: y}"),
             Ok(Expr::Map(vec![MapElement::Cond {
@@ -2602,6 +2932,7 @@ mod tests {
                 }
                 .tag(18..32),
                 MapElement::Loop {
+                    index: None,
                     binding: "x".bid(39),
                     iterable: "y".id(44),
                     element: ("c".lit(48), "z".id(52)).mel().to_box(),
@@ -2636,6 +2967,7 @@ mod tests {
                 }
                 .tag(20..36),
                 MapElement::Loop {
+                    index: None,
                     binding: "x".bid(42),
                     iterable: "y".id(47),
                     element: MapElement::Singleton {
@@ -2667,6 +2999,24 @@ mod tests {
             .tag(0..16)),
         );
 
+        assert_eq!(
+            expr("let a = 1 in a in b"),
+            Ok(Expr::Let {
+                bindings: vec![("a".bid(4), 1.expr(8))],
+                expression: Box::new("a".id(13).is_in("b".id(18), 15..17).tag(13..19)),
+            }
+            .tag(0..19)),
+        );
+
+        assert_eq!(
+            expr("let a = (1 in b) in a"),
+            Ok(Expr::Let {
+                bindings: vec![("a".bid(4), 1.expr(9).is_in("b".id(14), 11..13).tag(9..15))],
+                expression: "a".id(20).to_box(),
+            }
+            .tag(0..21)),
+        );
+
         assert_eq!(
             expr("let a = 1 let b = 2 in a"),
             Ok(Expr::Let {
@@ -2676,6 +3026,46 @@ mod tests {
             .tag(0..24)),
         );
 
+        assert_eq!(
+            expr("let a = 1, b = 2 in a"),
+            Ok(Expr::Let {
+                bindings: vec![("a".bid(4), 1.expr(8)), ("b".bid(11), 2.expr(15)),],
+                expression: "a".id(20).to_box(),
+            }
+            .tag(0..21)),
+        );
+
+        assert_eq!(
+            expr("let a = 1, b = 2 let c = 3 in a"),
+            Ok(Expr::Let {
+                bindings: vec![
+                    ("a".bid(4), 1.expr(8)),
+                    ("b".bid(11), 2.expr(15)),
+                    ("c".bid(21), 3.expr(25)),
+                ],
+                expression: "a".id(30).to_box(),
+            }
+            .tag(0..31)),
+        );
+
+        assert_eq!(
+            expr("let final a = 1 in a"),
+            Ok(Expr::Let {
+                bindings: vec![(Binding::Final("a".key(10)).tag(4..11), 1.expr(14))],
+                expression: "a".id(19).to_box(),
+            }
+            .tag(0..20)),
+        );
+
+        assert_eq!(
+            expr("let final = 1 in final"),
+            Ok(Expr::Let {
+                bindings: vec![(Binding::Identifier("final".key(4..9)).tag(4..9), 1.expr(12))],
+                expression: "final".id(17..22).to_box(),
+            }
+            .tag(0..22)),
+        );
+
         assert_eq!(
             expr("let [a, b=1, ...] = c in [a, b]"),
             Ok(Expr::Let {
@@ -2891,6 +3281,31 @@ mod tests {
         );
     }
 
+    #[test]
+    fn modules() {
+        assert_eq!(
+            expr("let a = 1 let b = 2"),
+            Ok(Expr::Let {
+                bindings: vec![("a".bid(4), 1.expr(8)), ("b".bid(14), 2.expr(18))],
+                expression: Expr::Map(vec![
+                    MapElement::Singleton {
+                        key: "a".lit(4),
+                        value: "a".id(4),
+                    }
+                    .tag(4),
+                    MapElement::Singleton {
+                        key: "b".lit(14),
+                        value: "b".id(14),
+                    }
+                    .tag(14),
+                ])
+                .tag(4..19)
+                .to_box(),
+            }
+            .tag(0..19)),
+        );
+    }
+
     #[test]
     fn branching() {
         assert_eq!(
@@ -2910,7 +3325,7 @@ mod tests {
             expr("a.b"),
             Ok(
                 "a".id(0)
-                .index("b".lit(2), 1).tag(0..3)
+                .access("b".lit(2), 1).tag(0..3)
             ),
         };
 
@@ -2920,9 +3335,9 @@ mod tests {
             expr("a.b.c"),
             Ok("a"
                 .id(0)
-                .index("b".lit(2), 1)
+                .access("b".lit(2), 1)
                 .tag(0..3)
-                .index("c".lit(4), 3)
+                .access("c".lit(4), 3)
                 .tag(0..5)),
         );
 
@@ -2932,7 +3347,7 @@ mod tests {
                 .id(0)
                 .index("b".id(2), 1..4)
                 .tag(0..4)
-                .index("c".lit(5), 4)
+                .access("c".lit(5), 4)
                 .tag(0..6)),
         );
 
@@ -2940,7 +3355,7 @@ mod tests {
             expr("a.b[c]"),
             Ok("a"
                 .id(0)
-                .index("b".lit(2), 1)
+                .access("b".lit(2), 1)
                 .tag(0..3)
                 .index("c".id(4), 3..6)
                 .tag(0..6)),
@@ -3079,6 +3494,16 @@ mod tests {
             Ok(2.expr(1).pow(3.expr(3), 2).tag(1..4).neg(0).tag(0..4)),
         );
 
+        assert_eq!(
+            expr("-2 ^ 2"),
+            Ok(2.expr(1).pow(2.expr(5), 3).tag(1..6).neg(0).tag(0..6)),
+        );
+
+        assert_eq!(
+            expr("2 ^ -1"),
+            Ok(2.expr(0).pow(1.expr(5).neg(4).tag(4..6), 2).tag(0..6)),
+        );
+
         assert_eq!(
             expr("-2^-3"),
             Ok(2.expr(1)
@@ -3120,23 +3545,57 @@ mod tests {
                 .tag(0..22)),
         );
 
+        // Operators of equal precedence associate to the left
+        assert_eq!(
+            expr("1 - 2 - 3"),
+            Ok(1.expr(0)
+                .sub(2.expr(4), 2)
+                .tag(0..5)
+                .sub(3.expr(8), 6)
+                .tag(0..9)),
+        );
+
+        assert_eq!(
+            expr("16 / 2 / 2"),
+            Ok(16
+                .expr(0..2)
+                .div(2.expr(5), 3)
+                .tag(0..6)
+                .div(2.expr(9), 7)
+                .tag(0..10)),
+        );
+
+        assert_eq!(
+            expr("1 - (2 - 3)"),
+            Ok(1.expr(0)
+                .sub(2.expr(5).sub(3.expr(9), 7).tag(5..10), 2)
+                .tag(0..11)),
+        );
+
         assert_eq!(expr("1 < 2"), Ok(1.expr(0).lt(2.expr(4), 2).tag(0..5)),);
 
         assert_eq!(
             expr("1 > 2 <= 3 >= 4 == 5 != 6"),
             Ok(1.expr(0)
-                .gt(2.expr(4), 2)
-                .tag(0..5)
-                .lte(3.expr(9), 6..8)
-                .tag(0..10)
-                .gte(4.expr(14), 11..13)
-                .tag(0..15)
-                .equal(5.expr(19), 16..18)
-                .tag(0..20)
-                .not_equal(6.expr(24), 21..23)
+                .chain(vec![
+                    (EagerOp::Greater.tag(2), 2.expr(4)),
+                    (EagerOp::LessEqual.tag(6..8), 3.expr(9)),
+                    (EagerOp::GreaterEqual.tag(11..13), 4.expr(14)),
+                    (EagerOp::Equal.tag(16..18), 5.expr(19)),
+                    (EagerOp::NotEqual.tag(21..23), 6.expr(24)),
+                ])
                 .tag(0..25)),
         );
 
+        assert_eq!(
+            expr("(1 < 2) < 3"),
+            Ok(1.expr(1)
+                .lt(2.expr(5), 3)
+                .tag(1..6)
+                .lt(3.expr(10), 8)
+                .tag(0..11)),
+        );
+
         assert_eq!(
             expr("1 and 2 or 3"),
             Ok(1.expr(0)
@@ -3146,6 +3605,50 @@ mod tests {
                 .tag(0..12)),
         );
 
+        assert_eq!(
+            expr("x not in y"),
+            Ok("x".id(0).not_in("y".id(9), 2..8).tag(0..10)),
+        );
+
+        assert_eq!(
+            expr("x in y"),
+            Ok("x".id(0).is_in("y".id(5), 2..4).tag(0..6)),
+        );
+
+        assert_eq!(
+            expr("not x in y"),
+            Ok("x"
+                .id(4)
+                .is_in("y".id(9), 6..8)
+                .tag(4..10)
+                .not(0..3)
+                .tag(0..10)),
+        );
+
+        assert_eq!(
+            expr("not x not in y and z"),
+            Ok("x"
+                .id(4)
+                .not_in("y".id(13), 6..12)
+                .tag(4..14)
+                .not(0..3)
+                .tag(0..14)
+                .and("z".id(19), 15..18)
+                .tag(0..20)),
+        );
+
+        assert_eq!(
+            expr("-not x in y"),
+            Ok("x"
+                .id(5)
+                .not(1..4)
+                .tag(1..6)
+                .neg(0)
+                .tag(0..6)
+                .is_in("y".id(10), 7..9)
+                .tag(0..11)),
+        );
+
         assert_eq!(
             expr("2 // 2 * 2"),
             Ok(2.expr(0)
@@ -3280,12 +3783,55 @@ mod tests {
     //     };
     // }
 
+    #[test]
+    fn multiple_errors() {
+        // Collect the message and location of each error
+        let errs = |input: &str| -> Vec<(String, String)> {
+            parse_all_errors(input)
+                .unwrap_err()
+                .into_iter()
+                .map(|e| {
+                    let e = e.render(Some(input));
+                    let lines: Vec<_> = e.rendered().unwrap().lines().collect();
+                    (lines[0].to_owned(), lines[lines.len() - 1].to_owned())
+                })
+                .collect()
+        };
+        let err = |msg: &str, loc: &str| {
+            (
+                format!("Error: {}", msg),
+                format!("while parsing at {}", loc),
+            )
+        };
+
+        assert_eq!(
+            errs("import \"a\" as\nimport b as c\n{}"),
+            vec![
+                err("expected binding pattern", "1:14"),
+                err("expected import path", "2:7"),
+            ]
+        );
+        assert_eq!(
+            errs("import \"a\" as\n  x\nimport \"b\" as\n1 +"),
+            vec![
+                err("expected binding pattern", "3:14"),
+                err("expected operand", "4:4"),
+            ]
+        );
+        assert_eq!(
+            parse_all_errors("import \"a\" as x\nx"),
+            parse_file("import \"a\" as x\nx").map_err(|e| vec![e])
+        );
+    }
+
     #[test]
     fn errors() {
         err!("let", 3, S::Binding);
         err!("let a", 5, T::Eq);
         err!("let a =", 7, S::Expression);
-        err!("let a = 1", 9, S::In);
+        err!("let a = 1 b", 9, S::In);
+        err!("let a = 1, in a", 10, S::Binding);
+        err!("let a = 1, b in a", 12, T::Eq);
         err!("let a = 1 in", 12, S::Expression);
 
         err!("if", 2, S::Expression);
@@ -3391,6 +3937,10 @@ mod tests {
         err!("import \"path\" as", 16, S::Binding);
         err!("import \"path\" as y", 18, S::Expression);
 
+        err!("1 2", 2, S::EndOfInput);
+        err!("[1], [2]", 3, S::EndOfInput);
+        err!("let a = 1 in a b", 15, S::EndOfInput);
+
         // errl!("let [x, ..., y, ...] = z in 2", 16..19, Syntax::MultiSlurp);
         // errl!(
         //     "let {x, ...a, y, ...b} = z in 2",
@@ -1,5 +1,6 @@
 use std::fmt::{Debug, Display};
 use std::hash::Hash;
+use std::rc::Rc;
 
 use gc::custom_trace;
 use indexmap::{map::Iter, IndexMap};
@@ -20,6 +21,9 @@ pub type Key = GlobalSymbol;
 pub type List = Vec<Object>;
 
 /// Type used for mapping of strings (that is, [`Key`]) to objects.
+///
+/// Maps preserve insertion order. Overwriting an existing key keeps its
+/// original position, and removing a key preserves the order of the others.
 pub type Map = OrderedMap<Key, Object>;
 
 pub type NativeFunction = fn(&List, Option<&Map>) -> Result<Object, Error>;
@@ -35,21 +39,73 @@ pub type Res<T> = Result<T, Error>;
 pub struct Builtin {
     func: NativeFunction,
     name: Key,
+
+    /// If set, calling this builtin with at least one but fewer than this
+    /// many positional arguments (and no keyword arguments) returns a
+    /// partially applied function instead of calling it.
+    curried: Option<usize>,
+
+    /// Minimal and maximal (if any) number of positional arguments.
+    params: (usize, Option<usize>),
 }
 
 impl Builtin {
     pub fn new(func: NativeFunction, name: Key) -> Builtin {
-        Builtin { func, name }
+        Builtin {
+            func,
+            name,
+            curried: None,
+            params: (0, None),
+        }
+    }
+
+    /// Set the number of positional parameters this builtin accepts.
+    pub fn params(self, min: usize, max: Option<usize>) -> Builtin {
+        Builtin {
+            params: (min, max),
+            ..self
+        }
+    }
+
+    /// Enable partial application for this builtin.
+    pub fn curried(self, count: usize) -> Builtin {
+        Builtin {
+            curried: Some(count),
+            ..self
+        }
     }
 
     pub fn call(&self, args: &List, kwargs: Option<&Map>) -> Result<Object, Error> {
-        (self.func)(args, kwargs)
+        let no_kwargs = kwargs.map(|kw| kw.len() == 0).unwrap_or(true);
+        match self.curried {
+            Some(count) if no_kwargs && !args.is_empty() && args.len() < count => {
+                let this = *self;
+                let bound = args.clone();
+                let closure: Rc<NativeClosure> = Rc::new(move |rest, kwargs| {
+                    let mut args = bound.clone();
+                    args.extend_from_slice(rest);
+                    this.call(&args, kwargs)
+                });
+                Ok(Object::new_func(closure))
+            }
+            _ => (self.func)(args, kwargs),
+        }
     }
 
     pub fn name(&self) -> Key {
         self.name
     }
 
+    /// The minimal and maximal (if any) number of positional arguments.
+    /// Curried builtins accept a single argument, in which case they return
+    /// a partially applied function.
+    pub fn arity(&self) -> (usize, Option<usize>) {
+        match (self.curried, self.params) {
+            (Some(_), (min, max)) => (min.min(1), max),
+            (None, params) => params,
+        }
+    }
+
     pub fn native_callable(&self) -> &NativeFunction {
         &self.func
     }
@@ -187,6 +243,10 @@ impl<K, V> OrderedMap<K, V> {
     pub fn iter(&self) -> Iter<'_, K, V> {
         self.0.iter()
     }
+
+    pub fn get_index(&self, index: usize) -> Option<(&K, &V)> {
+        self.0.get_index(index)
+    }
 }
 
 impl<K: Hash + Eq, V> OrderedMap<K, V> {
@@ -194,8 +254,12 @@ impl<K: Hash + Eq, V> OrderedMap<K, V> {
         self.0.get(k)
     }
 
+    pub fn get_mut(&mut self, k: &K) -> Option<&mut V> {
+        self.0.get_mut(k)
+    }
+
     pub fn remove(&mut self, k: &K) -> Option<V> {
-        self.0.remove(k)
+        self.0.shift_remove(k)
     }
 
     pub fn insert(&mut self, key: K, value: V) -> Option<V> {
@@ -285,6 +349,9 @@ pub enum EagerOp {
     /// Index or subscripting operator
     Index,
 
+    /// Attribute access operator (dot syntax)
+    Access,
+
     /// Exponentiation
     Power,
 
@@ -323,12 +390,19 @@ pub enum EagerOp {
 
     /// Containment
     Contains,
+
+    /// Reverse containment
+    In,
+
+    /// Negated reverse containment
+    NotIn,
 }
 
 impl EagerOp {
     pub fn instruction(&self) -> Instruction {
         match self {
             Self::Index => Instruction::Index,
+            Self::Access => Instruction::Access,
             Self::Power => Instruction::Power,
             Self::Multiply => Instruction::Multiply,
             Self::IntegerDivide => Instruction::IntegerDivide,
@@ -342,6 +416,8 @@ impl EagerOp {
             Self::Equal => Instruction::Equal,
             Self::NotEqual => Instruction::NotEqual,
             Self::Contains => Instruction::Contains,
+            Self::In => Instruction::In,
+            Self::NotIn => Instruction::NotIn,
         }
     }
 }
@@ -350,6 +426,7 @@ impl Display for EagerOp {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::Index => f.write_str("subscript"),
+            Self::Access => f.write_str("attribute access"),
             Self::Power => f.write_str("^"),
             Self::Multiply => f.write_str("*"),
             Self::IntegerDivide => f.write_str("//"),
@@ -363,6 +440,8 @@ impl Display for EagerOp {
             Self::Equal => f.write_str("=="),
             Self::NotEqual => f.write_str("!="),
             Self::Contains => f.write_str("in"),
+            Self::In => f.write_str("in"),
+            Self::NotIn => f.write_str("not in"),
         }
     }
 }
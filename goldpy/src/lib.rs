@@ -1,8 +1,9 @@
 use std::path::PathBuf;
 
 use pyo3::prelude::*;
+use pyo3::types::PyDict;
 
-use gold::{Object, PyImportConfig, Error};
+use gold::{Error, ImportConfig, Key, Object, PyFile, PyImportConfig};
 
 #[pyfunction]
 fn eval(x: String, resolver: PyImportConfig) -> PyResult<Object> {
@@ -15,15 +16,41 @@ fn eval_raw(x: String) -> PyResult<Object> {
 }
 
 #[pyfunction]
-fn eval_file(x: String) -> Object {
-    gold::eval_file(&PathBuf::from(x)).map_err(Error::to_py).unwrap()
+#[pyo3(signature = (source, /, **bindings))]
+fn eval_str(source: String, bindings: Option<&Bound<'_, PyDict>>) -> PyResult<Object> {
+    let mut objects = Vec::new();
+    if let Some(bindings) = bindings {
+        for (key, value) in bindings.iter() {
+            objects.push((
+                Key::new(key.extract::<String>()?),
+                value.extract::<Object>()?,
+            ));
+        }
+    }
+    gold::eval_with_bindings(source.as_str(), &ImportConfig::default(), objects)
+        .map_err(Error::to_py)
+}
+
+#[pyfunction]
+fn eval_file(x: String) -> PyResult<Object> {
+    gold::eval_file(&PathBuf::from(x)).map_err(Error::to_py)
+}
+
+#[pyfunction]
+fn parse(x: String) -> PyResult<PyFile> {
+    gold::parse(x.as_str())
+        .map(PyFile::from)
+        .map_err(Error::to_py)
 }
 
 #[pymodule]
 fn goldpy<'py>(_py: Python<'py>, m: &Bound<'py, PyModule>) -> PyResult<()> {
+    m.add_class::<PyFile>()?;
     m.add_class::<PyImportConfig>()?;
     m.add_function(wrap_pyfunction!(eval, m)?)?;
     m.add_function(wrap_pyfunction!(eval_raw, m)?)?;
+    m.add_function(wrap_pyfunction!(eval_str, m)?)?;
     m.add_function(wrap_pyfunction!(eval_file, m)?)?;
+    m.add_function(wrap_pyfunction!(parse, m)?)?;
     Ok(())
 }
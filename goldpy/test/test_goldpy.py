@@ -1,3 +1,5 @@
+import pytest
+
 import goldpy
 
 
@@ -36,3 +38,41 @@ def test_importer():
     assert goldpy.eval('import "test" as {msg}\nmsg', importer) == 'you imported test'
     assert goldpy.eval('import "manifold" as {msg}\nmsg', importer) == 'you imported manifold'
     assert goldpy.eval('import "std" as {info}\ninfo.distribution', importer) == 'gold'
+
+
+def test_parse():
+    file = goldpy.parse('import "a" as x\nimport "b" as {y}\n{x: x, y: y}')
+    assert file.imports == ['a', 'b']
+    assert str(file) == str(goldpy.parse(str(file)))
+
+    assert goldpy.parse('1 + 2').imports == []
+
+
+def test_eval_str():
+    assert goldpy.eval_str('{a: [1, 2]}') == {'a': [1, 2]}
+    assert goldpy.eval_str('{a: x, b: y}', x=1.5, y=None) == {'a': 1.5, 'b': None}
+    assert goldpy.eval_str('x + 1', x=1) == 2
+
+    f = goldpy.eval_str('fn (x) x + y', y=2)
+    assert f(1) == 3
+
+    with pytest.raises(NameError):
+        goldpy.eval_str('x')
+
+
+def test_eval_file(tmp_path):
+    path = tmp_path / 'test.gold'
+    path.write_text('{a: [1, true, "x"]}')
+    assert goldpy.eval_file(str(path)) == {'a': [1, True, 'x']}
+
+    with pytest.raises(OSError):
+        goldpy.eval_file(str(tmp_path / 'missing.gold'))
+
+
+def test_python_callables():
+    assert goldpy.eval_str('f(1, 2)', f=lambda x, y: x + y) == 3
+    assert goldpy.eval_str('f(x: 1)', f=lambda x: [x, x]) == [1, 1]
+    assert goldpy.eval_str('map(f, [1, 2])', f=str) == ['1', '2']
+
+    with pytest.raises(Exception, match='ZeroDivisionError'):
+        goldpy.eval_str('f(1)', f=lambda x: x / 0)